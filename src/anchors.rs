@@ -0,0 +1,343 @@
+use anyhow::{anyhow, Error};
+use html5ever::parse_document;
+use html5ever::tendril::{StrTendril, TendrilSink};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use percent_encoding::percent_decode_str;
+use pulldown_cmark::{Event as MDEvent, Parser, Tag};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+/// GitHub prefixes the ids of rendered Markdown headings with this
+/// and rewrites fragments on the fly
+const GITHUB_ID_PREFIX: &str = "user-content-";
+
+/// Rules for turning a heading into an anchor (a "slug").
+/// Documentation generators slugify headings differently,
+/// so links to `#fragment`s only match if the right style is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnchorStyle {
+    /// GitHub (also used by Docusaurus and other tools based on `github-slugger`).
+    /// Punctuation gets removed and every space becomes a hyphen.
+    Github,
+    /// GitLab. Like GitHub, but consecutive hyphens get collapsed into one.
+    Gitlab,
+    /// Sphinx/docutils. Only ASCII letters and digits are kept, everything in
+    /// between becomes a single hyphen and leading digits are dropped.
+    Sphinx,
+}
+
+impl Default for AnchorStyle {
+    fn default() -> Self {
+        AnchorStyle::Github
+    }
+}
+
+impl FromStr for AnchorStyle {
+    type Err = Error;
+    fn from_str(style: &str) -> Result<Self, Self::Err> {
+        match style.to_lowercase().as_str() {
+            "github" | "docusaurus" => Ok(AnchorStyle::Github),
+            "gitlab" => Ok(AnchorStyle::Gitlab),
+            "sphinx" => Ok(AnchorStyle::Sphinx),
+            _ => Err(anyhow!("Unknown anchor style {}", style)),
+        }
+    }
+}
+
+impl AnchorStyle {
+    /// Turn a heading into the anchor the documentation generator would create
+    pub fn slugify(&self, heading: &str) -> String {
+        let heading = heading.trim().to_lowercase();
+        match self {
+            AnchorStyle::Github => slugify_github(&heading),
+            AnchorStyle::Gitlab => {
+                let slug = slugify_github(&heading);
+                let mut out = String::with_capacity(slug.len());
+                for c in slug.chars() {
+                    if !(c == '-' && out.ends_with('-')) {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+            AnchorStyle::Sphinx => {
+                // Note: docutils transliterates accented letters (e.g. `é` to `e`)
+                // before dropping non-ASCII characters, which isn't done here
+                let mut out = String::with_capacity(heading.len());
+                let mut separate = false;
+                for c in heading.chars().filter(char::is_ascii) {
+                    if c.is_ascii_alphanumeric() {
+                        if separate && !out.is_empty() {
+                            out.push('-');
+                        }
+                        separate = false;
+                        out.push(c);
+                    } else {
+                        separate = true;
+                    }
+                }
+                // Identifiers must not start with a digit
+                out.trim_start_matches(|c: char| c.is_ascii_digit() || c == '-')
+                    .to_string()
+            }
+        }
+    }
+}
+
+fn slugify_github(heading: &str) -> String {
+    heading
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Slugifies the headings of a document, making repeated slugs unique the
+/// way `github-slugger` does: the second `examples` becomes `examples-1`,
+/// the third `examples-2`, skipping slugs taken by other headings.
+/// GitLab does the same. Sphinx numbers repeated section ids differently
+/// (`id1`), so its slugs are left as they are.
+struct Slugger {
+    style: AnchorStyle,
+    /// How often each slug got repeated so far
+    occurrences: HashMap<String, usize>,
+}
+
+impl Slugger {
+    fn new(style: AnchorStyle) -> Self {
+        Self {
+            style,
+            occurrences: HashMap::new(),
+        }
+    }
+
+    fn slug(&mut self, heading: &str) -> String {
+        let slug = self.style.slugify(heading);
+        if self.style == AnchorStyle::Sphinx {
+            return slug;
+        }
+        let mut unique = slug.clone();
+        while self.occurrences.contains_key(&unique) {
+            let count = self.occurrences.entry(slug.clone()).or_default();
+            *count += 1;
+            unique = format!("{}-{}", slug, count);
+        }
+        self.occurrences.insert(unique.clone(), 0);
+        unique
+    }
+}
+
+/// Check if `fragment` (as found in a URL, i.e. possibly percent-encoded)
+/// is one of the given anchors
+pub(crate) fn contains_anchor(
+    anchors: &HashSet<String>,
+    fragment: &str,
+    ignore_case: bool,
+) -> bool {
+    let fragment = percent_decode_str(fragment).decode_utf8_lossy();
+    if ignore_case {
+        let fragment = fragment.to_lowercase();
+        anchors
+            .iter()
+            .any(|anchor| anchor.to_lowercase() == fragment)
+    } else {
+        anchors.contains(fragment.as_ref())
+    }
+}
+
+/// Collect all anchors of an HTML document: element ids, `name` attributes
+/// and slugified headings (for pages which add heading ids via JavaScript)
+pub(crate) fn html_anchors(input: &str, style: AnchorStyle) -> HashSet<String> {
+    let tendril = StrTendril::from(input);
+    let rc_dom = parse_document(RcDom::default(), Default::default()).one(tendril);
+
+    let mut anchors = HashSet::new();
+    walk_html_anchors(&mut anchors, &rc_dom.document, &mut Slugger::new(style));
+    anchors
+}
+
+fn walk_html_anchors(anchors: &mut HashSet<String>, node: &Handle, slugger: &mut Slugger) {
+    if let NodeData::Element {
+        ref name,
+        ref attrs,
+        ..
+    } = node.data
+    {
+        for attr in attrs.borrow().iter() {
+            if matches!(attr.name.local.as_ref(), "id" | "name") {
+                let value = attr.value.to_string();
+                if let Some(stripped) = value.strip_prefix(GITHUB_ID_PREFIX) {
+                    anchors.insert(stripped.to_string());
+                }
+                anchors.insert(value);
+            }
+        }
+        if matches!(name.local.as_ref(), "h1" | "h2" | "h3" | "h4" | "h5" | "h6") {
+            let mut text = String::new();
+            collect_text(&mut text, node);
+            anchors.insert(slugger.slug(&text));
+        }
+    }
+
+    for child in node.children.borrow().iter() {
+        walk_html_anchors(anchors, child, slugger);
+    }
+}
+
+fn collect_text(text: &mut String, node: &Handle) {
+    if let NodeData::Text { ref contents } = node.data {
+        text.push_str(&contents.borrow());
+    }
+    for child in node.children.borrow().iter() {
+        collect_text(text, child);
+    }
+}
+
+/// Collect all anchors of a Markdown document: slugified headings
+/// and anchors defined in embedded HTML
+pub(crate) fn markdown_anchors(input: &str, style: AnchorStyle) -> HashSet<String> {
+    let mut anchors = HashSet::new();
+    let mut slugger = Slugger::new(style);
+    let mut heading: Option<String> = None;
+    for event in Parser::new(input) {
+        match event {
+            MDEvent::Start(Tag::Heading(_)) => heading = Some(String::new()),
+            MDEvent::End(Tag::Heading(_)) => {
+                if let Some(text) = heading.take() {
+                    anchors.insert(slugger.slug(&text));
+                }
+            }
+            MDEvent::Text(text) | MDEvent::Code(text) => {
+                if let Some(heading) = heading.as_mut() {
+                    heading.push_str(&text);
+                }
+            }
+            MDEvent::Html(html) => anchors.extend(html_anchors(&html, style)),
+            _ => {}
+        }
+    }
+    anchors
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slugify_github() {
+        let style = AnchorStyle::Github;
+        assert_eq!(style.slugify("Hello, World!"), "hello-world");
+        assert_eq!(style.slugify("What's new in v2.0?"), "whats-new-in-v20");
+        assert_eq!(style.slugify("foo_bar - baz"), "foo_bar---baz");
+        assert_eq!(style.slugify("🚀 Launch"), "-launch");
+        assert_eq!(style.slugify("Über uns"), "über-uns");
+        // Headings only differing in punctuation share a slug
+        assert_eq!(style.slugify("Examples"), style.slugify("Examples!"));
+    }
+
+    #[test]
+    fn test_slugify_gitlab() {
+        let style = AnchorStyle::Gitlab;
+        assert_eq!(style.slugify("Hello, World!"), "hello-world");
+        assert_eq!(style.slugify("foo_bar - baz"), "foo_bar-baz");
+        assert_eq!(style.slugify("🚀 Launch"), "-launch");
+        assert_eq!(style.slugify("Examples"), style.slugify("Examples!"));
+    }
+
+    #[test]
+    fn test_slugify_sphinx() {
+        let style = AnchorStyle::Sphinx;
+        assert_eq!(style.slugify("Hello, World!"), "hello-world");
+        assert_eq!(style.slugify("1. Getting Started"), "getting-started");
+        assert_eq!(style.slugify("foo_bar - baz"), "foo-bar-baz");
+        assert_eq!(style.slugify("🚀 Launch"), "launch");
+        assert_eq!(style.slugify("Examples"), style.slugify("Examples!"));
+    }
+
+    #[test]
+    fn test_anchor_style_from_str() {
+        assert_eq!(
+            "GitHub".parse::<AnchorStyle>().unwrap(),
+            AnchorStyle::Github
+        );
+        assert_eq!(
+            "docusaurus".parse::<AnchorStyle>().unwrap(),
+            AnchorStyle::Github
+        );
+        assert!("asciidoc".parse::<AnchorStyle>().is_err());
+    }
+
+    #[test]
+    fn test_html_anchors() {
+        let input = r#"<html>
+            <h2 id="user-content-install">Install</h2>
+            <a name="legacy"></a>
+            <h3>Getting <code>started</code></h3>
+        </html>"#;
+        let anchors = html_anchors(input, AnchorStyle::Github);
+        assert!(anchors.contains("install"));
+        assert!(anchors.contains("user-content-install"));
+        assert!(anchors.contains("legacy"));
+        assert!(anchors.contains("getting-started"));
+    }
+
+    #[test]
+    fn test_markdown_anchors() {
+        let input = "# Kimchi\n\n## Usage `--help`\n\n<a id=\"custom\"></a>\n";
+        let anchors = markdown_anchors(input, AnchorStyle::Github);
+        assert!(anchors.contains("kimchi"));
+        assert!(anchors.contains("usage---help"));
+        assert!(anchors.contains("custom"));
+    }
+
+    #[test]
+    fn test_repeated_headings() {
+        let input = "# Examples\n\n## Examples!\n\n## Examples-1\n\n### Examples\n\n\
+            ## C++ & C#\n\n## C# / C++\n";
+        let anchors = markdown_anchors(input, AnchorStyle::Github);
+        let expected: HashSet<String> = [
+            "examples",
+            "examples-1",
+            "examples-1-1",
+            "examples-2",
+            "c--c",
+            "c--c-1",
+        ]
+        .iter()
+        .map(|anchor| anchor.to_string())
+        .collect();
+        assert_eq!(anchors, expected);
+
+        let input = "<h2>FAQ</h2><h2>FAQ</h2><h2 id=\"faq-2\">Other</h2>";
+        let anchors = html_anchors(input, AnchorStyle::Gitlab);
+        assert!(anchors.contains("faq") && anchors.contains("faq-1"));
+        assert!(anchors.contains("faq-2") && anchors.contains("other"));
+
+        let anchors = markdown_anchors("# Notes\n\n# Notes\n", AnchorStyle::Sphinx);
+        assert_eq!(anchors.len(), 1);
+        assert!(anchors.contains("notes"));
+    }
+
+    #[test]
+    fn test_contains_anchor() {
+        let anchors: HashSet<String> = [
+            "über-uns".to_string(),
+            "Install".to_string(),
+            "%+f".to_string(),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert!(contains_anchor(&anchors, "%C3%BCber-uns", false));
+        // Not a valid escape, so it's kept as is
+        assert!(contains_anchor(&anchors, "%+f", false));
+        assert!(!contains_anchor(&anchors, "install", false));
+        assert!(contains_anchor(&anchors, "install", true));
+    }
+}
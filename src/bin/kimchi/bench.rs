@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Extraction throughput measured by `--bench-extract`
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct ExtractBenchmark {
+    /// Number of inputs read, e.g. the files matching a glob
+    inputs: usize,
+    /// Number of unique links found
+    links: usize,
+    /// Time it took to read the inputs and extract their links, in seconds
+    duration: f64,
+    inputs_per_sec: f64,
+    links_per_sec: f64,
+}
+
+impl ExtractBenchmark {
+    pub(crate) fn new(inputs: usize, links: usize, duration: Duration) -> Self {
+        let secs = duration.as_secs_f64();
+        // An empty run can finish faster than the clock resolution
+        let rate = |count: usize| match secs > 0.0 {
+            true => count as f64 / secs,
+            false => 0.0,
+        };
+        Self {
+            inputs,
+            links,
+            duration: secs,
+            inputs_per_sec: rate(inputs),
+            links_per_sec: rate(links),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_extract_benchmark() {
+        let bench = ExtractBenchmark::new(10, 250, Duration::from_millis(500));
+        assert_eq!(bench.inputs_per_sec, 20.0);
+        assert_eq!(bench.links_per_sec, 500.0);
+        assert_eq!(
+            ExtractBenchmark::new(0, 0, Duration::default()).links_per_sec,
+            0.0
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{stdout, Stdout},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use crossterm::{
+    cursor::{Hide, Show},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+};
+use kimchi::Response;
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph},
+    Terminal,
+};
+
+/// Number of failures kept for the scrolling failure list
+const MAX_FAILURES: usize = 500;
+
+/// Number of hosts shown in the per-host progress
+const MAX_HOSTS: usize = 10;
+
+/// Minimum time between two redraws, so fast runs don't spend their time rendering
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct HostProgress {
+    checked: usize,
+    failed: usize,
+}
+
+/// Everything shown on the dashboard, kept apart from the terminal for testing
+#[derive(Debug, Default)]
+struct DashboardState {
+    total: usize,
+    checked: usize,
+    failed: usize,
+    excluded: usize,
+    hosts: HashMap<String, HostProgress>,
+    failures: VecDeque<String>,
+}
+
+impl DashboardState {
+    fn add(&mut self, response: &Response) {
+        self.checked += 1;
+        let host = response.uri.domain().unwrap_or("<local>").to_string();
+        let progress = self.hosts.entry(host).or_default();
+        progress.checked += 1;
+        if response.status.is_excluded() || response.status.is_unsupported() {
+            self.excluded += 1;
+        } else if !response.status.is_success() {
+            self.failed += 1;
+            progress.failed += 1;
+            if self.failures.len() == MAX_FAILURES {
+                self.failures.pop_front();
+            }
+            self.failures.push_back(response.to_string());
+        }
+    }
+
+    /// The hosts with the most checked links first
+    fn busiest_hosts(&self) -> Vec<(&String, &HostProgress)> {
+        let mut hosts: Vec<_> = self.hosts.iter().collect();
+        hosts.sort_by(|(a_host, a), (b_host, b)| {
+            b.checked.cmp(&a.checked).then_with(|| a_host.cmp(b_host))
+        });
+        hosts.truncate(MAX_HOSTS);
+        hosts
+    }
+}
+
+/// A live terminal dashboard for `--tui`, showing the overall progress,
+/// the progress per host and the latest failures.
+/// It takes over the terminal using the alternate screen until `finish`
+/// gets called or it is dropped.
+pub(crate) struct Dashboard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    state: DashboardState,
+    last_draw: Option<Instant>,
+}
+
+impl Dashboard {
+    pub(crate) fn new(total: usize) -> Result<Self> {
+        let mut stdout = stdout();
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+        let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        let mut dashboard = Self {
+            terminal,
+            state: DashboardState {
+                total,
+                ..DashboardState::default()
+            },
+            last_draw: None,
+        };
+        dashboard.draw()?;
+        Ok(dashboard)
+    }
+
+    pub(crate) fn update(&mut self, response: &Response) -> Result<()> {
+        self.state.add(response);
+        match self.last_draw {
+            Some(last_draw) if last_draw.elapsed() < REDRAW_INTERVAL => Ok(()),
+            _ => self.draw(),
+        }
+    }
+
+    /// Show the final state and hand the terminal back
+    pub(crate) fn finish(mut self) -> Result<()> {
+        self.draw()?;
+        self.restore()
+    }
+
+    fn restore(&mut self) -> Result<()> {
+        execute!(self.terminal.backend_mut(), LeaveAlternateScreen, Show)?;
+        Ok(())
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        self.last_draw = Some(Instant::now());
+        let state = &self.state;
+        self.terminal.draw(|f| {
+            let hosts = state.busiest_hosts();
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(hosts.len() as u16 + 2),
+                    Constraint::Min(3),
+                ])
+                .split(f.size());
+
+            let ratio = match state.total {
+                0 => 1.0,
+                total => (state.checked as f64 / total as f64).min(1.0),
+            };
+            let gauge = Gauge::default()
+                .block(Block::default().title("Progress").borders(Borders::ALL))
+                .gauge_style(Style::default().fg(Color::Green))
+                .label(format!("{}/{}", state.checked, state.total))
+                .ratio(ratio);
+            f.render_widget(gauge, chunks[0]);
+
+            let counts = Paragraph::new(Spans::from(vec![
+                Span::styled(
+                    format!("✅ {}  ", state.checked - state.failed - state.excluded),
+                    Style::default().fg(Color::Green),
+                ),
+                Span::styled(
+                    format!("🚫 {}  ", state.failed),
+                    Style::default().fg(Color::Red),
+                ),
+                Span::styled(
+                    format!("👻 {}", state.excluded),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+            .block(Block::default().title("Summary").borders(Borders::ALL));
+            f.render_widget(counts, chunks[1]);
+
+            let hosts: Vec<ListItem> = hosts
+                .into_iter()
+                .map(|(host, progress)| {
+                    ListItem::new(format!(
+                        "{}: {} checked, {} failed",
+                        host, progress.checked, progress.failed
+                    ))
+                })
+                .collect();
+            let hosts =
+                List::new(hosts).block(Block::default().title("Hosts").borders(Borders::ALL));
+            f.render_widget(hosts, chunks[2]);
+
+            // Show the latest failures that fit, newest at the bottom
+            let height = chunks[3].height.saturating_sub(2) as usize;
+            let failures: Vec<ListItem> = state
+                .failures
+                .iter()
+                .skip(state.failures.len().saturating_sub(height))
+                .map(|failure| {
+                    ListItem::new(failure.as_str()).style(Style::default().fg(Color::Red))
+                })
+                .collect();
+            let failures =
+                List::new(failures).block(Block::default().title("Failures").borders(Borders::ALL));
+            f.render_widget(failures, chunks[3]);
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        // Leaving the alternate screen twice is harmless
+        let _ = self.restore();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use kimchi::{collector::Input, test_utils::website, Status};
+
+    fn response(url: &str, status: Status) -> Response {
+        Response::new(website(url), status, Input::Stdin)
+    }
+
+    #[test]
+    fn test_dashboard_state() {
+        let mut state = DashboardState {
+            total: 3,
+            ..DashboardState::default()
+        };
+        state.add(&response(
+            "https://a.org/ok",
+            Status::Ok(http::StatusCode::OK),
+        ));
+        state.add(&response(
+            "https://a.org/gone",
+            Status::Failed(http::StatusCode::NOT_FOUND),
+        ));
+        state.add(&response("https://b.org", Status::Excluded));
+
+        assert_eq!((state.checked, state.failed, state.excluded), (3, 1, 1));
+        assert_eq!(state.failures.len(), 1);
+        let hosts = state.busiest_hosts();
+        assert_eq!(hosts[0].0, "a.org");
+        assert_eq!(
+            *hosts[0].1,
+            HostProgress {
+                checked: 2,
+                failed: 1
+            }
+        );
+    }
+}
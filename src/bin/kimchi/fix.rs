@@ -0,0 +1,116 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use kimchi::{collector::Input, replace_links, FileType, Response};
+use reqwest::Url;
+
+/// Permanently redirected and archived links per file, for `--fix`
+#[derive(Debug, Default)]
+pub(crate) struct Fixes {
+    files: HashMap<PathBuf, HashMap<Url, Url>>,
+}
+
+impl Fixes {
+    /// Remember the new URL of a link if it got moved permanently, or the
+    /// snapshot of a broken link found with `--wayback-fallback`.
+    /// Only links of local Markdown and HTML files can be fixed.
+    pub(crate) fn add(&mut self, response: &Response) {
+        let path = match &response.source {
+            Input::FsPath(path) if is_fixable(path) => path,
+            _ => return,
+        };
+        if let (kimchi::Uri::Website(url), Some(target)) = (
+            &response.uri,
+            response
+                .permanent_redirect()
+                .or_else(|| response.archived().cloned()),
+        ) {
+            self.files
+                .entry(path.clone())
+                .or_default()
+                .insert(url.clone(), target);
+        }
+    }
+
+    /// Rewrite the files and return a diff of the changes
+    pub(crate) fn apply(&self) -> Result<String> {
+        let mut paths: Vec<&PathBuf> = self.files.keys().collect();
+        paths.sort();
+        let mut diffs = String::new();
+        for path in paths {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Cannot read `{}` to fix its links", path.display()))?;
+            let (fixed, count) = replace_links(&content, FileType::from(path), &self.files[path]);
+            if count == 0 {
+                continue;
+            }
+            fs::write(path, &fixed)
+                .with_context(|| format!("Cannot write fixed links to `{}`", path.display()))?;
+            diffs.push_str(&diff(path, &content, &fixed));
+        }
+        Ok(diffs)
+    }
+}
+
+/// Compressed files are left alone
+fn is_fixable(path: &Path) -> bool {
+    matches!(FileType::from(path), FileType::Markdown | FileType::Html)
+        && path.extension().map_or(true, |ext| ext != "gz")
+}
+
+/// A diff of the changed lines. Replacing links never adds or removes lines,
+/// so the lines of both versions can be compared one by one.
+fn diff(path: &Path, old: &str, new: &str) -> String {
+    let mut diff = format!("--- {0}\n+++ {0}\n", path.display());
+    for (number, (old, new)) in old.lines().zip(new.lines()).enumerate() {
+        if old != new {
+            diff.push_str(&format!("@@ line {} @@\n-{}\n+{}\n", number + 1, old, new));
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use kimchi::{test_utils::website, Redirect, Status};
+
+    #[test]
+    fn test_fixes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("README.md");
+        fs::write(&path, "# Links\n\n[Docs](https://old.org/docs#usage)\n")?;
+
+        let mut response = Response::new(
+            website("https://old.org/docs#usage"),
+            Status::Ok(http::StatusCode::OK),
+            Input::FsPath(path.clone()),
+        );
+        response.redirects = vec![Redirect {
+            code: http::StatusCode::MOVED_PERMANENTLY,
+            url: Url::parse("https://new.org/docs")?,
+        }];
+        let mut fixes = Fixes::default();
+        fixes.add(&response);
+
+        let diff = fixes.apply()?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "# Links\n\n[Docs](https://new.org/docs#usage)\n"
+        );
+        assert!(diff.contains("@@ line 3 @@\n-[Docs](https://old.org/docs#usage)\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_fixable() {
+        assert!(is_fixable(Path::new("README.md")));
+        assert!(is_fixable(Path::new("index.html")));
+        assert!(!is_fixable(Path::new("README.md.gz")));
+        assert!(!is_fixable(Path::new("links.txt")));
+    }
+}
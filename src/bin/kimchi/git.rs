@@ -0,0 +1,126 @@
+use anyhow::{bail, Context, Result};
+use glob::glob_with;
+use kimchi::{collector::Input, FileType};
+use shellexpand::tilde;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Get all files changed relative to the given git ref,
+/// including uncommitted changes. Deleted files are skipped.
+/// Paths are canonicalized to make them comparable to the inputs.
+pub(crate) fn changed_files(reference: &str) -> Result<HashSet<PathBuf>> {
+    let toplevel = git(&["rev-parse", "--show-toplevel"])?;
+    let toplevel = PathBuf::from(toplevel.trim());
+
+    let changed = git(&["diff", "--name-only", "--diff-filter=d", reference, "--"])
+        .with_context(|| format!("Cannot get changes since `{}`", reference))?;
+
+    Ok(changed
+        .lines()
+        .filter_map(|name| toplevel.join(name).canonicalize().ok())
+        .collect())
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Cannot run `git`. Is it installed?")?;
+    if !output.status.success() {
+        bail!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Restrict the local file inputs to the changed files.
+/// Glob patterns get expanded to the changed Markdown, HTML, sitemap and
+/// notebook files they match. Explicitly given paths are kept if they changed,
+/// whatever their type: they get checked as plaintext like without `--since`,
+/// while a glob such as `**/*` would otherwise pull in every changed file.
+/// Other inputs (URLs, stdin) are not affected.
+pub(crate) fn filter_inputs(inputs: Vec<Input>, changed: &HashSet<PathBuf>) -> Result<Vec<Input>> {
+    let is_changed = |path: &Path| {
+        path.canonicalize()
+            .map(|path| changed.contains(&path))
+            .unwrap_or(false)
+    };
+
+    let mut filtered = vec![];
+    for input in inputs {
+        match input {
+            // Not restricted to the checkable file types, see above
+            Input::FsPath(ref path) => {
+                if is_changed(path) {
+                    filtered.push(input);
+                }
+            }
+            Input::FsGlob {
+                ref pattern,
+                ignore_case,
+            } => {
+                let mut match_opts = glob::MatchOptions::new();
+                match_opts.case_sensitive = !ignore_case;
+                for path in glob_with(&tilde(pattern), match_opts)? {
+                    let path = path?;
+                    let checkable = matches!(
+                        FileType::from(&path),
+                        FileType::Markdown
+                            | FileType::Html
+                            | FileType::Sitemap
+                            | FileType::Notebook
+                    );
+                    if checkable && is_changed(&path) {
+                        filtered.push(Input::from_path(path));
+                    }
+                }
+            }
+            _ => filtered.push(input),
+        }
+    }
+    Ok(filtered)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_filter_inputs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for name in &["changed.md", "unchanged.md", "changed.rs", "changed.ipynb"] {
+            File::create(dir.path().join(name))?;
+        }
+        let changed: HashSet<PathBuf> = ["changed.md", "changed.rs", "changed.ipynb"]
+            .iter()
+            .map(|name| dir.path().join(name).canonicalize().unwrap())
+            .collect();
+
+        // An explicitly given file is kept whatever its type
+        let inputs = vec![
+            Input::from_path(dir.path().join("unchanged.md")),
+            Input::from_path(dir.path().join("changed.rs")),
+            Input::new(dir.path().join("*").to_str().unwrap(), false),
+            Input::Stdin,
+        ];
+
+        let filtered = filter_inputs(inputs, &changed)?;
+        assert_eq!(
+            filtered,
+            vec![
+                Input::from_path(dir.path().join("changed.rs")),
+                Input::from_path(dir.path().join("changed.ipynb")),
+                Input::from_path(dir.path().join("changed.md")),
+                Input::Stdin,
+            ]
+        );
+        Ok(())
+    }
+}
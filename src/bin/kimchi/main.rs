@@ -1,26 +1,67 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use console::style;
+use futures::future::join_all;
 use headers::authorization::Basic;
 use headers::{Authorization, HeaderMap, HeaderMapExt, HeaderName};
 use indicatif::{ProgressBar, ProgressStyle};
 use options::Format;
-use regex::RegexSet;
-use std::{collections::HashSet, time::Duration};
-use std::{fs, str::FromStr};
+use regex::{Regex, RegexSet};
+use reqwest::Url;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashSet},
+    convert::TryFrom,
+    time::{Duration, Instant},
+};
+use std::{
+    fs,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    str::FromStr,
+};
 use structopt::StructOpt;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time;
+use tracing_subscriber::EnvFilter;
 
+mod bench;
+mod dashboard;
+mod fix;
+mod git;
 mod options;
+mod ordered;
 mod stats;
 
-use crate::options::{Config, KimchiOptions};
-use crate::stats::ResponseStats;
+use crate::bench::ExtractBenchmark;
+use crate::dashboard::Dashboard;
+use crate::fix::Fixes;
+use crate::options::{Config, KimchiOptions, OutputFile};
+use crate::ordered::ReorderBuffer;
+use crate::stats::{OwnHostLink, ResponseStats};
 
 use kimchi::{
-    collector::{self, Input},
-    Status,
+    collector::{self, CollectorOptions, Input},
+    extract::PlaintextFinder,
+    HttpVersion, LinkKind, RedirectPolicy, Request, Status, Uri,
 };
-use kimchi::{ClientBuilder, ClientPool, Response};
+use kimchi::{
+    AnchorStyle, Checkpoint, Client, ClientBuilder, ClientPool, Excludes, HostPolicy, LinkCache,
+    OAuth2Credentials, RequestRate, Response,
+};
+
+/// Number of concurrent requests per distinct host used by `--auto-concurrency`
+const CONCURRENCY_PER_HOST: usize = 8;
+
+/// Maximum number of responses held back by `--ordered-output`
+const ORDERED_OUTPUT_WINDOW: usize = 1024;
+
+/// Warn in verbose mode once fewer requests are left for a rate limited host
+const RATE_LIMIT_WARNING_THRESHOLD: u64 = 10;
+
+/// Known-good URLs requested by `--preflight`, unless others are configured
+const PREFLIGHT_URLS: &[&str] = &["https://www.example.com", "https://www.wikipedia.org"];
 
 /// A C-like enum that can be cast to `i32` and used as process exit code.
 enum ExitCode {
@@ -31,6 +72,7 @@ enum ExitCode {
     #[allow(unused)]
     UnexpectedFailure = 1,
     LinkCheckFailure = 2,
+    NoConnectivity = 3,
 }
 
 fn main() -> Result<()> {
@@ -43,6 +85,7 @@ fn main() -> Result<()> {
 }
 
 fn run_main() -> Result<i32> {
+    init_logging();
     let mut opts = KimchiOptions::from_args();
 
     // Load a potentially existing config file and merge it into the config from the CLI
@@ -51,6 +94,11 @@ fn run_main() -> Result<i32> {
     }
     let cfg = &opts.config;
 
+    if opts.print_config {
+        println!("{}", cfg.to_redacted_string(&cfg.format)?);
+        return Ok(ExitCode::Success as i32);
+    }
+
     let runtime = match cfg.threads {
         Some(threads) => {
             // We define our own runtime instead of the `tokio::main` attribute
@@ -63,44 +111,69 @@ fn run_main() -> Result<i32> {
         None => tokio::runtime::Runtime::new()?,
     };
 
-    runtime.block_on(run(cfg, opts.inputs()))
+    let inputs = match &cfg.since {
+        Some(reference) => git::filter_inputs(opts.inputs(), &git::changed_files(reference)?)?,
+        None => opts.inputs(),
+    };
+
+    runtime.block_on(run(cfg, inputs))
 }
 
-fn color_response(response: &Response) -> String {
+fn color_response(response: &Response, verbose_errors: bool) -> String {
+    let text = match verbose_errors {
+        true => format!("{:#}", response),
+        false => response.to_string(),
+    };
     let out = match response.status {
-        Status::Ok(_) => style(response).green().bright(),
-        Status::Redirected(_) => style(response),
-        Status::Excluded => style(response).dim(),
-        Status::Error(_) => style(response).yellow().bright(),
-        Status::Timeout(_) => style(response).yellow().bright(),
-        Status::Failed(_) => style(response).red().bright(),
+        Status::Ok(_) => style(text).green().bright(),
+        Status::Redirected(_) => style(text),
+        Status::Excluded => style(text).dim(),
+        Status::Error(_) => style(text).yellow().bright(),
+        Status::Tls(_) => style(text).red(),
+        Status::Timeout(_) => style(text).yellow().bright(),
+        Status::AuthRequired(_) => style(text).yellow(),
+        Status::Unsupported(_) => style(text).dim(),
+        Status::Failed(_)
+        | Status::Invalid(_)
+        | Status::HeaderMismatch(_)
+        | Status::ContentMismatch(_)
+        | Status::RedirectLoop(_) => style(text).red().bright(),
     };
     out.to_string()
 }
 
-fn show_progress(progress_bar: &Option<ProgressBar>, response: &Response, verbose: bool) {
+fn show_progress(progress_bar: &Option<ProgressBar>, response: &Response, cfg: &Config) {
     if let Some(pb) = progress_bar {
         pb.inc(1);
         pb.set_message(&response.to_string());
-        if verbose {
-            pb.println(color_response(response));
+        if cfg.verbose && !cfg.summary_only {
+            pb.println(color_response(response, cfg.verbose_errors));
         }
     } else {
-        if (response.status.is_success() || response.status.is_excluded()) && !verbose {
+        if cfg.summary_only
+            || (response.status.is_success() || response.status.is_excluded()) && !cfg.verbose
+        {
             return;
         }
-        println!("{}", color_response(response));
+        println!("{}", color_response(response, cfg.verbose_errors));
     }
 }
 
-fn fmt(stats: &ResponseStats, format: &Format) -> Result<String> {
+fn fmt(stats: &ResponseStats, format: &Format, emoji: bool, duration: Duration) -> Result<String> {
     Ok(match format {
         Format::String => stats.to_string(),
-        Format::Json => serde_json::to_string_pretty(&stats)?,
+        Format::Json => stats.to_json(duration)?,
+        Format::Markdown => stats.to_markdown(emoji)?,
+        Format::Prometheus => stats.to_prometheus(duration)?,
+        Format::Failures => stats.to_failure_list(),
     })
 }
 
 async fn run(cfg: &Config, inputs: Vec<Input>) -> Result<i32> {
+    // Fail before checking any links instead of losing the results at the end
+    check_output_files(&cfg.output)?;
+    let start = Instant::now();
+
     let mut headers = parse_headers(&cfg.headers)?;
     if let Some(auth) = &cfg.basic_auth {
         let auth_header = parse_basic_auth(&auth)?;
@@ -108,61 +181,365 @@ async fn run(cfg: &Config, inputs: Vec<Input>) -> Result<i32> {
     }
 
     let accepted = cfg.accept.clone().and_then(|a| parse_statuscodes(&a).ok());
+    let retry_on_status = match &cfg.retry_on_status {
+        Some(codes) => parse_statuscodes(codes)
+            .with_context(|| format!("Invalid status codes to retry: {}", codes))?,
+        None => HashSet::new(),
+    };
     let timeout = parse_timeout(cfg.timeout);
     let max_concurrency = cfg.max_concurrency;
     let method: reqwest::Method = reqwest::Method::from_str(&cfg.method.to_uppercase())?;
+    let anchor_style = AnchorStyle::from_str(&cfg.anchor_style)?;
+    let http_version = HttpVersion::from_str(&cfg.http_version)?;
     let include = RegexSet::new(&cfg.include)?;
     let exclude = RegexSet::new(&cfg.exclude)?;
+    let soft_404_patterns = match cfg.soft_404_pattern.is_empty() {
+        true => None,
+        false => Some(RegexSet::new(&cfg.soft_404_pattern)?),
+    };
+    let request_rate = match &cfg.rate_limit {
+        Some(rate) => Some(RequestRate::from_str(rate)?),
+        None => None,
+    };
+    let expect_content = match cfg.expect_content.is_empty() {
+        true => None,
+        false => Some(RegexSet::new(&cfg.expect_content)?),
+    };
+
+    let fallback_dns = cfg
+        .fallback_dns
+        .iter()
+        .map(|name_server| parse_name_server(name_server))
+        .collect::<Result<Vec<_>>>()?;
 
-    let client = ClientBuilder::default()
+    let mut builder = ClientBuilder::default();
+    if let Some(rewriter) = url_rewriter(&cfg.rewrite)? {
+        builder.url_rewriter(rewriter);
+    }
+    for scheme_timeout in &cfg.scheme_timeout {
+        let (scheme, timeout) = parse_scheme_timeout(scheme_timeout)?;
+        builder.timeout_for_scheme(scheme, timeout);
+    }
+    for host_policy in &cfg.host_policy {
+        let (pattern, policy) = parse_host_policy(host_policy)?;
+        builder.host_policy(pattern, policy);
+    }
+    for (pattern, headers) in &cfg.require_headers {
+        for header in headers {
+            let (name, value) = parse_required_header(header)?;
+            builder.require_header(pattern.as_str(), name, value);
+        }
+    }
+    for (pattern, texts) in &cfg.require_content {
+        for text in texts {
+            let text =
+                Regex::new(text).with_context(|| format!("Invalid required content `{}`", text))?;
+            builder.require_content(pattern.as_str(), text);
+        }
+    }
+    for (pattern, texts) in &cfg.forbid_content {
+        for text in texts {
+            let text = Regex::new(text)
+                .with_context(|| format!("Invalid forbidden content `{}`", text))?;
+            builder.forbid_content(pattern.as_str(), text);
+        }
+    }
+    for accept_url in &cfg.accept_url {
+        let (pattern, accepted) = parse_accept_url(accept_url)?;
+        builder.accept_for_url(pattern, accepted);
+    }
+    for expect_content_type in &cfg.expect_content_type {
+        let (pattern, content_types) = parse_expect_content_type(expect_content_type)?;
+        builder.expect_content_type(pattern, content_types);
+    }
+    for host_method in &cfg.host_method {
+        let (pattern, methods) = parse_host_method(host_method)?;
+        builder.methods_for_host(pattern, methods);
+    }
+    for path in &cfg.cacert {
+        builder.add_root_certificate(read_certificate(path)?);
+    }
+    if let Some(cert) = &cfg.client_cert {
+        builder.identity(read_identity(cert, cfg.client_key.as_deref())?);
+    }
+    let cache = match &cfg.cache {
+        Some(path) => Some(LinkCache::load(path)?),
+        None => None,
+    };
+    if let Some(cache) = &cache {
+        builder.cache(cache.clone());
+    }
+    if let Some(max_idle) = cfg.pool_max_idle_per_host {
+        builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(idle_timeout) = cfg.pool_idle_timeout {
+        builder.pool_idle_timeout(parse_timeout(idle_timeout));
+    }
+    if let Some(interval) = cfg.tcp_keepalive {
+        builder.tcp_keepalive(parse_timeout(interval));
+    }
+    if !cfg.uncheckable_scheme.is_empty() {
+        builder.uncheckable_schemes(cfg.uncheckable_scheme.clone());
+    }
+
+    let client = builder
         .includes(include)
         .excludes(exclude)
+        .exclude_hosts(cfg.exclude_hosts.clone())
+        .exclude_extensions(cfg.exclude_extensions.clone())
         .exclude_all_private(cfg.exclude_all_private)
         .exclude_private_ips(cfg.exclude_private)
         .exclude_link_local_ips(cfg.exclude_link_local)
         .exclude_loopback_ips(cfg.exclude_loopback)
         .max_redirects(cfg.max_redirects)
+        .redirect_policy(RedirectPolicy {
+            max_permanent: cfg.max_permanent_redirects,
+            max_temporary: cfg.max_temporary_redirects,
+        })
         .user_agent(cfg.user_agent.clone())
+        .browser_ua(cfg.browser_ua)
         .allow_insecure(cfg.insecure)
+        .http_version(http_version)
         .custom_headers(headers)
         .method(method)
         .timeout(timeout)
         .github_token(cfg.github_token.clone())
+        .gitlab_token(cfg.gitlab_token.clone())
+        .oauth2(oauth2_credentials(cfg)?)
+        .fallback_dns(fallback_dns)
         .scheme(cfg.scheme.clone())
         .accepted(accepted)
+        .retry_on_status(retry_on_status)
+        .detect_auth_required(cfg.detect_auth_required)
+        .check_mail_reachability(cfg.check_mail_reachability)
+        .wayback_fallback(cfg.wayback_fallback)
+        .check_image_types(cfg.check_image_types)
+        .max_total_retries(cfg.max_total_retries)
+        .soft_404_patterns(soft_404_patterns)
+        .expect_content(expect_content)
+        .min_content_length(cfg.min_content_length)
+        .check_anchors(cfg.check_anchors)
+        .anchor_style(anchor_style)
+        .anchors_ignore_case(cfg.anchors_ignore_case)
+        .default_host_policies(!cfg.no_default_host_policies)
+        .request_rate(request_rate)
+        .request_rate_per_host(cfg.rate_limit_per_host)
+        .respect_crawl_delay(cfg.respect_crawl_delay)
+        .aux_timeout(parse_timeout(cfg.aux_timeout))
         .build()?;
 
-    let links = collector::collect_links(
-        &inputs,
-        cfg.base_url.clone(),
-        cfg.skip_missing,
+    if cfg.preflight && !cfg.bench_extract {
+        let urls: Vec<String> = match cfg.preflight_url.is_empty() {
+            true => PREFLIGHT_URLS.iter().map(|url| url.to_string()).collect(),
+            false => cfg.preflight_url.clone(),
+        };
+        if !has_connectivity(&client, &urls, parse_timeout(cfg.aux_timeout)).await? {
+            eprintln!(
+                "No connectivity: none of the preflight URLs could be reached ({}). \
+                Skipping the link check.",
+                urls.join(", ")
+            );
+            return Ok(ExitCode::NoConnectivity as i32);
+        }
+    }
+    if !cfg.bench_extract {
+        // Invalid OAuth2 credentials would fail every link to the hosts
+        client
+            .authenticate()
+            .await
+            .context("OAuth2 authentication failed")?;
+    }
+
+    let plaintext_patterns = cfg
+        .plaintext_pattern
+        .iter()
+        .map(|pattern| Regex::new(pattern))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut skip_kinds = vec![];
+    if cfg.skip_images {
+        skip_kinds.push(LinkKind::Image);
+    }
+    if cfg.skip_assets {
+        skip_kinds.push(LinkKind::Asset);
+    }
+    let collector_opts = CollectorOptions {
+        skip_missing_inputs: cfg.skip_missing,
+        strict_encoding: cfg.strict_encoding,
         max_concurrency,
-    )
-    .await?;
+        max_fetch_concurrency: cfg.max_fetch_concurrency,
+        plaintext_finder: PlaintextFinder::new(plaintext_patterns, cfg.plaintext_pattern_only),
+        link_attributes: cfg.link_attribute.clone(),
+        skip_kinds,
+        exclude_link_text: match cfg.exclude_link_text.is_empty() {
+            true => None,
+            false => Some(RegexSet::new(&cfg.exclude_link_text)?),
+        },
+        check_anchors: cfg.check_anchors,
+        anchor_style,
+        anchors_ignore_case: cfg.anchors_ignore_case,
+        input_base_urls: cfg
+            .input_base_url
+            .iter()
+            .map(|input| parse_input_base_url(input))
+            .collect::<Result<_>>()?,
+        filter: Some(client.clone()),
+        crawl_depth: cfg.crawl_depth,
+        crawl_max_links: cfg.max_links,
+        excludes: client.excludes().clone(),
+        crawl_client: Some(client.clone()),
+    };
+    let collect_start = Instant::now();
+    let collected = collector::collect(inputs, cfg.base_url.clone(), &collector_opts).await?;
+    if cfg.bench_extract {
+        let bench = ExtractBenchmark::new(
+            collected.inputs_read,
+            collected.links.len(),
+            collect_start.elapsed(),
+        );
+        println!("{}", serde_json::to_string_pretty(&bench)?);
+        return Ok(ExitCode::Success as i32);
+    }
+    let links = collected.links;
+
+    // Linted before any links get skipped, as it needs no requests
+    let own_host_links = match Excludes::compile_host_globs(&cfg.own_host)
+        .context("Invalid host pattern in `own_host`")?
+    {
+        Some(own_hosts) => find_own_host_links(&links, &own_hosts),
+        None => vec![],
+    };
+
+    let (links, skipped_per_host) = match cfg.max_links_per_host {
+        Some(max_links) => cap_links_per_host(links, max_links),
+        None => (links, BTreeMap::new()),
+    };
+    let links = match cfg.max_links {
+        Some(max_links) => cap_links(links, max_links, cfg.truncate_links)?,
+        None => links,
+    };
+
+    // Links checked by an interrupted run don't get checked again
+    let mut checkpoint = match &cfg.resume {
+        Some(path) => Some(Checkpoint::open(path)?),
+        None => None,
+    };
+    let mut restored = vec![];
+    let links = match &checkpoint {
+        Some(checkpoint) => links
+            .into_iter()
+            .filter(|link| match checkpoint.restore(link) {
+                Some(response) => {
+                    restored.push(response);
+                    false
+                }
+                None => true,
+            })
+            .collect(),
+        None => links,
+    };
+    if let (Some(path), false) = (&cfg.resume, restored.is_empty()) {
+        eprintln!(
+            "Resuming from checkpoint `{}`: {} links were checked before",
+            path.display(),
+            restored.len()
+        );
+    }
+
+    let max_concurrency = if cfg.auto_concurrency {
+        auto_concurrency(&links, max_concurrency)
+    } else {
+        max_concurrency
+    };
 
-    let pb = if cfg.progress {
+    // The dashboard replaces all other progress output, but is only of use
+    // in an interactive terminal
+    let mut dashboard = match cfg.tui && console::user_attended() {
+        true => Some(Dashboard::new(links.len())?),
+        false => None,
+    };
+
+    // Progress bars only clutter CI logs
+    let pb = if dashboard.is_some() {
+        None
+    } else if cfg.progress && (cfg.force_progress || !is_ci(|var| std::env::var(var).ok())) {
         let bar =
-        ProgressBar::new(links.len() as u64).with_style(ProgressStyle::default_bar().template(
-            "{spinner:.red.bright} {pos}/{len:.dim} [{elapsed_precise}] {bar:25} {wide_msg}",
-        ));
+            ProgressBar::new(links.len() as u64).with_style(ProgressStyle::default_bar().template(
+                "{spinner:.red.bright} {pos}/{len:.dim} [{elapsed_precise}] {bar:25} {wide_msg}",
+            ));
         bar.enable_steady_tick(100);
         Some(bar)
     } else {
         None
     };
 
-    let (send_req, recv_req) = mpsc::channel(max_concurrency);
+    let checked = Arc::new(AtomicUsize::new(0));
+    let failed = Arc::new(AtomicUsize::new(0));
+    let heartbeat = match (&pb, cfg.heartbeat) {
+        (None, secs) if secs > 0 && dashboard.is_none() => Some(spawn_heartbeat(
+            Duration::from_secs(secs as u64),
+            links.len(),
+            checked.clone(),
+            failed.clone(),
+        )),
+        _ => None,
+    };
+
+    let delay = cfg.delay.map(Duration::from_millis);
+    // With a delay, a request only gets queued once the previous one was
+    // picked up, so requests can't pile up and leave in a burst
+    let queue_size = match delay {
+        Some(_) => 1,
+        None => max_concurrency,
+    };
+    let (send_req, recv_req) = mpsc::channel(queue_size);
     let (send_resp, mut recv_resp) = mpsc::channel(max_concurrency);
 
-    let mut stats = ResponseStats::new();
+    let mut stats = ResponseStats::new()
+        .strict(cfg.strict)
+        .fail_on_auth_required(cfg.fail_on_auth_required)
+        .fail_on_input_errors(cfg.fail_on_input_errors)
+        .fail_on_own_host_links(cfg.fail_on_own_host_links)
+        .verbose_errors(cfg.verbose_errors)
+        .max_failures(cfg.max_failures)
+        .max_failure_percent(cfg.max_failure_percent)
+        .host_summary(cfg.verbose || cfg.top_hosts.is_some(), cfg.top_hosts)
+        .required_hosts(
+            Excludes::compile_host_globs(&cfg.required_host)
+                .context("Invalid host pattern in `required_host`")?,
+        );
+    stats.add_input_errors(collected.input_errors);
+    stats.add_input_warnings(collected.input_warnings);
+    stats.add_skipped_per_host(skipped_per_host);
+    stats.add_own_host_links(own_host_links);
+
+    let mut links: Vec<Request> = links.into_iter().collect();
+    // Look up GitHub issues and pull requests in batches up front, so the
+    // checks below are answered from the cache
+    client.resolve_github_links(&links).await;
+    let mut reorder = match cfg.ordered_output {
+        true => {
+            // Sort to get the same order across runs
+            links.sort_by_key(|link| (link.source.to_string(), link.uri.to_string()));
+            Some(ReorderBuffer::new(&links, ORDERED_OUTPUT_WINDOW))
+        }
+        false => None,
+    };
+    let window = reorder.as_ref().map(ReorderBuffer::window);
 
     let bar = pb.clone();
     tokio::spawn(async move {
-        for link in links {
+        for (i, link) in links.into_iter().enumerate() {
+            if let Some(window) = &window {
+                window.acquire().await.unwrap().forget();
+            }
+            let permit = send_req.reserve().await.unwrap();
+            if let (Some(delay), true) = (delay, i > 0) {
+                time::sleep(delay).await;
+            }
             if let Some(pb) = &bar {
                 pb.set_message(&link.to_string());
             };
-            send_req.send(link).await.unwrap();
+            permit.send(link);
         }
     });
 
@@ -173,22 +550,81 @@ async fn run(cfg: &Config, inputs: Vec<Input>) -> Result<i32> {
         clients.listen().await;
     });
 
-    while let Some(response) = recv_resp.recv().await {
-        show_progress(&pb, &response, cfg.verbose);
+    let mut rate_limit_warnings = HashSet::new();
+    let mut fixes = Fixes::default();
+    for response in restored {
+        if cfg.fix {
+            fixes.add(&response);
+        }
         stats.add(response);
     }
+    while let Some(response) = recv_resp.recv().await {
+        let responses = match &mut reorder {
+            Some(reorder) => reorder.push(response),
+            None => vec![response],
+        };
+        for response in responses {
+            if let Some(checkpoint) = &mut checkpoint {
+                checkpoint.record(&response)?;
+            }
+            let response = stats.judge(response);
+            match &mut dashboard {
+                Some(dashboard) => dashboard.update(&response)?,
+                None => {
+                    show_progress(&pb, &response, cfg);
+                    if cfg.verbose {
+                        warn_rate_limit(&pb, &response, &mut rate_limit_warnings);
+                    }
+                }
+            }
+            checked.fetch_add(1, Ordering::Relaxed);
+            if !(response.status.is_success() || response.status.is_excluded()) {
+                failed.fetch_add(1, Ordering::Relaxed);
+            }
+            if cfg.fix {
+                fixes.add(&response);
+            }
+            stats.add(response);
+        }
+    }
+    if let Some(heartbeat) = heartbeat {
+        heartbeat.abort();
+    }
 
     // Note that print statements may interfere with the progress bar, so this
     // must go before printing the stats
     if let Some(pb) = &pb {
         pb.finish_with_message("Done");
     }
+    if let Some(dashboard) = dashboard {
+        dashboard.finish()?;
+    }
 
-    let stats_formatted = fmt(&stats, &cfg.format)?;
-    if let Some(output) = &cfg.output {
-        fs::write(output, stats_formatted).context("Cannot write status output to file")?;
-    } else {
-        println!("\n{}", stats_formatted);
+    let duration = start.elapsed();
+    if let (Some(cache), Some(path)) = (&cache, &cfg.cache) {
+        cache.save(path)?;
+    }
+    if cfg.fix {
+        print!("{}", fixes.apply()?);
+    }
+    if cfg.github_annotations {
+        print!("{}", stats.to_github_annotations());
+    }
+    if cfg.output.is_empty() {
+        println!("\n{}", fmt(&stats, &cfg.format, !cfg.no_emoji, duration)?);
+    }
+    for output in &cfg.output {
+        let format = output.format.as_ref().unwrap_or(&cfg.format);
+        let report = fmt(&stats, format, !cfg.no_emoji, duration)?;
+        fs::write(&output.path, report).with_context(|| {
+            format!("Cannot write status output to `{}`", output.path.display())
+        })?;
+    }
+    if let Some(mut checkpoint) = checkpoint {
+        match cfg.keep_checkpoint {
+            true => checkpoint.flush()?,
+            false => checkpoint.remove()?,
+        }
     }
 
     match stats.is_success() {
@@ -197,6 +633,216 @@ async fn run(cfg: &Config, inputs: Vec<Input>) -> Result<i32> {
     }
 }
 
+/// Whether any of the URLs can be reached. Only working or redirected links
+/// count, as e.g. an excluded link never got requested.
+async fn has_connectivity(client: &Client, urls: &[String], timeout: Duration) -> Result<bool> {
+    let responses = join_all(
+        urls.iter()
+            .map(|url| time::timeout(timeout, client.check(url.as_str()))),
+    )
+    .await;
+    for response in responses {
+        // Running out of time counts as unreachable
+        let response = match response {
+            Ok(response) => response?,
+            Err(_) => continue,
+        };
+        if matches!(response.status, Status::Ok(_) | Status::Redirected(_)) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Log to stderr, filtered by the `RUST_LOG` environment variable
+/// (e.g. `RUST_LOG=kimchi=debug`). Only warnings are shown by default.
+fn init_logging() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+/// Warn once per host when its rate limit is about to be exhausted
+fn warn_rate_limit(pb: &Option<ProgressBar>, response: &Response, warned: &mut HashSet<String>) {
+    let (rate_limit, host) = match (response.rate_limit(), response.uri.domain()) {
+        (Some(rate_limit), Some(host)) => (rate_limit, host),
+        _ => return,
+    };
+    if rate_limit.remaining >= RATE_LIMIT_WARNING_THRESHOLD || !warned.insert(host.to_string()) {
+        return;
+    }
+    let warning = style(format!(
+        "Rate limit for {} almost exhausted: {}",
+        host,
+        stats::format_rate_limit(&rate_limit)
+    ))
+    .yellow()
+    .to_string();
+    match pb {
+        Some(pb) => pb.println(warning),
+        None => eprintln!("{}", warning),
+    }
+}
+
+/// Environment variables set by common CI providers
+const CI_ENV_VARS: &[&str] = &["CI", "GITHUB_ACTIONS", "GITLAB_CI"];
+
+/// Detect if we're running on CI, given a lookup for environment variables
+fn is_ci<F: Fn(&str) -> Option<String>>(env: F) -> bool {
+    CI_ENV_VARS.iter().any(|var| match env(var) {
+        Some(value) => !value.is_empty() && value != "false" && value != "0",
+        None => false,
+    })
+}
+
+/// Periodically print how many links got checked to stderr
+fn spawn_heartbeat(
+    period: Duration,
+    total: usize,
+    checked: Arc<AtomicUsize>,
+    failed: Arc<AtomicUsize>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = time::interval_at(time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            eprintln!(
+                "Checked {}/{} links, {} failed so far",
+                checked.load(Ordering::Relaxed),
+                total,
+                failed.load(Ordering::Relaxed)
+            );
+        }
+    })
+}
+
+/// Make sure every output file is only written once
+fn check_output_files(outputs: &[OutputFile]) -> Result<()> {
+    let mut paths = HashSet::new();
+    for output in outputs {
+        if !paths.insert(&output.path) {
+            bail!(
+                "Output file `{}` is given more than once",
+                output.path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Enforce the `--max-links` limit before any request gets sent
+fn cap_links(
+    links: HashSet<Request>,
+    max_links: usize,
+    truncate: bool,
+) -> Result<HashSet<Request>> {
+    if links.len() <= max_links {
+        return Ok(links);
+    }
+    if !truncate {
+        bail!(
+            "Found {} links, which exceeds the limit of {} links. \
+            Raise `--max-links` or use `--truncate-links` to only check a subset.",
+            links.len(),
+            max_links
+        );
+    }
+    eprintln!(
+        "Warning: found {} links, only checking the first {} (see `--max-links`)",
+        links.len(),
+        max_links
+    );
+    // Sort to make the truncation deterministic
+    let mut links: Vec<Request> = links.into_iter().collect();
+    links.sort_by(|a, b| {
+        (a.uri.as_str(), a.source.to_string()).cmp(&(b.uri.as_str(), b.source.to_string()))
+    });
+    Ok(links.into_iter().take(max_links).collect())
+}
+
+/// Absolute links to the own hosts, sorted by input and line.
+/// Relative links resolved against a base URL on one of these hosts are fine.
+fn find_own_host_links(links: &HashSet<Request>, own_hosts: &RegexSet) -> Vec<OwnHostLink> {
+    let mut found: Vec<OwnHostLink> = links
+        .iter()
+        .filter(|link| !link.relative)
+        // As written in the input, before any rewriting
+        .filter_map(
+            |link| match link.rewritten_from.as_ref().unwrap_or(&link.uri) {
+                Uri::Website(url) if matches!(url.scheme(), "http" | "https") => {
+                    let host = url.host_str()?.trim_end_matches('.');
+                    if !own_hosts.is_match(host) {
+                        return None;
+                    }
+                    Some(OwnHostLink {
+                        input: link.source.clone(),
+                        line: link.line,
+                        link: url.to_string(),
+                    })
+                }
+                _ => None,
+            },
+        )
+        .collect();
+    found.sort_by(|a, b| {
+        (a.input.to_string(), a.line, &a.link).cmp(&(b.input.to_string(), b.line, &b.link))
+    });
+    found
+}
+
+/// Enforce the `--max-links-per-host` limit before any request gets sent.
+/// Only the first links of every host (sorted by URL) get checked; a link
+/// used in several inputs counts once. Returns the number of skipped links
+/// per host. Mail addresses don't count, as they aren't requested from
+/// their host.
+fn cap_links_per_host(
+    links: HashSet<Request>,
+    max_links: usize,
+) -> (HashSet<Request>, BTreeMap<String, usize>) {
+    // Sort to make the selection deterministic
+    let mut links: Vec<Request> = links.into_iter().collect();
+    links.sort_by(|a, b| {
+        (a.uri.as_str(), a.source.to_string()).cmp(&(b.uri.as_str(), b.source.to_string()))
+    });
+    let mut kept: BTreeMap<String, HashSet<Uri>> = BTreeMap::new();
+    let mut skipped: BTreeMap<String, HashSet<Uri>> = BTreeMap::new();
+    let links = links
+        .into_iter()
+        .filter(|link| {
+            let host = match (&link.uri, link.uri.domain()) {
+                (Uri::Mail(_), _) | (_, None) => return true,
+                (_, Some(host)) => host.trim_end_matches('.').to_lowercase(),
+            };
+            let host_links = kept.entry(host.clone()).or_default();
+            if host_links.contains(&link.uri) || host_links.len() < max_links {
+                host_links.insert(link.uri.clone());
+                true
+            } else {
+                skipped.entry(host).or_default().insert(link.uri.clone());
+                false
+            }
+        })
+        .collect();
+    let skipped = skipped
+        .into_iter()
+        .map(|(host, links)| (host, links.len()))
+        .collect();
+    (links, skipped)
+}
+
+/// Scale concurrency with the number of distinct hosts: many different hosts
+/// can be checked in parallel without hammering a single one of them.
+fn auto_concurrency(links: &HashSet<Request>, max_concurrency: usize) -> usize {
+    let hosts: HashSet<_> = links.iter().filter_map(|l| l.uri.domain()).collect();
+    links
+        .len()
+        .min(hosts.len() * CONCURRENCY_PER_HOST)
+        .min(max_concurrency)
+        .max(1)
+}
+
 fn read_header(input: &str) -> Result<(String, String)> {
     let elements: Vec<_> = input.split('=').collect();
     if elements.len() != 2 {
@@ -212,6 +858,158 @@ fn parse_timeout(timeout: usize) -> Duration {
     Duration::from_secs(timeout as u64)
 }
 
+fn parse_scheme_timeout(input: &str) -> Result<(String, Duration)> {
+    let (scheme, timeout) = read_header(input).with_context(|| {
+        format!(
+            "Scheme timeout should be of the form scheme=seconds, got {}",
+            input
+        )
+    })?;
+    let timeout = timeout
+        .parse()
+        .with_context(|| format!("Invalid timeout for scheme {}: {}", scheme, timeout))?;
+    Ok((scheme, parse_timeout(timeout)))
+}
+
+fn parse_host_policy(input: &str) -> Result<(String, HostPolicy)> {
+    let (pattern, policy) = read_header(input).with_context(|| {
+        format!(
+            "Host policy should be of the form pattern=delay,concurrency, got {}",
+            input
+        )
+    })?;
+    let invalid = || format!("Invalid policy for host {}: {}", pattern, policy);
+    let (delay, concurrency) = match policy.split(',').collect::<Vec<_>>().as_slice() {
+        [delay, concurrency] => (delay.trim().parse(), concurrency.trim().parse()),
+        _ => bail!(invalid()),
+    };
+    let policy = HostPolicy::new(
+        Duration::from_millis(delay.with_context(invalid)?),
+        concurrency.with_context(invalid)?,
+    );
+    Ok((pattern, policy))
+}
+
+fn parse_host_method(input: &str) -> Result<(String, Vec<reqwest::Method>)> {
+    let (pattern, methods) = read_header(input).with_context(|| {
+        format!(
+            "Host method should be of the form pattern=method,..., got {}",
+            input
+        )
+    })?;
+    let methods = methods
+        .split(',')
+        .map(|method| {
+            reqwest::Method::from_str(&method.trim().to_uppercase())
+                .with_context(|| format!("Invalid method for host {}: {}", pattern, method))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((pattern, methods))
+}
+
+/// Parse `pattern=code,...`. Patterns may contain `=` (e.g. in a query),
+/// so the input gets split at the last one.
+fn parse_accept_url(input: &str) -> Result<(String, HashSet<http::StatusCode>)> {
+    let (pattern, codes) = match input.rfind('=') {
+        Some(index) => (&input[..index], &input[index + 1..]),
+        None => bail!(
+            "Accepted status codes should be of the form pattern=code,..., got {}",
+            input
+        ),
+    };
+    let accepted = parse_statuscodes(codes.replace(' ', ""))
+        .with_context(|| format!("Invalid status codes for {}: {}", pattern, codes))?;
+    Ok((pattern.to_string(), accepted))
+}
+
+/// Parse `pattern=type,...`, splitting at the last `=` like `parse_accept_url`
+fn parse_expect_content_type(input: &str) -> Result<(String, Vec<String>)> {
+    let (pattern, content_types) = match input.rfind('=') {
+        Some(index) => (&input[..index], &input[index + 1..]),
+        None => bail!(
+            "Expected content types should be of the form pattern=type,..., got {}",
+            input
+        ),
+    };
+    let content_types: Vec<String> = content_types
+        .split(',')
+        .map(|content_type| content_type.trim().to_string())
+        .filter(|content_type| !content_type.is_empty())
+        .collect();
+    if let Some(invalid) = content_types.iter().find(|t| !t.contains('/')) {
+        bail!("Invalid content type for {}: {}", pattern, invalid);
+    }
+    if content_types.is_empty() {
+        bail!("No content types given for {}", pattern);
+    }
+    Ok((pattern.to_string(), content_types))
+}
+
+/// Build a rewriter replacing the matches of the patterns in the `rewrite`
+/// config, in alphabetical order of the patterns
+#[allow(clippy::type_complexity)]
+fn url_rewriter(
+    rules: &BTreeMap<String, String>,
+) -> Result<Option<Box<dyn Fn(Uri) -> Uri + Send + Sync>>> {
+    if rules.is_empty() {
+        return Ok(None);
+    }
+    let rules = rules
+        .iter()
+        .map(|(pattern, replacement)| {
+            let pattern = Regex::new(pattern)
+                .with_context(|| format!("Invalid rewrite pattern {}", pattern))?;
+            Ok((pattern, replacement.clone()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(Box::new(move |uri: Uri| {
+        let mut rewritten = uri.as_str().to_string();
+        for (pattern, replacement) in &rules {
+            rewritten = pattern
+                .replace_all(&rewritten, replacement.as_str())
+                .into_owned();
+        }
+        // Rewrites producing an invalid link are ignored
+        Uri::try_from(rewritten.as_str()).unwrap_or(uri)
+    })))
+}
+
+/// Parse `Header` or `Header: value`
+fn parse_required_header(input: &str) -> Result<(HeaderName, Option<String>)> {
+    let (name, value) = match input.find(':') {
+        Some(idx) => (&input[..idx], Some(input[idx + 1..].trim().to_string())),
+        None => (input, None),
+    };
+    let name = HeaderName::from_str(name.trim())
+        .with_context(|| format!("Invalid required header {}", input))?;
+    Ok((name, value))
+}
+
+/// Parse `prefix=base`, splitting at the first `=`
+fn parse_input_base_url(input: &str) -> Result<(String, Url)> {
+    let (prefix, base_url) = match input.find('=') {
+        Some(idx) => (&input[..idx], &input[idx + 1..]),
+        None => bail!(
+            "Input base URL should be of the form prefix=base, got {}",
+            input
+        ),
+    };
+    let base_url = Url::parse(base_url)
+        .with_context(|| format!("Invalid base URL for input {}: {}", prefix, base_url))?;
+    Ok((prefix.to_string(), base_url))
+}
+
+/// Parse the address of a name server, using port 53 if none is given
+fn parse_name_server(name_server: &str) -> Result<SocketAddr> {
+    if let Ok(addr) = name_server.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    let ip: IpAddr = name_server
+        .parse()
+        .with_context(|| format!("Invalid name server address {}", name_server))?;
+    Ok(SocketAddr::new(ip, 53))
+}
+
 fn parse_headers<T: AsRef<str>>(headers: &[T]) -> Result<HeaderMap> {
     let mut out = HeaderMap::new();
     for header in headers {
@@ -233,6 +1031,55 @@ fn parse_statuscodes<T: AsRef<str>>(accept: T) -> Result<HashSet<http::StatusCod
     Ok(statuscodes)
 }
 
+fn read_certificate(path: &Path) -> Result<reqwest::Certificate> {
+    let contents = fs::read(path)
+        .with_context(|| format!("Cannot read certificate file `{}`", path.display()))?;
+    // PEM files are text-based, everything else is assumed to be DER-encoded
+    let cert = if contents.starts_with(b"-----BEGIN") {
+        reqwest::Certificate::from_pem(&contents)
+    } else {
+        reqwest::Certificate::from_der(&contents)
+    };
+    cert.with_context(|| format!("Invalid certificate in `{}`", path.display()))
+}
+
+fn read_identity(cert: &Path, key: Option<&Path>) -> Result<reqwest::Identity> {
+    let read = |path: &Path| {
+        fs::read(path)
+            .with_context(|| format!("Cannot read client certificate file `{}`", path.display()))
+    };
+    let cert_contents = read(cert)?;
+    let identity = match key {
+        Some(key) => reqwest::Identity::from_pkcs8_pem(&cert_contents, &read(key)?),
+        None => reqwest::Identity::from_pkcs12_der(&cert_contents, ""),
+    };
+    identity.with_context(|| format!("Invalid client certificate in `{}`", cert.display()))
+}
+
+/// The OAuth2 client credentials, if `oauth2_token_url` is given
+fn oauth2_credentials(cfg: &Config) -> Result<Option<OAuth2Credentials>> {
+    let token_url = match &cfg.oauth2_token_url {
+        Some(url) => {
+            Url::parse(url).with_context(|| format!("Invalid OAuth2 token URL {}", url))?
+        }
+        None => return Ok(None),
+    };
+    let (client_id, client_secret) = match (&cfg.oauth2_client_id, &cfg.oauth2_client_secret) {
+        (Some(id), Some(secret)) => (id.clone(), secret.clone()),
+        _ => bail!("`oauth2_token_url` requires `oauth2_client_id` and `oauth2_client_secret`"),
+    };
+    if cfg.oauth2_host.is_empty() {
+        bail!("`oauth2_token_url` requires at least one `oauth2_host` to send the token to");
+    }
+    Ok(Some(OAuth2Credentials {
+        token_url,
+        client_id,
+        client_secret,
+        scopes: cfg.oauth2_scope.clone(),
+        hosts: cfg.oauth2_host.clone(),
+    }))
+}
+
 fn parse_basic_auth(auth: &str) -> Result<Authorization<Basic>> {
     let params: Vec<_> = auth.split(':').collect();
     if params.len() != 2 {
@@ -248,6 +1095,7 @@ fn parse_basic_auth(auth: &str) -> Result<Authorization<Basic>> {
 mod test {
     use super::*;
     use http::StatusCode;
+    use kimchi::test_utils::website;
     use reqwest::header;
 
     #[test]
@@ -257,6 +1105,149 @@ mod test {
         assert_eq!(parse_headers(&["accept=text/html"]).unwrap(), custom);
     }
 
+    #[test]
+    fn test_parse_scheme_timeout() {
+        assert_eq!(
+            parse_scheme_timeout("http=60").unwrap(),
+            ("http".to_string(), Duration::from_secs(60))
+        );
+        assert!(parse_scheme_timeout("http").is_err());
+        assert!(parse_scheme_timeout("http=soon").is_err());
+    }
+
+    #[test]
+    fn test_parse_input_base_url() {
+        assert_eq!(
+            parse_input_base_url("docs/=https://docs.example.com/?v=2").unwrap(),
+            (
+                "docs/".to_string(),
+                Url::parse("https://docs.example.com/?v=2").unwrap()
+            )
+        );
+        assert!(parse_input_base_url("docs/").is_err());
+        assert!(parse_input_base_url("docs/=not a url").is_err());
+    }
+
+    #[test]
+    fn test_parse_name_server() {
+        assert_eq!(
+            parse_name_server("1.1.1.1").unwrap(),
+            "1.1.1.1:53".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_name_server("[2606:4700:4700::1111]:5353").unwrap(),
+            "[2606:4700:4700::1111]:5353".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(parse_name_server("::1").unwrap().port(), 53);
+        assert!(parse_name_server("dns.example.com").is_err());
+    }
+
+    #[test]
+    fn test_parse_host_policy() {
+        assert_eq!(
+            parse_host_policy("*.example.com=500,2").unwrap(),
+            (
+                "*.example.com".to_string(),
+                HostPolicy::new(Duration::from_millis(500), 2)
+            )
+        );
+        assert!(parse_host_policy("*.example.com=500").is_err());
+        assert!(parse_host_policy("*.example.com=slow,2").is_err());
+    }
+
+    #[test]
+    fn test_url_rewriter() {
+        let mut rules = BTreeMap::new();
+        rules.insert(
+            r"//docs\.internal/".to_string(),
+            "//docs.staging.internal/".to_string(),
+        );
+        rules.insert(r"/v(\d+)/".to_string(), "/version-$1/".to_string());
+        let rewrite = url_rewriter(&rules).unwrap().unwrap();
+        assert_eq!(
+            rewrite(website("https://docs.internal/v2/setup")),
+            website("https://docs.staging.internal/version-2/setup")
+        );
+        assert_eq!(
+            rewrite(website("https://example.org/")),
+            website("https://example.org/")
+        );
+
+        assert!(url_rewriter(&BTreeMap::new()).unwrap().is_none());
+        rules.insert("(".to_string(), String::new());
+        assert!(url_rewriter(&rules).is_err());
+    }
+
+    #[test]
+    fn test_parse_required_header() {
+        assert_eq!(
+            parse_required_header("Content-Disposition").unwrap(),
+            (HeaderName::from_static("content-disposition"), None)
+        );
+        assert_eq!(
+            parse_required_header("X-Robots-Tag: noindex, nofollow").unwrap(),
+            (
+                HeaderName::from_static("x-robots-tag"),
+                Some("noindex, nofollow".to_string())
+            )
+        );
+        assert!(parse_required_header("Not a header").is_err());
+    }
+
+    #[test]
+    fn test_parse_host_method() {
+        assert_eq!(
+            parse_host_method("*.example.com=head, GET").unwrap(),
+            (
+                "*.example.com".to_string(),
+                vec![reqwest::Method::HEAD, reqwest::Method::GET]
+            )
+        );
+        assert!(parse_host_method("*.example.com").is_err());
+        assert!(parse_host_method("*.example.com=HEAD,G ET").is_err());
+    }
+
+    #[test]
+    fn test_parse_accept_url() {
+        let (pattern, accepted) = parse_accept_url("/health\\?probe=1$=200, 204").unwrap();
+        assert_eq!(pattern, "/health\\?probe=1$");
+        assert_eq!(
+            accepted,
+            [StatusCode::OK, StatusCode::NO_CONTENT]
+                .iter()
+                .copied()
+                .collect()
+        );
+        assert!(parse_accept_url("/health").is_err());
+        assert!(parse_accept_url("/health=ok").is_err());
+    }
+
+    #[test]
+    fn test_parse_expect_content_type() {
+        let (pattern, content_types) =
+            parse_expect_content_type("\\.pdf$=application/pdf, image/*").unwrap();
+        assert_eq!(pattern, "\\.pdf$");
+        assert_eq!(content_types, vec!["application/pdf", "image/*"]);
+        assert!(parse_expect_content_type("\\.pdf$").is_err());
+        assert!(parse_expect_content_type("\\.pdf$=pdf").is_err());
+        assert!(parse_expect_content_type("\\.pdf$=").is_err());
+    }
+
+    #[test]
+    fn test_is_ci() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |var: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == var)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert!(!is_ci(env(&[])));
+        assert!(is_ci(env(&[("CI", "true")])));
+        assert!(is_ci(env(&[("GITHUB_ACTIONS", "true")])));
+        assert!(!is_ci(env(&[("CI", "false")])));
+    }
+
     #[test]
     fn test_parse_statuscodes() {
         let actual = parse_statuscodes("200,204,301").unwrap();
@@ -271,6 +1262,115 @@ mod test {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_auto_concurrency() {
+        let links = |urls: &[&str]| -> HashSet<Request> {
+            urls.iter()
+                .map(|url| Request::try_from(*url).unwrap())
+                .collect()
+        };
+
+        assert_eq!(auto_concurrency(&HashSet::new(), 128), 1);
+        assert_eq!(
+            auto_concurrency(&links(&["https://a.org/1", "https://a.org/2"]), 128),
+            2
+        );
+        let many: Vec<String> = (0..20).map(|i| format!("https://a.org/{}", i)).collect();
+        let many: Vec<&str> = many.iter().map(String::as_str).collect();
+        assert_eq!(auto_concurrency(&links(&many), 128), CONCURRENCY_PER_HOST);
+        assert_eq!(auto_concurrency(&links(&many), 4), 4);
+    }
+
+    #[test]
+    fn test_cap_links() {
+        let links: HashSet<Request> = ["https://c.org", "https://a.org", "https://b.org"]
+            .iter()
+            .map(|url| Request::try_from(*url).unwrap())
+            .collect();
+
+        assert_eq!(cap_links(links.clone(), 3, false).unwrap(), links);
+        assert!(cap_links(links.clone(), 2, false).is_err());
+
+        let truncated: Vec<String> = cap_links(links, 2, true)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.uri.to_string())
+            .collect();
+        assert_eq!(truncated.len(), 2);
+        assert!(!truncated.contains(&"https://c.org/".to_string()));
+    }
+
+    #[test]
+    fn test_find_own_host_links() {
+        let guide = Input::FsPath("docs/guide.md".into());
+        let link = |url: &str, line: usize| {
+            Request::new(Uri::try_from(url).unwrap(), guide.clone()).with_line(Some(line))
+        };
+        let links: HashSet<Request> = vec![
+            link("https://docs.example.com/setup", 12),
+            link("http://api.docs.example.com/", 3),
+            link("https://docs.example.com/intro", 5).with_relative(true),
+            link("https://example.org/", 7),
+            link("mailto:team@docs.example.com", 9),
+        ]
+        .into_iter()
+        .collect();
+        let own_hosts =
+            Excludes::compile_host_globs(&["docs.example.com", "*.docs.example.com"][..])
+                .unwrap()
+                .unwrap();
+
+        let found: Vec<String> = find_own_host_links(&links, &own_hosts)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                "docs/guide.md:3: http://api.docs.example.com/",
+                "docs/guide.md:12: https://docs.example.com/setup",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cap_links_per_host() {
+        let mut links: HashSet<Request> = [
+            "https://a.org/3",
+            "https://a.org/1",
+            "https://a.org/2",
+            "https://b.org/",
+            "mailto:one@a.org",
+            "mailto:two@a.org",
+        ]
+        .iter()
+        .map(|url| Request::try_from(*url).unwrap())
+        .collect();
+        // Used in another input as well
+        links.insert(Request::new(
+            Uri::try_from("https://a.org/1").unwrap(),
+            Input::Stdin,
+        ));
+
+        let (capped, skipped) = cap_links_per_host(links, 1);
+        let mut capped: Vec<String> = capped.into_iter().map(|r| r.uri.to_string()).collect();
+        capped.sort();
+        assert_eq!(
+            capped,
+            vec![
+                "https://a.org/1",
+                "https://a.org/1",
+                "https://b.org/",
+                "one@a.org",
+                "two@a.org"
+            ]
+        );
+        assert_eq!(
+            skipped.into_iter().collect::<Vec<_>>(),
+            vec![("a.org".to_string(), 2)]
+        );
+    }
+
     #[test]
     fn test_parse_basic_auth() {
         let mut expected = HeaderMap::new();
@@ -284,4 +1384,22 @@ mod test {
         actual.typed_insert(auth_header);
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_output_files() {
+        let outputs: Vec<OutputFile> = ["results.json:json", "results.md:markdown", "report"]
+            .iter()
+            .map(|output| output.parse().unwrap())
+            .collect();
+        assert_eq!(outputs[0].format, Some(Format::Json));
+        assert_eq!(outputs[1].path, Path::new("results.md"));
+        assert_eq!(outputs[2].format, None);
+        assert!(check_output_files(&outputs).is_ok());
+
+        let twice: Vec<OutputFile> = ["results.json:json", "results.json:markdown"]
+            .iter()
+            .map(|output| output.parse().unwrap())
+            .collect();
+        assert!(check_output_files(&twice).is_err());
+    }
 }
@@ -1,22 +1,40 @@
 use kimchi::collector::Input;
 
-use anyhow::{anyhow, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use lazy_static::lazy_static;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
 use std::str::FromStr;
-use std::{fs, io::ErrorKind, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
 use structopt::{clap::crate_version, StructOpt};
 
 pub(crate) const USER_AGENT: &str = concat!("kimchi/", crate_version!());
 const METHOD: &str = "get";
 const TIMEOUT: usize = 20;
 const MAX_CONCURRENCY: usize = 128;
+const MAX_FETCH_CONCURRENCY: usize = 16;
 const MAX_REDIRECTS: usize = 10;
+const HEARTBEAT: usize = 30;
+const AUX_TIMEOUT: usize = 5;
+const MIN_CONTENT_LENGTH: u64 = 1;
+const ANCHOR_STYLE: &str = "github";
+const HTTP_VERSION: &str = "auto";
+const REDACTED: &str = "<redacted>";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum Format {
     String,
     Json,
+    Markdown,
+    Prometheus,
+    /// Only the URLs of the failed links, one per line
+    Failures,
 }
 
 impl FromStr for Format {
@@ -25,6 +43,9 @@ impl FromStr for Format {
         match format {
             "string" => Ok(Format::String),
             "json" => Ok(Format::Json),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "prometheus" | "openmetrics" => Ok(Format::Prometheus),
+            "failures" | "failures-only" => Ok(Format::Failures),
             _ => Err(anyhow!("Could not parse format {}", format)),
         }
     }
@@ -36,12 +57,94 @@ impl Default for Format {
     }
 }
 
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::String => "string",
+            Format::Json => "json",
+            Format::Markdown => "markdown",
+            Format::Prometheus => "prometheus",
+            Format::Failures => "failures",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A file to write the status report to, given as `path` or `path:format`.
+/// Without an explicit format, the one from `--format` is used.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct OutputFile {
+    pub path: PathBuf,
+    pub format: Option<Format>,
+}
+
+impl FromStr for OutputFile {
+    type Err = Error;
+    fn from_str(output: &str) -> Result<Self, Self::Err> {
+        // Only split off known formats, so paths containing a colon
+        // (e.g. `C:\report.json` on Windows) keep working
+        if let Some(idx) = output.rfind(':') {
+            if let Ok(format) = output[idx + 1..].parse() {
+                return Ok(OutputFile {
+                    path: PathBuf::from(&output[..idx]),
+                    format: Some(format),
+                });
+            }
+        }
+        Ok(OutputFile {
+            path: PathBuf::from(output),
+            format: None,
+        })
+    }
+}
+
+impl TryFrom<String> for OutputFile {
+    type Error = Error;
+    fn try_from(output: String) -> Result<Self, Self::Error> {
+        output.parse()
+    }
+}
+
+impl From<OutputFile> for String {
+    fn from(output: OutputFile) -> Self {
+        match output.format {
+            Some(format) => format!("{}:{}", output.path.display(), format),
+            None => output.path.display().to_string(),
+        }
+    }
+}
+
+/// Accept a single value as well as a list in the config file,
+/// e.g. `output = "report.json"`
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
 // this exists because structopt requires `&str` type values for defaults
 // (we can't use e.g. `TIMEOUT` or `timeout()` which gets created for serde)
 lazy_static! {
     static ref TIMEOUT_STR: String = TIMEOUT.to_string();
+    static ref AUX_TIMEOUT_STR: String = AUX_TIMEOUT.to_string();
     static ref MAX_CONCURRENCY_STR: String = MAX_CONCURRENCY.to_string();
+    static ref MAX_FETCH_CONCURRENCY_STR: String = MAX_FETCH_CONCURRENCY.to_string();
     static ref MAX_REDIRECTS_STR: String = MAX_REDIRECTS.to_string();
+    static ref HEARTBEAT_STR: String = HEARTBEAT.to_string();
+    static ref MIN_CONTENT_LENGTH_STR: String = MIN_CONTENT_LENGTH.to_string();
 }
 
 // Macro for generating default functions to be used by serde
@@ -59,9 +162,15 @@ macro_rules! default_function {
 default_function! {
     max_redirects: usize = MAX_REDIRECTS;
     max_concurrency: usize = MAX_CONCURRENCY;
+    max_fetch_concurrency: usize = MAX_FETCH_CONCURRENCY;
     user_agent: String = USER_AGENT.to_string();
     timeout: usize = TIMEOUT;
+    aux_timeout: usize = AUX_TIMEOUT;
     method: String = METHOD.to_string();
+    heartbeat: usize = HEARTBEAT;
+    min_content_length: u64 = MIN_CONTENT_LENGTH;
+    anchor_style: String = ANCHOR_STYLE.to_string();
+    http_version: String = HTTP_VERSION.to_string();
 }
 
 // Macro for merging configuration values
@@ -91,6 +200,11 @@ pub(crate) struct KimchiOptions {
     #[structopt(short, long = "config", default_value = "./kimchi.toml")]
     pub config_file: String,
 
+    /// Print the effective configuration after merging the CLI arguments and the config file,
+    /// then exit. Secrets are redacted. Uses JSON with `--format json`, TOML otherwise
+    #[structopt(long)]
+    pub print_config: bool,
+
     #[structopt(flatten)]
     pub config: Config,
 }
@@ -108,28 +222,152 @@ impl KimchiOptions {
     }
 }
 
-#[derive(Debug, Deserialize, StructOpt)]
+#[derive(Debug, Clone, Deserialize, Serialize, StructOpt)]
 pub struct Config {
     /// Verbose program output
     #[structopt(short, long)]
     #[serde(default)]
     pub verbose: bool,
 
-    /// Show progress
+    /// Show the full chain of causes for runtime errors,
+    /// e.g. the DNS or TLS error behind a failed connection
+    #[structopt(long)]
+    #[serde(default)]
+    pub verbose_errors: bool,
+
+    /// Don't print a line for any link while checking, not even for broken
+    /// links. Only the summary gets printed (or written to `--output`)
+    #[structopt(long, conflicts_with = "verbose")]
+    #[serde(default)]
+    pub summary_only: bool,
+
+    /// Show the number of links, success rate and median response time of
+    /// the N least healthy hosts in the summary. With `--verbose`, all hosts
+    /// are shown
+    #[structopt(long)]
+    #[serde(default)]
+    pub top_hosts: Option<usize>,
+
+    /// Show progress. Ignored on CI unless `--force-progress` is given
     #[structopt(short, long)]
     #[serde(default)]
     pub progress: bool,
 
+    /// Show progress even on CI, where it's disabled by default
+    #[structopt(long)]
+    #[serde(default)]
+    pub force_progress: bool,
+
+    /// Show a live dashboard with the progress per host and the latest failures
+    /// instead of the progress bar. Ignored if stdout isn't a terminal
+    #[structopt(long)]
+    #[serde(default)]
+    pub tui: bool,
+
+    /// Print the results in a stable order (sorted by input and URL) instead of
+    /// the order in which the checks finish. Links are still checked concurrently
+    #[structopt(long)]
+    #[serde(default)]
+    pub ordered_output: bool,
+
+    /// Interval in seconds for printing a progress summary to stderr when the progress bar
+    /// is disabled, so long runs don't look stuck (e.g. on CI). Set to 0 to disable
+    #[structopt(long, default_value = &HEARTBEAT_STR)]
+    #[serde(default = "heartbeat")]
+    pub heartbeat: usize,
+
     /// Maximum number of allowed redirects
     #[structopt(short, long, default_value = &MAX_REDIRECTS_STR)]
     #[serde(default = "max_redirects")]
     pub max_redirects: usize,
 
+    /// Maximum number of permanent redirects (301, 308) to follow. Links with
+    /// more are reported as redirected, e.g. to update links which moved
+    #[structopt(long)]
+    #[serde(default)]
+    pub max_permanent_redirects: Option<usize>,
+
+    /// Maximum number of temporary redirects (302, 303, 307) to follow.
+    /// Links with more are reported as redirected
+    #[structopt(long)]
+    #[serde(default)]
+    pub max_temporary_redirects: Option<usize>,
+
+    /// Replace links which got moved permanently (a single `301` or `308`
+    /// redirect to a working page) with their new URL in the Markdown and
+    /// HTML files checked. Prints a diff of the changes
+    #[structopt(long)]
+    #[serde(default)]
+    pub fix: bool,
+
+    /// Look up broken links in the Wayback Machine of the Internet Archive
+    /// and report their latest snapshot. With `--fix`, broken links get
+    /// replaced with their snapshot
+    #[structopt(long)]
+    #[serde(default)]
+    pub wayback_fallback: bool,
+
+    /// Only collect the links of the inputs and print the extraction
+    /// throughput as JSON, without checking any links
+    #[structopt(long)]
+    #[serde(default)]
+    pub bench_extract: bool,
+
     /// Maximum number of concurrent network requests
     #[structopt(long, default_value = &MAX_CONCURRENCY_STR)]
     #[serde(default = "max_concurrency")]
     pub max_concurrency: usize,
 
+    /// Derive the number of concurrent requests from the number of distinct
+    /// hosts to check, capped by `--max-concurrency`
+    #[structopt(long)]
+    #[serde(default)]
+    pub auto_concurrency: bool,
+
+    /// Maximum number of links to check. Protects against accidentally checking
+    /// huge directories; by default the run fails if more links are found
+    #[structopt(long)]
+    #[serde(default)]
+    pub max_links: Option<usize>,
+
+    /// Maximum number of links to check per host, e.g. to spot-check huge sites.
+    /// The first links (sorted by URL) get checked, the number of skipped links is
+    /// listed in the summary
+    #[structopt(long)]
+    #[serde(default)]
+    pub max_links_per_host: Option<usize>,
+
+    /// Only warn and check the first `--max-links` links (sorted by URL)
+    /// instead of failing if the limit is exceeded
+    #[structopt(long)]
+    #[serde(default)]
+    pub truncate_links: bool,
+
+    /// Maximum number of retries for the whole run. Once exhausted,
+    /// failing links are reported without being retried
+    #[structopt(long)]
+    #[serde(default)]
+    pub max_total_retries: Option<usize>,
+
+    /// Remember the `ETag` and `Last-Modified` headers of working links in this file.
+    /// Later runs revalidate these links with conditional requests, so unchanged
+    /// resources don't get downloaded again
+    #[structopt(long, parse(from_os_str))]
+    #[serde(default)]
+    pub cache: Option<PathBuf>,
+
+    /// Record the checked links in this checkpoint file. If a run gets
+    /// interrupted, running again with the same file only checks the links
+    /// which aren't recorded yet. The file gets deleted once a run completes
+    #[structopt(long, parse(from_os_str))]
+    #[serde(default)]
+    pub resume: Option<PathBuf>,
+
+    /// Keep the checkpoint file of `--resume` after a completed run
+    #[structopt(long)]
+    #[serde(default)]
+    pub keep_checkpoint: bool,
+
     /// Number of threads to utilize.
     /// Defaults to number of cores available to the system
     #[structopt(short = "T", long)]
@@ -141,11 +379,77 @@ pub struct Config {
     #[serde(default = "user_agent")]
     pub user_agent: String,
 
+    /// Send the user agent and `Accept`/`Accept-Language` headers of a web browser,
+    /// for websites blocking other clients. Overrides `--user-agent`
+    #[structopt(long)]
+    #[serde(default)]
+    pub browser_ua: bool,
+
     /// Proceed for server connections considered insecure (invalid TLS)
     #[structopt(short, long)]
     #[serde(default)]
     pub insecure: bool,
 
+    /// HTTP version to use: `auto` (HTTP/2 if the server supports it), `1.1`, or `2`
+    /// (HTTP/2 without negotiating it first)
+    #[structopt(long, default_value = HTTP_VERSION)]
+    #[serde(default = "http_version")]
+    pub http_version: String,
+
+    /// Maximum number of idle connections kept open per host (default: unlimited).
+    /// Combine `1` with a host policy concurrency of 1 to send all requests to a host
+    /// over a single connection
+    #[structopt(long)]
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<usize>,
+
+    /// Seconds to keep idle connections open for reuse (default: 90)
+    #[structopt(long)]
+    #[serde(default)]
+    pub pool_idle_timeout: Option<usize>,
+
+    /// Interval in seconds of TCP keep-alive probes on open connections (default: off)
+    #[structopt(long)]
+    #[serde(default)]
+    pub tcp_keepalive: Option<usize>,
+
+    /// Additional root certificate (PEM or DER) to trust, e.g. for a private CA.
+    /// Can be given multiple times
+    #[structopt(long, parse(from_os_str))]
+    #[serde(default)]
+    pub cacert: Vec<PathBuf>,
+
+    /// Client certificate for mutual TLS. Either a PEM certificate (requires
+    /// `--client-key`) or a PKCS #12 archive without password
+    #[structopt(long, parse(from_os_str))]
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+
+    /// PKCS #8 private key (PEM) belonging to the `--client-cert` certificate
+    #[structopt(long, parse(from_os_str), requires = "client-cert")]
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+
+    /// Name server to ask if the system resolver can't resolve a host (e.g. `1.1.1.1` or
+    /// `8.8.8.8:53`), for unreliable resolvers which sporadically fail for valid hosts.
+    /// Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub fallback_dns: Vec<String>,
+
+    /// Check that the network works before checking any links, by requesting
+    /// known-good URLs. If none of them can be reached, the run stops with
+    /// exit code 3 instead of reporting every external link as broken
+    #[structopt(long)]
+    #[serde(default)]
+    pub preflight: bool,
+
+    /// URL to request for `--preflight`, replacing the default URLs.
+    /// Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub preflight_url: Vec<String>,
+
     /// Only test links with the given scheme (e.g. https)
     #[structopt(short, long)]
     #[serde(default)]
@@ -161,6 +465,18 @@ pub struct Config {
     #[serde(default)]
     pub exclude: Vec<String>,
 
+    /// Exclude hosts matching these glob patterns (e.g. `*.example.com`), case-insensitive.
+    /// Unlike `--exclude`, only the host of a URL is matched
+    #[structopt(long)]
+    #[serde(default)]
+    pub exclude_hosts: Vec<String>,
+
+    /// Exclude links to files with these extensions (e.g. `zip`, `tar.gz`), e.g. large downloads.
+    /// Only the path of a URL is matched, not the query string
+    #[structopt(long)]
+    #[serde(default)]
+    pub exclude_extensions: Vec<String>,
+
     /// Exclude all private IPs from checking.
     /// Equivalent to `--exclude-private --exclude-link-local --exclude-loopback`
     #[structopt(short = "E", long)]
@@ -182,11 +498,24 @@ pub struct Config {
     #[serde(default)]
     pub exclude_loopback: bool,
 
-    /// Exlucde all mail addresses from checking
+    /// Exclude all mail addresses from checking
     #[structopt(long)]
     #[serde(default)]
     pub exclude_mail: bool,
 
+    /// Exclude links with this scheme as "unsupported scheme", as they are handled by other
+    /// apps than a browser. Replaces the default schemes (tel, sms, mms, callto, facetime,
+    /// skype, irc, ircs, xmpp, geo, magnet, webcal). Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub uncheckable_scheme: Vec<String>,
+
+    /// Verify that mail addresses exist by asking their mail server, instead of
+    /// only checking their syntax. Slow and often blocked by mail servers
+    #[structopt(long)]
+    #[serde(default)]
+    pub check_mail_reachability: bool,
+
     /// Custom request headers
     #[structopt(short, long)]
     #[serde(default)]
@@ -197,32 +526,319 @@ pub struct Config {
     #[serde(default)]
     pub accept: Option<String>,
 
+    /// Accepted status codes for links matching a regular expression, given
+    /// as `pattern=code,...`. Only these codes pass for matching links,
+    /// overriding `--accept`. If several patterns match, the first one wins
+    #[structopt(long)]
+    #[serde(default)]
+    pub accept_url: Vec<String>,
+
+    /// Comma-separated list of additional status codes to retry, e.g. `520,521,598`.
+    /// Timeouts, connection errors, 429, 500, 502, 503 and 504 are always retried
+    #[structopt(long)]
+    #[serde(default)]
+    pub retry_on_status: Option<String>,
+
+    /// Report links answering with 401 or 403 as "auth required" instead of failing them.
+    /// Links behind a login usually aren't broken
+    #[structopt(long)]
+    #[serde(default)]
+    pub detect_auth_required: bool,
+
+    /// Count links which require authentication as failures instead of only reporting them
+    #[structopt(long, requires = "detect-auth-required")]
+    #[serde(default)]
+    pub fail_on_auth_required: bool,
+
+    /// Only accept 2xx responses, ignoring `--accept`. Redirects count as failures
+    #[structopt(long)]
+    #[serde(default)]
+    pub strict: bool,
+
+    /// Fail if an input can't be read, e.g. a file matched by a glob without read permissions.
+    /// Such inputs are always listed in the summary
+    #[structopt(long)]
+    #[serde(default)]
+    pub fail_on_input_errors: bool,
+
+    /// Only let broken links to hosts matching this pattern (e.g. `*.example.com`) fail the run.
+    /// Other broken links are still reported. Links to local files are always required.
+    /// Excluded links never fail, and links requiring authentication only fail with
+    /// `--fail-on-auth-required`. Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub required_host: Vec<String>,
+
+    /// Host of your own site (e.g. `docs.example.com` or `*.example.com`), which should
+    /// only be linked relatively. Absolute links to it get listed as policy violations,
+    /// as they break in preview deployments. Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub own_host: Vec<String>,
+
+    /// Fail the run if there are absolute links to an `--own-host`
+    #[structopt(long)]
+    #[serde(default)]
+    pub fail_on_own_host_links: bool,
+
+    /// Let the run pass with up to this many broken links. All broken links are still
+    /// reported. Ignored with `--strict`
+    #[structopt(long)]
+    #[serde(default)]
+    pub max_failures: Option<usize>,
+
+    /// Let the run pass if at most this percentage of the checked (not excluded) links
+    /// is broken. If `--max-failures` is given as well, both limits apply. Ignored with
+    /// `--strict`
+    #[structopt(long)]
+    #[serde(default)]
+    pub max_failure_percent: Option<f64>,
+
     /// Website timeout from connect to response finished
     #[structopt(short, long, default_value = &TIMEOUT_STR)]
     #[serde(default = "timeout")]
     pub timeout: usize,
 
+    /// Verify that image links (e.g. `<img src>`, `srcset`) return an image content type
+    #[structopt(long)]
+    #[serde(default)]
+    pub check_image_types: bool,
+
+    /// Expected content types for links matching a regular expression, given as
+    /// `pattern=type,...`, e.g. `\.pdf$=application/pdf`. Types like `image/*` match
+    /// all subtypes. If several patterns match, the first one wins
+    #[structopt(long)]
+    #[serde(default)]
+    pub expect_content_type: Vec<String>,
+
+    /// Check that the `#fragment` of a link exists as an anchor on the linked page.
+    /// Links to anchors in the same document (`#usage`) are checked without a request
+    #[structopt(long)]
+    #[serde(default)]
+    pub check_anchors: bool,
+
+    /// How headings are turned into anchors by the generator of the linked pages
+    /// (github, gitlab, sphinx)
+    #[structopt(long, default_value = ANCHOR_STYLE)]
+    #[serde(default = "anchor_style")]
+    pub anchor_style: String,
+
+    /// Match anchors case-insensitively
+    #[structopt(long)]
+    #[serde(default)]
+    pub anchors_ignore_case: bool,
+
+    /// Treat redirects to a page whose URL or title matches this regex as broken ("soft 404").
+    /// Can be given multiple times
+    #[structopt(long = "soft-404-pattern")]
+    #[serde(default)]
+    pub soft_404_pattern: Vec<String>,
+
+    /// Links matching this regex (e.g. downloads) must not have an empty or truncated body,
+    /// see `--min-content-length`. Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub expect_content: Vec<String>,
+
+    /// Minimum body size in bytes for links matching `--expect-content`
+    #[structopt(long, default_value = &MIN_CONTENT_LENGTH_STR)]
+    #[serde(default = "min_content_length")]
+    pub min_content_length: u64,
+
+    /// Website timeout for links with a specific scheme, overriding `--timeout`.
+    /// E.g. `http=60` for slow plain HTTP mirrors
+    #[structopt(long)]
+    #[serde(default)]
+    pub scheme_timeout: Vec<String>,
+
+    /// Timeout in seconds for the requests supporting the link check: preflight URLs,
+    /// robots.txt files, OAuth2 tokens and Wayback Machine lookups
+    #[structopt(long, default_value = &AUX_TIMEOUT_STR)]
+    #[serde(default = "aux_timeout")]
+    pub aux_timeout: usize,
+
+    /// Politeness rule for hosts matching a glob pattern, given as
+    /// `pattern=delay,concurrency` with the delay between requests in milliseconds.
+    /// E.g. `*.example.com=500,2`. Overrides the built-in rules for Wikipedia and archive.org
+    #[structopt(long)]
+    #[serde(default)]
+    pub host_policy: Vec<String>,
+
+    /// Don't apply the built-in politeness rules for hosts known to rate limit link checkers
+    #[structopt(long)]
+    #[serde(default)]
+    pub no_default_host_policies: bool,
+
+    /// Maximum number of requests per time unit, e.g. `60/min` or `5/s`.
+    /// Requests get spread evenly instead of being sent in bursts
+    #[structopt(long)]
+    #[serde(default)]
+    pub rate_limit: Option<String>,
+
+    /// Apply `--rate-limit` to every host separately
+    #[structopt(long, requires = "rate-limit")]
+    #[serde(default)]
+    pub rate_limit_per_host: bool,
+
+    /// Wait between requests to a host as long as the `Crawl-delay` of its
+    /// robots.txt asks for (at most 30 seconds)
+    #[structopt(long)]
+    #[serde(default)]
+    pub respect_crawl_delay: bool,
+
+    /// Wait at least this many milliseconds between sending two requests,
+    /// regardless of the concurrency and the host
+    #[structopt(long)]
+    #[serde(default)]
+    pub delay: Option<u64>,
+
     /// Request method
     // Using `-X` as a short param similar to curl
     #[structopt(short = "X", long, default_value = METHOD)]
     #[serde(default = "method")]
     pub method: String,
 
+    /// Request methods for hosts matching a glob pattern, given as
+    /// `pattern=method,...`. E.g. `*.example.com=HEAD,GET` only accepts links
+    /// which work with both `HEAD` and `GET`
+    #[structopt(long)]
+    #[serde(default)]
+    pub host_method: Vec<String>,
+
     /// Base URL to check relative URLs
     #[structopt(short, long)]
     #[serde(default)]
     pub base_url: Option<String>,
 
+    /// Base URL for the inputs in a directory or under a URL, given as `prefix=base`,
+    /// e.g. `docs/=https://docs.example.com/`. Overrides `--base-url` for these inputs.
+    /// The longest matching prefix wins. Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub input_base_url: Vec<String>,
+
+    /// Follow the links of URL inputs to pages of the same origin up to this
+    /// many levels deep and check their links too. Links to other origins
+    /// are checked, but not followed. Crawling stops at `--max-links`
+    #[structopt(long, default_value = "0")]
+    #[serde(default)]
+    pub crawl_depth: usize,
+
+    /// Maximum number of concurrent requests for fetching URL inputs, sitemaps
+    /// and crawled pages while collecting the links. Checking the links is
+    /// limited by `--max-concurrency` instead
+    #[structopt(long, default_value = &MAX_FETCH_CONCURRENCY_STR)]
+    #[serde(default = "max_fetch_concurrency")]
+    pub max_fetch_concurrency: usize,
+
     /// Basic authentication support. E.g. `username:password`
     #[structopt(long)]
     #[serde(default)]
     pub basic_auth: Option<String>,
 
+    /// File containing the `basic_auth` credentials, e.g. a Docker secret.
+    /// Config file only
+    #[structopt(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub basic_auth_file: Option<PathBuf>,
+
     /// GitHub API token to use when checking github.com links, to avoid rate limiting
     #[structopt(long, env = "GITHUB_TOKEN")]
     #[serde(default)]
     pub github_token: Option<String>,
 
+    /// File containing the `github_token`. Config file only
+    #[structopt(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_token_file: Option<PathBuf>,
+
+    /// GitLab API token to use when checking git remotes on GitLab (e.g.
+    /// `git@gitlab.com:group/project.git`)
+    #[structopt(long, env = "GITLAB_TOKEN")]
+    #[serde(default)]
+    pub gitlab_token: Option<String>,
+
+    /// File containing the `gitlab_token`. Config file only
+    #[structopt(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gitlab_token_file: Option<PathBuf>,
+
+    /// Token endpoint for authenticating with the OAuth2 client credentials
+    /// flow. The bearer token is only sent to the `--oauth2-host`s
+    #[structopt(long)]
+    #[serde(default)]
+    pub oauth2_token_url: Option<String>,
+
+    /// Client id for `--oauth2-token-url`
+    #[structopt(long)]
+    #[serde(default)]
+    pub oauth2_client_id: Option<String>,
+
+    /// Client secret for `--oauth2-token-url`
+    #[structopt(long, env = "OAUTH2_CLIENT_SECRET", hide_env_values = true)]
+    #[serde(default)]
+    pub oauth2_client_secret: Option<String>,
+
+    /// File containing the `oauth2_client_secret`. Config file only
+    #[structopt(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth2_client_secret_file: Option<PathBuf>,
+
+    /// Scope to request with `--oauth2-token-url`. Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub oauth2_scope: Vec<String>,
+
+    /// Host to send the OAuth2 token to, as a glob pattern (e.g. `*.example.com`).
+    /// Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub oauth2_host: Vec<String>,
+
+    /// Only check local files which changed since the given git ref (e.g. `origin/main`).
+    /// Globs only match changed Markdown, HTML, sitemap and notebook files, while
+    /// explicitly given files are checked if they changed, whatever their type
+    #[structopt(long)]
+    #[serde(default)]
+    pub since: Option<String>,
+
+    /// Regular expression for finding links in plaintext inputs, in addition to the default
+    /// link finder. The first capture group is used as the URL. Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub plaintext_pattern: Vec<String>,
+
+    /// Only use `--plaintext-pattern` to find links in plaintext inputs,
+    /// instead of the default link finder
+    #[structopt(long, requires = "plaintext-pattern")]
+    #[serde(default)]
+    pub plaintext_pattern_only: bool,
+
+    /// Additional HTML attribute holding a URL, e.g. `data-permalink`.
+    /// `data-src`, `data-srcset`, `data-href` and `data-url` are always checked.
+    /// Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub link_attribute: Vec<String>,
+
+    /// Don't check images, e.g. from `<img src>`, `srcset` or `![](...)`.
+    /// Images which are also used as regular links still get checked
+    #[structopt(long)]
+    #[serde(default)]
+    pub skip_images: bool,
+
+    /// Don't check resources loaded by pages, e.g. scripts, stylesheets and videos
+    #[structopt(long)]
+    #[serde(default)]
+    pub skip_assets: bool,
+
+    /// Exclude links whose text matches this regex, e.g. `(?i)^example$` for
+    /// links titled "example". Matches the text of Markdown links and images
+    /// and of HTML `<a>` elements. Can be given multiple times
+    #[structopt(long)]
+    #[serde(default)]
+    pub exclude_link_text: Vec<String>,
+
     /// Skip missing input files (default is to error if they don't exist)
     #[structopt(long)]
     #[serde(default)]
@@ -233,15 +849,64 @@ pub struct Config {
     #[serde(default)]
     pub glob_ignore_case: bool,
 
-    /// Output file of status report
-    #[structopt(short, long, parse(from_os_str))]
+    /// Treat input files which aren't valid UTF-8 as unreadable.
+    /// By default they get decoded from their declared charset or Windows-1252,
+    /// and are listed as warnings in the summary
+    #[structopt(long)]
     #[serde(default)]
-    pub output: Option<PathBuf>,
+    pub strict_encoding: bool,
 
-    /// Output file format of status report (json, string)
+    /// Output file of status report. Add `:<format>` to override `--format` for that file
+    /// (e.g. `results.json:json results.md:markdown`). Can be given multiple times
+    #[structopt(short, long)]
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub output: Vec<OutputFile>,
+
+    /// Output file format of status report (json, markdown, prometheus, string, failures).
+    /// `failures` lists the URLs of the failed links only, one per line and sorted
     #[structopt(short, long, default_value = "string")]
     #[serde(default)]
     pub format: Format,
+
+    /// Don't use emoji in the Markdown report
+    #[structopt(long)]
+    #[serde(default)]
+    pub no_emoji: bool,
+
+    /// Print the broken links as GitHub Actions annotations (`::error file=...,line=...::`),
+    /// which show up on the lines of the changed files in pull requests
+    #[structopt(long)]
+    #[serde(default)]
+    pub github_annotations: bool,
+
+    /// Headers required in the responses to links matching a regular
+    /// expression, given as `Header` or `Header: value`. Config file only, e.g.
+    /// `[require_headers]` with `"\\.zip$" = ["Content-Disposition"]`.
+    /// Kept last, as TOML tables have to follow all plain values
+    #[structopt(skip)]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub require_headers: BTreeMap<String, Vec<String>>,
+
+    /// Regular expressions which the bodies of links matching a regular expression
+    /// must match, e.g. `[require_content]` with `"^https://docs\\.example\\.com/$" =
+    /// ["Getting Started"]`. Config file only
+    #[structopt(skip)]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub require_content: BTreeMap<String, Vec<String>>,
+
+    /// Like `require_content`, but the bodies mustn't match, e.g. to catch
+    /// placeholder text. Config file only
+    #[structopt(skip)]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub forbid_content: BTreeMap<String, Vec<String>>,
+
+    /// Rewrite links matching a regular expression before checking them,
+    /// e.g. `[rewrite]` with `"//docs\\.internal/" = "//docs.staging.internal/"`.
+    /// The replacement may refer to capture groups, e.g. `$1`. If several
+    /// patterns match, all get applied in alphabetical order. Config file only
+    #[structopt(skip)]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rewrite: BTreeMap<String, String>,
 }
 
 impl Config {
@@ -261,7 +926,58 @@ impl Config {
             }
         };
 
-        Ok(Some(toml::from_slice(&contents)?))
+        let mut config: Config = toml::from_slice(&contents)?;
+        config.read_secret_files(Path::new(path))?;
+        Ok(Some(config))
+    }
+
+    /// Read the secrets referenced by the `*_file` keys, e.g. Docker or
+    /// Kubernetes secrets. Relative paths are relative to the config file.
+    fn read_secret_files(&mut self, config_path: &Path) -> Result<()> {
+        let dir = config_path.parent().unwrap_or_else(|| Path::new(""));
+        read_secret_file(
+            "basic_auth",
+            &mut self.basic_auth,
+            &self.basic_auth_file,
+            dir,
+        )?;
+        read_secret_file(
+            "github_token",
+            &mut self.github_token,
+            &self.github_token_file,
+            dir,
+        )?;
+        read_secret_file(
+            "gitlab_token",
+            &mut self.gitlab_token,
+            &self.gitlab_token_file,
+            dir,
+        )?;
+        read_secret_file(
+            "oauth2_client_secret",
+            &mut self.oauth2_client_secret,
+            &self.oauth2_client_secret_file,
+            dir,
+        )
+    }
+
+    /// Render the configuration in the given format with all secrets redacted
+    pub(crate) fn to_redacted_string(&self, format: &Format) -> Result<String> {
+        let mut cfg = self.clone();
+        let redact = |secret: &mut Option<String>| {
+            if secret.is_some() {
+                *secret = Some(REDACTED.to_string());
+            }
+        };
+        redact(&mut cfg.basic_auth);
+        redact(&mut cfg.github_token);
+        redact(&mut cfg.gitlab_token);
+        redact(&mut cfg.oauth2_client_secret);
+
+        match format {
+            Format::Json => Ok(serde_json::to_string_pretty(&cfg)?),
+            _ => Ok(toml::to_string(&cfg)?),
+        }
     }
 
     /// Merge the configuration from TOML into the CLI configuration
@@ -272,30 +988,141 @@ impl Config {
 
             // Keys with defaults to assign
             verbose: false;
+            verbose_errors: false;
+            summary_only: false;
+            top_hosts: None;
             progress: false;
+            force_progress: false;
+            tui: false;
+            ordered_output: false;
+            heartbeat: HEARTBEAT;
             max_redirects: MAX_REDIRECTS;
+            max_permanent_redirects: None;
+            max_temporary_redirects: None;
+            fix: false;
+            wayback_fallback: false;
+            bench_extract: false;
             max_concurrency: MAX_CONCURRENCY;
+            auto_concurrency: false;
+            max_links: None;
+            max_links_per_host: None;
+            truncate_links: false;
+            max_total_retries: None;
+            cache: None;
+            resume: None;
+            keep_checkpoint: false;
             threads: None;
             user_agent: USER_AGENT;
+            browser_ua: false;
             insecure: false;
+            http_version: HTTP_VERSION;
+            pool_max_idle_per_host: None;
+            pool_idle_timeout: None;
+            tcp_keepalive: None;
+            cacert: Vec::<PathBuf>::new();
+            client_cert: None;
+            client_key: None;
+            fallback_dns: Vec::<String>::new();
+            preflight: false;
+            preflight_url: Vec::<String>::new();
             scheme: None;
             include: Vec::<String>::new();
             exclude: Vec::<String>::new();
+            exclude_hosts: Vec::<String>::new();
+            exclude_extensions: Vec::<String>::new();
             exclude_all_private: false;
             exclude_private: false;
             exclude_link_local: false;
             exclude_loopback: false;
             exclude_mail: false;
+            uncheckable_scheme: Vec::<String>::new();
+            check_mail_reachability: false;
             headers: Vec::<String>::new();
             accept: None;
+            accept_url: Vec::<String>::new();
+            retry_on_status: None;
+            detect_auth_required: false;
+            fail_on_auth_required: false;
+            strict: false;
+            fail_on_input_errors: false;
+            required_host: Vec::<String>::new();
+            own_host: Vec::<String>::new();
+            fail_on_own_host_links: false;
+            max_failures: None;
+            max_failure_percent: None;
             timeout: TIMEOUT;
+            scheme_timeout: Vec::<String>::new();
+            aux_timeout: AUX_TIMEOUT;
+            host_policy: Vec::<String>::new();
+            no_default_host_policies: false;
+            rate_limit: None;
+            rate_limit_per_host: false;
+            respect_crawl_delay: false;
+            delay: None;
+            check_image_types: false;
+            expect_content_type: Vec::<String>::new();
+            check_anchors: false;
+            anchor_style: ANCHOR_STYLE;
+            anchors_ignore_case: false;
+            soft_404_pattern: Vec::<String>::new();
+            expect_content: Vec::<String>::new();
+            min_content_length: MIN_CONTENT_LENGTH;
             method: METHOD;
+            host_method: Vec::<String>::new();
             base_url: None;
+            input_base_url: Vec::<String>::new();
+            crawl_depth: 0;
+            max_fetch_concurrency: MAX_FETCH_CONCURRENCY;
             basic_auth: None;
             github_token: None;
+            gitlab_token: None;
+            oauth2_token_url: None;
+            oauth2_client_id: None;
+            oauth2_client_secret: None;
+            oauth2_scope: Vec::<String>::new();
+            oauth2_host: Vec::<String>::new();
+            since: None;
+            plaintext_pattern: Vec::<String>::new();
+            plaintext_pattern_only: false;
+            link_attribute: Vec::<String>::new();
+            skip_images: false;
+            skip_assets: false;
+            exclude_link_text: Vec::<String>::new();
             skip_missing: false;
             glob_ignore_case: false;
-            output: None;
+            strict_encoding: false;
+            output: Vec::<OutputFile>::new();
+            no_emoji: false;
+            github_annotations: false;
+            require_headers: BTreeMap::<String, Vec<String>>::new();
+            require_content: BTreeMap::<String, Vec<String>>::new();
+            forbid_content: BTreeMap::<String, Vec<String>>::new();
+            rewrite: BTreeMap::<String, String>::new();
         }
     }
 }
+
+/// Read a secret from the file given for `<key>_file` into `secret`,
+/// dropping the trailing newline most editors and `echo` add
+fn read_secret_file(
+    key: &str,
+    secret: &mut Option<String>,
+    file: &Option<PathBuf>,
+    dir: &Path,
+) -> Result<()> {
+    let file = match file {
+        Some(file) => dir.join(file),
+        None => return Ok(()),
+    };
+    if secret.is_some() {
+        bail!(
+            "Both `{}` and `{}_file` are set in the config file, only use one of them",
+            key,
+            key
+        );
+    }
+    let value = fs::read_to_string(&file)
+        .with_context(|| format!("Cannot read `{}_file` {}", key, file.display()))?;
+    *secret = Some(value.trim_end_matches(&['\r', '\n'][..]).to_string());
+    Ok(())
+}
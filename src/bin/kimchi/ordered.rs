@@ -0,0 +1,92 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use kimchi::{collector::Input, Request, Response, Uri};
+use tokio::sync::Semaphore;
+
+/// Restores the order in which links were sent off for checking,
+/// while the checks themselves still run concurrently.
+///
+/// At most `window` links can be in flight or waiting for an earlier one
+/// to finish, so a single slow link can't make the buffer grow unbounded.
+/// The sender has to take a permit from `window()` before sending a link.
+pub(crate) struct ReorderBuffer {
+    positions: HashMap<(Uri, Input), usize>,
+    pending: BTreeMap<usize, Response>,
+    next: usize,
+    window: Arc<Semaphore>,
+}
+
+impl ReorderBuffer {
+    pub(crate) fn new(links: &[Request], window: usize) -> Self {
+        let positions = links
+            .iter()
+            .enumerate()
+            .map(|(pos, link)| ((link.uri.clone(), link.source.clone()), pos))
+            .collect();
+        Self {
+            positions,
+            pending: BTreeMap::new(),
+            next: 0,
+            window: Arc::new(Semaphore::new(window.max(1))),
+        }
+    }
+
+    pub(crate) fn window(&self) -> Arc<Semaphore> {
+        self.window.clone()
+    }
+
+    /// Add a response and return all responses which are now due, in order
+    pub(crate) fn push(&mut self, response: Response) -> Vec<Response> {
+        let key = (response.uri.clone(), response.source.clone());
+        let pos = match self.positions.remove(&key) {
+            Some(pos) => pos,
+            // Not sent by us, so there is no order to restore
+            None => return vec![response],
+        };
+        self.pending.insert(pos, response);
+
+        let mut due = vec![];
+        while let Some(response) = self.pending.remove(&self.next) {
+            due.push(response);
+            self.next += 1;
+        }
+        self.window.add_permits(due.len());
+        due
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use kimchi::{test_utils::website, Status};
+
+    fn response(url: &str) -> Response {
+        Response::new(website(url), Status::Ok(http::StatusCode::OK), Input::Stdin)
+    }
+
+    #[test]
+    fn test_reorder_buffer() {
+        let links: Vec<Request> = ["https://a.org", "https://b.org", "https://c.org"]
+            .iter()
+            .map(|url| Request::new(website(url), Input::Stdin))
+            .collect();
+        let mut buffer = ReorderBuffer::new(&links, 3);
+
+        assert!(buffer.push(response("https://c.org")).is_empty());
+        assert!(buffer.push(response("https://b.org")).is_empty());
+        let due: Vec<String> = buffer
+            .push(response("https://a.org"))
+            .iter()
+            .map(|r| r.uri.to_string())
+            .collect();
+        assert_eq!(
+            due,
+            vec!["https://a.org/", "https://b.org/", "https://c.org/"]
+        );
+        // Every response handed out frees a slot for the sender
+        assert_eq!(buffer.window().available_permits(), 6);
+    }
+}
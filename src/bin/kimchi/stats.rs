@@ -1,16 +1,59 @@
 use pad::{Alignment, PadStr};
-use serde::Serialize;
+use regex::RegexSet;
+use serde::{Serialize, Serializer};
 
 use std::{
-    collections::{HashMap, HashSet},
-    fmt::{self, Display},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::{self, Display, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use kimchi::{collector::Input, Response, Status::*};
+use kimchi::{
+    collector::{Input, InputError},
+    RateLimit, Response, Status,
+    Status::*,
+    Uri,
+};
 
 // Maximum padding for each entry in the final statistics output
 const MAX_PADDING: usize = 20;
 
+/// Version of the structure of the JSON output.
+/// Bump it on breaking changes, so consumers can detect them.
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// An absolute link to one of the own hosts, which should be relative
+/// instead, see `--own-host`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OwnHostLink {
+    pub input: Input,
+    /// Line of the first use of the link in the input, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub link: String,
+}
+
+impl Display for OwnHostLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.input, line, self.link),
+            None => write!(f, "{}: {}", self.input, self.link),
+        }
+    }
+}
+
+/// The JSON output: metadata about the run, wrapping the statistics
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    schema_version: u32,
+    kimchi_version: &'static str,
+    /// When the report was created, in seconds since the Unix epoch
+    timestamp: u64,
+    /// How long checking the links took, in seconds
+    duration: f64,
+    results: &'a ResponseStats,
+}
+
 #[derive(Serialize)]
 pub struct ResponseStats {
     total: usize,
@@ -20,7 +63,68 @@ pub struct ResponseStats {
     redirects: usize,
     excludes: usize,
     errors: usize,
+    auth_required: usize,
+    /// Failures of links which aren't required, see `required_hosts`
+    #[serde(skip_serializing_if = "is_zero")]
+    not_required: usize,
+    /// Links which can't be checked, e.g. `ftp` links
+    #[serde(skip_serializing_if = "is_zero")]
+    unsupported: usize,
+    /// Links whose TLS connection failed, e.g. because of an expired certificate
+    #[serde(skip_serializing_if = "is_zero")]
+    tls_errors: usize,
+    /// Links lacking a required header, see `require_headers`
+    #[serde(skip_serializing_if = "is_zero")]
+    header_mismatches: usize,
+    /// Links lacking required text or containing forbidden text, see `require_content`
+    #[serde(skip_serializing_if = "is_zero")]
+    content_mismatches: usize,
+    /// Broken links with a snapshot in the Wayback Machine, see `wayback_fallback`
+    #[serde(skip_serializing_if = "is_zero")]
+    archived: usize,
     fail_map: HashMap<Input, HashSet<Response>>,
+    /// Number of failed links per status code or error kind, see `failure_kind`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    failures_by_status: BTreeMap<String, usize>,
+    /// Lowest rate limit quota reported per host
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    rate_limits: HashMap<String, RateLimit>,
+    /// Inputs which couldn't be read
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    input_errors: Vec<InputError>,
+    /// Inputs which got read despite a problem, e.g. an encoding other than UTF-8
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    input_warnings: Vec<InputError>,
+    /// Working links which only got redirected to another host name or
+    /// scheme, with their canonical form, see `Response::canonical_host`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    non_canonical_hosts: BTreeMap<String, String>,
+    /// Number of links per host which didn't get checked because of
+    /// `--max-links-per-host`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    skipped_per_host: BTreeMap<String, usize>,
+    /// Absolute links to the own hosts, a policy violation
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    own_host_links: Vec<OwnHostLink>,
+    /// Health of the linked hosts, only tracked if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hosts: Option<HostHealth>,
+    #[serde(skip)]
+    strict: bool,
+    #[serde(skip)]
+    fail_on_auth_required: bool,
+    #[serde(skip)]
+    fail_on_input_errors: bool,
+    #[serde(skip)]
+    fail_on_own_host_links: bool,
+    #[serde(skip)]
+    required_hosts: Option<RegexSet>,
+    #[serde(skip)]
+    verbose_errors: bool,
+    #[serde(skip)]
+    max_failures: Option<usize>,
+    #[serde(skip)]
+    max_failure_percent: Option<f64>,
 }
 
 impl ResponseStats {
@@ -34,33 +138,745 @@ impl ResponseStats {
             redirects: 0,
             excludes: 0,
             errors: 0,
+            auth_required: 0,
+            not_required: 0,
+            unsupported: 0,
+            tls_errors: 0,
+            header_mismatches: 0,
+            content_mismatches: 0,
+            archived: 0,
             fail_map,
+            failures_by_status: BTreeMap::new(),
+            rate_limits: HashMap::new(),
+            input_errors: vec![],
+            input_warnings: vec![],
+            non_canonical_hosts: BTreeMap::new(),
+            skipped_per_host: BTreeMap::new(),
+            own_host_links: vec![],
+            hosts: None,
+            strict: false,
+            fail_on_auth_required: false,
+            fail_on_input_errors: false,
+            fail_on_own_host_links: false,
+            required_hosts: None,
+            verbose_errors: false,
+            max_failures: None,
+            max_failure_percent: None,
+        }
+    }
+
+    /// Only count clean 2xx responses as successful,
+    /// regardless of the accepted status codes
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Count links which require authentication as failures.
+    /// Implied by strict mode.
+    pub fn fail_on_auth_required(mut self, fail: bool) -> Self {
+        self.fail_on_auth_required = fail;
+        self
+    }
+
+    /// Fail the run if any input couldn't be read
+    pub fn fail_on_input_errors(mut self, fail: bool) -> Self {
+        self.fail_on_input_errors = fail;
+        self
+    }
+
+    /// Fail the run if there are absolute links to the own hosts
+    pub fn fail_on_own_host_links(mut self, fail: bool) -> Self {
+        self.fail_on_own_host_links = fail;
+        self
+    }
+
+    /// Show the causes of runtime errors in the summary
+    pub fn verbose_errors(mut self, verbose_errors: bool) -> Self {
+        self.verbose_errors = verbose_errors;
+        self
+    }
+
+    /// Only let failing links to these hosts (see `Excludes::compile_host_globs`)
+    /// fail the run. Failures of other links are still reported.
+    /// Links to local files are always required.
+    pub fn required_hosts(mut self, hosts: Option<RegexSet>) -> Self {
+        self.required_hosts = hosts;
+        self
+    }
+
+    /// Let the run pass with at most this many failing links.
+    /// Failures are still reported. Ignored in strict mode.
+    pub fn max_failures(mut self, max_failures: Option<usize>) -> Self {
+        self.max_failures = max_failures;
+        self
+    }
+
+    /// Let the run pass with at most this percentage of the checked
+    /// (i.e. not excluded) links failing. Ignored in strict mode.
+    pub fn max_failure_percent(mut self, percent: Option<f64>) -> Self {
+        self.max_failure_percent = percent;
+        self
+    }
+
+    /// Track the number of links, success rate and median response time
+    /// per host. The summary lists the `top` least healthy hosts, or all
+    /// hosts if `top` is `None`.
+    pub fn host_summary(mut self, enabled: bool, top: Option<usize>) -> Self {
+        self.hosts = match enabled {
+            true => Some(HostHealth {
+                top,
+                ..HostHealth::default()
+            }),
+            false => None,
+        };
+        self
+    }
+
+    /// Whether the failing links are within the budget of
+    /// `max_failures` and `max_failure_percent`
+    fn within_failure_budget(&self, failed: usize) -> bool {
+        if failed == 0 {
+            return true;
+        }
+        if self.strict || (self.max_failures.is_none() && self.max_failure_percent.is_none()) {
+            return false;
+        }
+        let checked = self.total - self.excludes - self.unsupported;
+        let within_count = self.max_failures.map_or(true, |max| failed <= max);
+        let within_percent = self
+            .max_failure_percent
+            .map_or(true, |max| failed as f64 * 100.0 <= max * checked as f64);
+        within_count && within_percent
+    }
+
+    fn is_required(&self, uri: &Uri) -> bool {
+        match (&self.required_hosts, uri) {
+            (None, _) => true,
+            (Some(_), Uri::Website(url)) if url.scheme() == "file" => true,
+            (Some(hosts), uri) => uri
+                .domain()
+                .map_or(false, |domain| hosts.is_match(domain.trim_end_matches('.'))),
+        }
+    }
+
+    pub fn add_input_errors(&mut self, errors: Vec<InputError>) {
+        self.input_errors.extend(errors);
+    }
+
+    /// Warnings don't affect the outcome of the run
+    pub fn add_input_warnings(&mut self, warnings: Vec<InputError>) {
+        self.input_warnings.extend(warnings);
+    }
+
+    pub fn add_own_host_links(&mut self, links: Vec<OwnHostLink>) {
+        self.own_host_links.extend(links);
+    }
+
+    /// Record the links left unchecked per host, see `--max-links-per-host`
+    pub fn add_skipped_per_host(&mut self, skipped: BTreeMap<String, usize>) {
+        for (host, count) in skipped {
+            *self.skipped_per_host.entry(host).or_default() += count;
         }
     }
 
+    /// Adjust the status of a response to the verdict of these statistics.
+    /// In strict mode, accepted non-2xx status codes are turned back into
+    /// redirects or failures.
+    pub fn judge(&self, mut response: Response) -> Response {
+        if self.strict {
+            response.status = match response.status {
+                Ok(code) if code.is_redirection() => Redirected(code),
+                Ok(code) if !code.is_success() => Failed(code),
+                status => status,
+            };
+        }
+        if self.strict || self.fail_on_auth_required {
+            if let AuthRequired(code) = response.status {
+                response.status = Failed(code);
+            }
+        }
+        response
+    }
+
     pub fn add(&mut self, response: Response) {
+        let response = self.judge(response);
         self.total += 1;
+        if let (Some(rate_limit), Some(host)) = (response.rate_limit(), response.uri.domain()) {
+            let lowest = self
+                .rate_limits
+                .entry(host.to_string())
+                .or_insert(rate_limit);
+            if rate_limit.remaining < lowest.remaining {
+                *lowest = rate_limit;
+            }
+        }
+        if let (Some(hosts), Some(host)) = (&mut self.hosts, response.uri.domain()) {
+            hosts.add(host, &response.status, response.duration);
+        }
+        if let (Uri::Website(url), Some(canonical)) = (&response.uri, response.canonical_host()) {
+            self.non_canonical_hosts
+                .insert(url.to_string(), canonical.to_string());
+        }
         match response.status {
-            Failed(_) => self.failures += 1,
+            Failed(_) | Invalid(_) | RedirectLoop(_) => self.failures += 1,
             Timeout(_) => self.timeouts += 1,
             Redirected(_) => self.redirects += 1,
             Excluded => self.excludes += 1,
             Error(_) => self.errors += 1,
+            Tls(_) => self.tls_errors += 1,
+            HeaderMismatch(_) => self.header_mismatches += 1,
+            ContentMismatch(_) => self.content_mismatches += 1,
+            AuthRequired(_) => self.auth_required += 1,
+            Unsupported(_) => self.unsupported += 1,
             _ => self.successful += 1,
         }
 
-        if matches!(
-            response.status,
-            Failed(_) | Timeout(_) | Redirected(_) | Error(_)
-        ) {
+        if is_failure(&response.status) {
+            if !self.is_required(&response.uri) {
+                self.not_required += 1;
+            }
+            if response.archived().is_some() {
+                self.archived += 1;
+            }
+            *self
+                .failures_by_status
+                .entry(failure_kind(&response.status))
+                .or_default() += 1;
             let fail = self.fail_map.entry(response.source.clone()).or_default();
             fail.insert(response);
         };
     }
 
+    /// Whether the run passed. The status of a link gets decided in this order:
+    /// 1. Excluded links and links which can't be checked never fail.
+    /// 2. `judge` applies strict mode and `fail_on_auth_required`,
+    ///    otherwise links requiring authentication don't fail.
+    /// 3. The remaining failures only count if the link is required.
+    /// 4. The run still passes if the failures are within the budget of
+    ///    `max_failures` and `max_failure_percent`, unless in strict mode.
     pub fn is_success(&self) -> bool {
-        self.total == self.successful + self.excludes
+        let failed = self.total
+            - self.successful
+            - self.excludes
+            - self.unsupported
+            - self.auth_required
+            - self.not_required;
+        self.within_failure_budget(failed)
+            && !(self.fail_on_input_errors && !self.input_errors.is_empty())
+            && !(self.fail_on_own_host_links && !self.own_host_links.is_empty())
+    }
+
+    /// Number of links which failed in any way, whether they're required or not
+    fn failed(&self) -> usize {
+        self.failures_by_status.values().sum()
+    }
+
+    /// The failures per status, most frequent first, e.g. `10× 404, 2× timeout`
+    fn failure_histogram(&self) -> String {
+        let mut counts: Vec<_> = self.failures_by_status.iter().collect();
+        counts.sort_by(|(a_kind, a), (b_kind, b)| b.cmp(a).then_with(|| a_kind.cmp(b_kind)));
+        counts
+            .into_iter()
+            .map(|(kind, count)| format!("{}× {}", count, kind))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Render the statistics as JSON, wrapped in an envelope with the
+    /// `schema_version` of the output and metadata about the run
+    pub fn to_json(&self, duration: Duration) -> serde_json::Result<String> {
+        let report = JsonReport {
+            schema_version: JSON_SCHEMA_VERSION,
+            kimchi_version: env!("CARGO_PKG_VERSION"),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            duration: duration.as_secs_f64(),
+            results: self,
+        };
+        serde_json::to_string_pretty(&report)
+    }
+
+    /// Render the statistics as OpenMetrics, e.g. for the textfile
+    /// collector of the Prometheus node exporter
+    pub fn to_prometheus(&self, duration: Duration) -> Result<String, fmt::Error> {
+        let mut out = String::new();
+        // With the host summary enabled, the link counts get a series per host
+        let mut hosts: Vec<_> = self
+            .hosts
+            .iter()
+            .flat_map(|hosts| hosts.tallies.iter())
+            .collect();
+        hosts.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let per_host = |count: fn(&HostTally) -> usize| {
+            hosts
+                .iter()
+                .map(|(host, tally)| (host.as_str(), count(tally)))
+                .collect::<Vec<_>>()
+        };
+        write_metric(
+            &mut out,
+            "kimchi_links_total",
+            "Number of links found",
+            self.total,
+        )?;
+        write_host_series(&mut out, "kimchi_links_total", &per_host(|t| t.total))?;
+        write_metric(
+            &mut out,
+            "kimchi_links_failed",
+            "Number of links which failed, timed out or got redirected",
+            self.failed(),
+        )?;
+        write_host_series(&mut out, "kimchi_links_failed", &per_host(|t| t.failed))?;
+        write_metric(
+            &mut out,
+            "kimchi_links_excluded",
+            "Number of links excluded from checking",
+            self.excludes,
+        )?;
+        write_host_series(&mut out, "kimchi_links_excluded", &per_host(|t| t.excluded))?;
+        write_metric(
+            &mut out,
+            "kimchi_check_duration_seconds",
+            "Time it took to check all links",
+            duration.as_secs_f64(),
+        )?;
+        writeln!(out, "# EOF")?;
+        Ok(out)
+    }
+
+    /// The URLs of all failed links, sorted and one per line, e.g. for `xargs`.
+    /// Links failing in several inputs are only listed once.
+    pub fn to_failure_list(&self) -> String {
+        let urls: BTreeSet<&str> = self
+            .fail_map
+            .values()
+            .flatten()
+            .map(|response| response.uri.as_str())
+            .collect();
+        urls.into_iter().map(|url| format!("{}\n", url)).collect()
+    }
+
+    /// Render the failures as GitHub Actions workflow commands, which show up
+    /// as annotations on the lines of the changed files.
+    /// Failures of links which aren't required are only warnings.
+    /// Links found in other inputs than files get annotations without a file.
+    pub fn to_github_annotations(&self) -> String {
+        let mut inputs: Vec<_> = self.fail_map.iter().collect();
+        inputs.sort_by_key(|(input, _)| input.to_string());
+        let mut out = String::new();
+        for (input, responses) in inputs {
+            let mut responses: Vec<_> = responses.iter().collect();
+            responses.sort_by_key(|response| (response.line, response.uri.as_str()));
+            for response in responses {
+                let level = match self.is_required(&response.uri) {
+                    true => "error",
+                    false => "warning",
+                };
+                let message = format!("Broken link {}: {}", response.uri, response.status);
+                let location = match (input, response.line) {
+                    (Input::FsPath(path), Some(line)) => format!(
+                        " file={},line={}",
+                        escape_property(&path.to_string_lossy()),
+                        line
+                    ),
+                    (Input::FsPath(path), None) => {
+                        format!(" file={}", escape_property(&path.to_string_lossy()))
+                    }
+                    _ => {
+                        out.push_str(&format!(
+                            "::{}::{} in {}\n",
+                            level,
+                            escape_data(&message),
+                            escape_data(&input.to_string())
+                        ));
+                        continue;
+                    }
+                };
+                out.push_str(&format!(
+                    "::{}{}::{}\n",
+                    level,
+                    location,
+                    escape_data(&message)
+                ));
+            }
+        }
+        out
+    }
+
+    /// Render the statistics as a Markdown document, which e.g. GitHub
+    /// displays natively when posted as a comment.
+    pub fn to_markdown(&self, emoji: bool) -> Result<String, fmt::Error> {
+        let label = |icon: &str, title: &str| {
+            if emoji {
+                format!("{} {}", icon, title)
+            } else {
+                title.to_string()
+            }
+        };
+
+        let mut out = String::new();
+        writeln!(out, "# {}\n", label("📝", "Summary"))?;
+        writeln!(out, "| Status | Count |")?;
+        writeln!(out, "|--------|------:|")?;
+        for (icon, title, stat) in &[
+            ("🔍", "Total", self.total),
+            ("✅", "Successful", self.successful),
+            ("⏳", "Timeouts", self.timeouts),
+            ("🔀", "Redirected", self.redirects),
+            ("👻", "Excluded", self.excludes),
+            ("🔒", "Auth required", self.auth_required),
+            ("🚫", "Errors", self.errors + self.failures),
+        ] {
+            writeln!(out, "| {} | {} |", label(icon, title), stat)?;
+        }
+        if self.not_required > 0 {
+            writeln!(
+                out,
+                "| {} | {} |",
+                label("💤", "Not required"),
+                self.not_required
+            )?;
+        }
+        if self.tls_errors > 0 {
+            writeln!(
+                out,
+                "| {} | {} |",
+                label("🔐", "TLS errors"),
+                self.tls_errors
+            )?;
+        }
+        if self.unsupported > 0 {
+            writeln!(
+                out,
+                "| {} | {} |",
+                label("❓", "Unsupported"),
+                self.unsupported
+            )?;
+        }
+        if self.header_mismatches > 0 {
+            writeln!(
+                out,
+                "| {} | {} |",
+                label("📋", "Header mismatches"),
+                self.header_mismatches
+            )?;
+        }
+        if self.content_mismatches > 0 {
+            writeln!(
+                out,
+                "| {} | {} |",
+                label("📄", "Content mismatches"),
+                self.content_mismatches
+            )?;
+        }
+        if self.archived > 0 {
+            writeln!(out, "| {} | {} |", label("🏛️", "Archived"), self.archived)?;
+        }
+
+        if !self.input_errors.is_empty() {
+            writeln!(out, "\n## {}\n", label("📂", "Input errors"))?;
+            writeln!(out, "| Input | Reason |")?;
+            writeln!(out, "|-------|--------|")?;
+            for error in &self.input_errors {
+                writeln!(
+                    out,
+                    "| {} | {} |",
+                    escape_markdown_cell(&error.input.to_string()),
+                    escape_markdown_cell(&error.reason)
+                )?;
+            }
+        }
+
+        if !self.input_warnings.is_empty() {
+            writeln!(out, "\n## {}\n", label("⚠️", "Input warnings"))?;
+            writeln!(out, "| Input | Reason |")?;
+            writeln!(out, "|-------|--------|")?;
+            for warning in &self.input_warnings {
+                writeln!(
+                    out,
+                    "| {} | {} |",
+                    escape_markdown_cell(&warning.input.to_string()),
+                    escape_markdown_cell(&warning.reason)
+                )?;
+            }
+        }
+
+        if !self.own_host_links.is_empty() {
+            writeln!(out, "\n## {}\n", label("🏠", "Own-host links"))?;
+            writeln!(out, "| Input | Line | Link |")?;
+            writeln!(out, "|-------|------|------|")?;
+            for link in &self.own_host_links {
+                writeln!(
+                    out,
+                    "| {} | {} | {} |",
+                    escape_markdown_cell(&link.input.to_string()),
+                    link.line.map(|line| line.to_string()).unwrap_or_default(),
+                    escape_markdown_cell(&link.link)
+                )?;
+            }
+        }
+
+        if !self.skipped_per_host.is_empty() {
+            writeln!(out, "\n## {}\n", label("⏭️", "Skipped per host"))?;
+            writeln!(out, "| Host | Skipped links |")?;
+            writeln!(out, "|------|---------------|")?;
+            for (host, count) in &self.skipped_per_host {
+                writeln!(out, "| {} | {} |", escape_markdown_cell(host), count)?;
+            }
+        }
+
+        if !self.non_canonical_hosts.is_empty() {
+            writeln!(out, "\n## {}\n", label("🏷️", "Non-canonical hosts"))?;
+            writeln!(out, "| Link | Canonical form |")?;
+            writeln!(out, "|------|----------------|")?;
+            for (link, canonical) in &self.non_canonical_hosts {
+                writeln!(
+                    out,
+                    "| {} | {} |",
+                    escape_markdown_cell(link),
+                    escape_markdown_cell(canonical)
+                )?;
+            }
+        }
+
+        if self.fail_map.is_empty() {
+            return Ok(out);
+        }
+
+        writeln!(out, "\n## {}\n", label("🚫", "Failed links"))?;
+        writeln!(out, "| URL | Status | Source |")?;
+        writeln!(out, "|-----|--------|--------|")?;
+        // Sort entries to get a stable report across runs
+        let mut failed: Vec<_> = self
+            .fail_map
+            .iter()
+            .flat_map(|(input, responses)| responses.iter().map(move |r| (input, r)))
+            .collect();
+        failed.sort_by_key(|(input, response)| (input.to_string(), response.uri.to_string()));
+        for (input, response) in failed {
+            let mut status = if emoji {
+                format!("{} {}", response.status.icon(), response.status)
+            } else {
+                response.status.to_string()
+            };
+            if let Some(label) = response.kind.label() {
+                status.push_str(&format!(" ({})", label));
+            }
+            writeln!(
+                out,
+                "| {} | {} | {} |",
+                escape_markdown_cell(response.uri.as_str()),
+                escape_markdown_cell(&status),
+                escape_markdown_cell(&input.to_string())
+            )?;
+        }
+        Ok(out)
+    }
+}
+
+/// A short label for the cause of a failure, grouping e.g. all `404`s or
+/// all DNS errors together to tell link rot apart from infrastructure issues
+fn failure_kind(status: &Status) -> String {
+    match status {
+        Failed(code) | Redirected(code) | AuthRequired(code) | Ok(code) => {
+            code.as_u16().to_string()
+        }
+        Timeout(_) => "timeout".to_string(),
+        Error(error) => {
+            let messages = std::iter::once(error.message())
+                .chain(error.causes().iter().map(String::as_str))
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>();
+            let mentions = |s: &str| messages.iter().any(|message| message.contains(s));
+            if mentions("dns error") || mentions("failed to lookup address") {
+                "DNS error".to_string()
+            } else if mentions("connect") {
+                "connection error".to_string()
+            } else {
+                "error".to_string()
+            }
+        }
+        Tls(_) => "TLS error".to_string(),
+        Invalid(_) => "invalid".to_string(),
+        HeaderMismatch(_) => "header mismatch".to_string(),
+        ContentMismatch(_) => "content mismatch".to_string(),
+        RedirectLoop(_) => "redirect loop".to_string(),
+        Excluded => "excluded".to_string(),
+        Unsupported(_) => "unsupported".to_string(),
+    }
+}
+
+/// Whether a link counts as failed, before deciding if it's required
+fn is_failure(status: &Status) -> bool {
+    matches!(
+        status,
+        Failed(_)
+            | Invalid(_)
+            | Timeout(_)
+            | Redirected(_)
+            | Error(_)
+            | Tls(_)
+            | HeaderMismatch(_)
+            | ContentMismatch(_)
+            | RedirectLoop(_)
+    )
+}
+
+/// Links and response times per host
+#[derive(Debug, Default)]
+struct HostTally {
+    /// All links to the host, including excluded ones
+    total: usize,
+    excluded: usize,
+    failed: usize,
+    /// Links which got checked
+    links: usize,
+    successful: usize,
+    durations: Vec<Duration>,
+}
+
+/// The health of the linked hosts, to spot slow or flaky dependencies
+#[derive(Debug, Default)]
+struct HostHealth {
+    tallies: HashMap<String, HostTally>,
+    /// Only report this many hosts, the least healthy first
+    top: Option<usize>,
+}
+
+/// A row of the host summary
+#[derive(Debug, PartialEq, Serialize)]
+struct HostSummary {
+    host: String,
+    links: usize,
+    /// Percentage of successful links
+    success_rate: f64,
+    median_response_time_ms: Option<u64>,
+}
+
+impl HostHealth {
+    fn add(&mut self, host: &str, status: &Status, duration: Option<Duration>) {
+        let tally = self
+            .tallies
+            .entry(host.trim_end_matches('.').to_string())
+            .or_default();
+        tally.total += 1;
+        if status.is_excluded() {
+            tally.excluded += 1;
+        }
+        if is_failure(status) {
+            tally.failed += 1;
+        }
+        if status.is_excluded() || status.is_unsupported() {
+            return;
+        }
+        tally.links += 1;
+        if status.is_success() {
+            tally.successful += 1;
+        }
+        tally.durations.extend(duration);
+    }
+
+    /// The hosts with the lowest success rate first,
+    /// ties broken by the slowest median response time
+    fn summary(&self) -> Vec<HostSummary> {
+        let mut summary: Vec<_> = self
+            .tallies
+            .iter()
+            // Hosts whose links all got excluded have no health to report
+            .filter(|(_, tally)| tally.links > 0)
+            .map(|(host, tally)| HostSummary {
+                host: host.clone(),
+                links: tally.links,
+                success_rate: tally.successful as f64 * 100.0 / tally.links as f64,
+                median_response_time_ms: median(&tally.durations)
+                    .map(|median| median.as_millis() as u64),
+            })
+            .collect();
+        summary.sort_by(|a, b| {
+            a.success_rate
+                .partial_cmp(&b.success_rate)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.median_response_time_ms.cmp(&a.median_response_time_ms))
+                .then_with(|| a.host.cmp(&b.host))
+        });
+        if let Some(top) = self.top {
+            summary.truncate(top);
+        }
+        summary
+    }
+}
+
+impl Serialize for HostHealth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.summary().serialize(serializer)
+    }
+}
+
+fn median(durations: &[Duration]) -> Option<Duration> {
+    let mut durations = durations.to_vec();
+    durations.sort();
+    let middle = durations.len() / 2;
+    match durations.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((durations[middle - 1] + durations[middle]) / 2),
+        _ => Some(durations[middle]),
+    }
+}
+
+/// Write a single gauge in the OpenMetrics text format
+fn write_metric<T: Display>(out: &mut String, name: &str, help: &str, value: T) -> fmt::Result {
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} gauge", name)?;
+    writeln!(out, "{} {}", name, value)
+}
+
+/// The samples of a metric per host, following its `write_metric` sample
+fn write_host_series(out: &mut String, name: &str, counts: &[(&str, usize)]) -> fmt::Result {
+    for (host, count) in counts {
+        writeln!(out, "{}{{host=\"{}\"}} {}", name, host, count)?;
+    }
+    Ok(())
+}
+
+/// Describe the quota left, e.g. `3/60 requests left, resets in 120s`
+pub(crate) fn format_rate_limit(rate_limit: &RateLimit) -> String {
+    let mut out = match rate_limit.limit {
+        Some(limit) => format!("{}/{} requests left", rate_limit.remaining, limit),
+        None => format!("{} requests left", rate_limit.remaining),
+    };
+    if let Some(reset) = rate_limit.reset {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        out.push_str(&format!(", resets in {}s", reset.saturating_sub(now)));
     }
+    out
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+fn escape_markdown_cell(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+/// Escape the message of a GitHub Actions workflow command
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property (e.g. `file`) of a GitHub Actions workflow command
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
 }
 
 fn write_stat(f: &mut fmt::Formatter, title: &str, stat: usize) -> fmt::Result {
@@ -85,7 +901,93 @@ impl Display for ResponseStats {
         write_stat(f, "⏳ Timeouts", self.timeouts)?;
         write_stat(f, "🔀 Redirected", self.redirects)?;
         write_stat(f, "👻 Excluded", self.excludes)?;
+        write_stat(f, "🔒 Auth required", self.auth_required)?;
         write_stat(f, "🚫 Errors", self.errors + self.failures)?;
+        if self.not_required > 0 {
+            write_stat(f, "💤 Not required", self.not_required)?;
+        }
+        if self.tls_errors > 0 {
+            write_stat(f, "🔐 TLS errors", self.tls_errors)?;
+        }
+        if self.unsupported > 0 {
+            write_stat(f, "❓ Unsupported", self.unsupported)?;
+        }
+        if self.header_mismatches > 0 {
+            write_stat(f, "📋 Header mismatches", self.header_mismatches)?;
+        }
+        if self.content_mismatches > 0 {
+            write_stat(f, "📄 Content mismatches", self.content_mismatches)?;
+        }
+        if self.archived > 0 {
+            write_stat(f, "🏛️ Archived", self.archived)?;
+        }
+
+        if !self.failures_by_status.is_empty() {
+            writeln!(f, "\n📊 Failures by status")?;
+            writeln!(f, "   {}", self.failure_histogram())?;
+        }
+
+        if !self.rate_limits.is_empty() {
+            writeln!(f, "\n⏱️ Rate limits")?;
+            let mut hosts: Vec<_> = self.rate_limits.iter().collect();
+            hosts.sort_by_key(|(host, _)| host.as_str());
+            for (host, rate_limit) in hosts {
+                writeln!(f, "   {}: {}", host, format_rate_limit(rate_limit))?;
+            }
+        }
+
+        if let Some(hosts) = &self.hosts {
+            let summary = hosts.summary();
+            if !summary.is_empty() {
+                writeln!(f, "\n🌐 Hosts")?;
+            }
+            for host in summary {
+                let median = match host.median_response_time_ms {
+                    Some(median) => format!(", median {}ms", median),
+                    None => String::new(),
+                };
+                writeln!(
+                    f,
+                    "   {}: {} links, {:.1}% successful{}",
+                    host.host, host.links, host.success_rate, median
+                )?;
+            }
+        }
+
+        if !self.input_errors.is_empty() {
+            writeln!(f, "\n📂 Input errors")?;
+            for error in &self.input_errors {
+                writeln!(f, "   {}\n      {}", error.input, error.reason)?;
+            }
+        }
+
+        if !self.input_warnings.is_empty() {
+            writeln!(f, "\n⚠️ Input warnings")?;
+            for warning in &self.input_warnings {
+                writeln!(f, "   {}\n      {}", warning.input, warning.reason)?;
+            }
+        }
+
+        if !self.own_host_links.is_empty() {
+            writeln!(f, "\n🏠 Own-host links (use relative links instead)")?;
+            for link in &self.own_host_links {
+                writeln!(f, "   {}", link)?;
+            }
+        }
+
+        if !self.skipped_per_host.is_empty() {
+            writeln!(f, "\n⏭️ Skipped per host (see --max-links-per-host)")?;
+            for (host, count) in &self.skipped_per_host {
+                writeln!(f, "   {}: {} links", host, count)?;
+            }
+        }
+
+        if !self.non_canonical_hosts.is_empty() {
+            writeln!(f, "\n🏷️ Non-canonical hosts")?;
+            for (link, canonical) in &self.non_canonical_hosts {
+                writeln!(f, "   {} → {}", link, canonical)?;
+            }
+        }
 
         if !&self.fail_map.is_empty() {
             writeln!(f)?;
@@ -93,13 +995,25 @@ impl Display for ResponseStats {
         for (input, responses) in &self.fail_map {
             writeln!(f, "Input: {}", input)?;
             for response in responses {
+                let status = match self.verbose_errors {
+                    true => format!("{:#}", response.status),
+                    false => response.status.to_string(),
+                };
+                let label = match response.kind.label() {
+                    Some(label) => format!(" ({})", label),
+                    None => String::new(),
+                };
                 writeln!(
                     f,
-                    "   {} {}\n      {}",
+                    "   {} {}{}\n      {}",
                     response.status.icon(),
                     response.uri,
-                    response.status
-                )?
+                    label,
+                    status
+                )?;
+                if let Some(snapshot) = response.archived() {
+                    writeln!(f, "      archived at {}", snapshot)?;
+                }
             }
         }
         writeln!(f)
@@ -108,46 +1022,514 @@ impl Display for ResponseStats {
 
 #[cfg(test)]
 mod test_super {
-    use kimchi::{test_utils::website, Status};
+    use kimchi::{test_utils::website, Excludes, Status};
 
     use super::*;
 
     #[test]
     fn test_stats() {
         let mut stats = ResponseStats::new();
-        stats.add(Response {
-            uri: website("http://example.org/ok"),
-            status: Status::Ok(http::StatusCode::OK),
-            source: Input::Stdin,
-        });
-        stats.add(Response {
-            uri: website("http://example.org/failed"),
-            status: Status::Failed(http::StatusCode::BAD_GATEWAY),
-            source: Input::Stdin,
-        });
-        stats.add(Response {
-            uri: website("http://example.org/redirect"),
-            status: Status::Redirected(http::StatusCode::PERMANENT_REDIRECT),
-            source: Input::Stdin,
-        });
+        stats.add(Response::new(
+            website("http://example.org/ok"),
+            Status::Ok(http::StatusCode::OK),
+            Input::Stdin,
+        ));
+        stats.add(Response::new(
+            website("http://example.org/failed"),
+            Status::Failed(http::StatusCode::BAD_GATEWAY),
+            Input::Stdin,
+        ));
+        stats.add(Response::new(
+            website("http://example.org/redirect"),
+            Status::Redirected(http::StatusCode::PERMANENT_REDIRECT),
+            Input::Stdin,
+        ));
         let mut expected_map = HashMap::new();
         expected_map.insert(
             Input::Stdin,
             vec![
-                Response {
-                    uri: website("http://example.org/failed"),
-                    status: Status::Failed(http::StatusCode::BAD_GATEWAY),
-                    source: Input::Stdin,
-                },
-                Response {
-                    uri: website("http://example.org/redirect"),
-                    status: Status::Redirected(http::StatusCode::PERMANENT_REDIRECT),
-                    source: Input::Stdin,
-                },
+                Response::new(
+                    website("http://example.org/failed"),
+                    Status::Failed(http::StatusCode::BAD_GATEWAY),
+                    Input::Stdin,
+                ),
+                Response::new(
+                    website("http://example.org/redirect"),
+                    Status::Redirected(http::StatusCode::PERMANENT_REDIRECT),
+                    Input::Stdin,
+                ),
             ]
             .into_iter()
             .collect::<HashSet<_>>(),
         );
         assert_eq!(stats.fail_map, expected_map);
     }
+
+    #[test]
+    fn test_host_summary() {
+        let response = |url: &str, status: Status, millis: u64| {
+            let mut response = Response::new(website(url), status, Input::Stdin);
+            response.duration = Some(Duration::from_millis(millis));
+            response
+        };
+        let ok = || Status::Ok(http::StatusCode::OK);
+        let mut stats = ResponseStats::new().host_summary(true, Some(2));
+        stats.add(response("https://a.org/1", ok(), 100));
+        stats.add(response("https://a.org/2", ok(), 300));
+        stats.add(response("https://b.org/1", ok(), 50));
+        stats.add(response(
+            "https://b.org/2",
+            Status::Failed(http::StatusCode::NOT_FOUND),
+            150,
+        ));
+        stats.add(response("https://c.org", ok(), 900));
+        stats.add(response("https://d.org", Status::Excluded, 0));
+
+        let summary = stats.hosts.as_ref().unwrap().summary();
+        assert_eq!(
+            summary,
+            vec![
+                HostSummary {
+                    host: "b.org".to_string(),
+                    links: 2,
+                    success_rate: 50.0,
+                    median_response_time_ms: Some(100),
+                },
+                HostSummary {
+                    host: "c.org".to_string(),
+                    links: 1,
+                    success_rate: 100.0,
+                    median_response_time_ms: Some(900),
+                },
+            ]
+        );
+        assert!(stats
+            .to_string()
+            .contains("b.org: 2 links, 50.0% successful, median 100ms"));
+
+        let stats = ResponseStats::new();
+        assert!(!stats.to_string().contains("Hosts"));
+    }
+
+    #[test]
+    fn test_failures_by_status() {
+        let mut stats = ResponseStats::new();
+        let mut add = |url: &str, status: Status| {
+            stats.add(Response::new(website(url), status, Input::Stdin));
+        };
+        let gone = || Status::Failed(http::StatusCode::NOT_FOUND);
+        add("https://example.org/a", gone());
+        add("https://example.org/b", gone());
+        add("https://example.org/c", Status::Timeout(None));
+        add(
+            "https://example.org/d",
+            Status::Error("error trying to connect: dns error: failed to lookup address".into()),
+        );
+        add("https://example.org/e", Status::Ok(http::StatusCode::OK));
+        add("https://example.org/f", Status::Excluded);
+
+        assert_eq!(
+            stats.failure_histogram(),
+            "2× 404, 1× DNS error, 1× timeout"
+        );
+        assert!(stats
+            .to_string()
+            .contains("Failures by status\n   2× 404, 1× DNS error, 1× timeout\n"));
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["failures_by_status"]["404"], 2);
+        assert_eq!(json["failures_by_status"]["timeout"], 1);
+
+        assert!(!ResponseStats::new()
+            .to_string()
+            .contains("Failures by status"));
+    }
+
+    #[test]
+    fn test_markdown() {
+        let mut stats = ResponseStats::new();
+        stats.add(Response::new(
+            website("http://example.org/ok"),
+            Status::Ok(http::StatusCode::OK),
+            Input::Stdin,
+        ));
+        stats.add(Response::new(
+            website("http://example.org/a|b"),
+            Status::Failed(http::StatusCode::NOT_FOUND),
+            Input::Stdin,
+        ));
+
+        let markdown = stats.to_markdown(false).unwrap();
+        assert!(markdown.starts_with("# Summary\n"));
+        assert!(markdown.contains("| Total | 2 |"));
+        assert!(markdown.contains("| Errors | 1 |"));
+        assert!(markdown.contains("| http://example.org/a\\|b | Failed (404 Not Found) | stdin |"));
+        assert!(!markdown.contains('✅'));
+
+        let markdown = stats.to_markdown(true).unwrap();
+        assert!(markdown.contains("| ✅ Successful | 1 |"));
+    }
+
+    #[test]
+    fn test_failure_list() {
+        let mut stats = ResponseStats::new();
+        let mut add = |url: &str, status: Status, input: Input| {
+            stats.add(Response::new(website(url), status, input));
+        };
+        let gone = || Status::Failed(http::StatusCode::NOT_FOUND);
+        add("https://example.org/b", gone(), Input::Stdin);
+        add("https://example.org/a", Status::Timeout(None), Input::Stdin);
+        add(
+            "https://example.org/b",
+            gone(),
+            Input::FsPath("README.md".into()),
+        );
+        add(
+            "https://example.org/ok",
+            Status::Ok(http::StatusCode::OK),
+            Input::Stdin,
+        );
+        add(
+            "https://example.org/excluded",
+            Status::Excluded,
+            Input::Stdin,
+        );
+        assert_eq!(
+            stats.to_failure_list(),
+            "https://example.org/a\nhttps://example.org/b\n"
+        );
+        assert_eq!(ResponseStats::new().to_failure_list(), "");
+    }
+
+    #[test]
+    fn test_github_annotations() {
+        let mut stats =
+            ResponseStats::new().required_hosts(Some(RegexSet::new(&["^example\\.org$"]).unwrap()));
+        let mut add = |url: &str, status: Status, input: Input, line: Option<usize>| {
+            let mut response = Response::new(website(url), status, input);
+            response.line = line;
+            stats.add(response);
+        };
+        let gone = || Status::Failed(http::StatusCode::NOT_FOUND);
+        let readme = || Input::FsPath("docs/a,b.md".into());
+        add("https://example.org/b", gone(), readme(), Some(7));
+        add("https://example.org/a", gone(), readme(), Some(3));
+        add("https://other.org/", gone(), readme(), None);
+        add(
+            "https://example.org/ok",
+            Status::Ok(http::StatusCode::OK),
+            readme(),
+            Some(1),
+        );
+        add(
+            "https://example.org/c",
+            Status::Timeout(None),
+            Input::Stdin,
+            None,
+        );
+        assert_eq!(
+            stats.to_github_annotations(),
+            "::warning file=docs/a%2Cb.md::Broken link https://other.org/: Failed (404 Not Found)\n\
+            ::error file=docs/a%2Cb.md,line=3::Broken link https://example.org/a: Failed (404 Not Found)\n\
+            ::error file=docs/a%2Cb.md,line=7::Broken link https://example.org/b: Failed (404 Not Found)\n\
+            ::error::Broken link https://example.org/c: Timeout in stdin\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let mut stats = ResponseStats::new();
+        stats.add(Response::new(
+            website("http://example.org/failed"),
+            Status::Failed(http::StatusCode::NOT_FOUND),
+            Input::Stdin,
+        ));
+
+        let json = stats.to_json(Duration::from_millis(1500)).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["schema_version"], JSON_SCHEMA_VERSION);
+        assert_eq!(json["kimchi_version"], env!("CARGO_PKG_VERSION"));
+        assert!(json["timestamp"].as_u64().unwrap() > 0);
+        assert_eq!(json["duration"], 1.5);
+        assert_eq!(json["results"]["total"], 1);
+        assert_eq!(json["results"]["failures"], 1);
+    }
+
+    #[test]
+    fn test_prometheus() {
+        let mut stats = ResponseStats::new();
+        stats.add(Response::new(
+            website("http://example.org/ok"),
+            Status::Ok(http::StatusCode::OK),
+            Input::Stdin,
+        ));
+        stats.add(Response::new(
+            website("http://example.org/failed"),
+            Status::Failed(http::StatusCode::NOT_FOUND),
+            Input::Stdin,
+        ));
+        stats.add(Response::new(
+            website("http://example.org/excluded"),
+            Status::Excluded,
+            Input::Stdin,
+        ));
+
+        let metrics = stats.to_prometheus(Duration::from_millis(1500)).unwrap();
+        assert!(metrics.contains("# TYPE kimchi_links_total gauge\nkimchi_links_total 3\n"));
+        assert!(metrics.contains("\nkimchi_links_failed 1\n"));
+        assert!(metrics.contains("\nkimchi_links_excluded 1\n"));
+        assert!(metrics.contains("\nkimchi_check_duration_seconds 1.5\n"));
+        assert!(metrics.ends_with("# EOF\n"));
+        assert!(!metrics.contains("host="));
+
+        // Links requiring authentication count neither as successful nor as failed
+        let mut stats = ResponseStats::new().host_summary(true, Some(1));
+        stats.add(Response::new(
+            website("http://example.org/private"),
+            Status::AuthRequired(http::StatusCode::UNAUTHORIZED),
+            Input::Stdin,
+        ));
+        stats.add(Response::new(
+            website("http://example.com/failed"),
+            Status::Failed(http::StatusCode::NOT_FOUND),
+            Input::Stdin,
+        ));
+        stats.add(Response::new(
+            website("http://example.com/excluded"),
+            Status::Excluded,
+            Input::Stdin,
+        ));
+
+        let metrics = stats.to_prometheus(Duration::from_millis(1500)).unwrap();
+        assert!(metrics.contains(
+            "kimchi_links_total 3\n\
+             kimchi_links_total{host=\"example.com\"} 2\n\
+             kimchi_links_total{host=\"example.org\"} 1\n"
+        ));
+        assert!(metrics.contains(
+            "kimchi_links_failed 1\n\
+             kimchi_links_failed{host=\"example.com\"} 1\n\
+             kimchi_links_failed{host=\"example.org\"} 0\n"
+        ));
+        assert!(metrics.contains("kimchi_links_excluded{host=\"example.com\"} 1\n"));
+    }
+
+    #[test]
+    fn test_strict() {
+        let mut stats = ResponseStats::new();
+        stats.add(Response::new(
+            website("http://example.org/moved"),
+            Status::Ok(http::StatusCode::MOVED_PERMANENTLY),
+            Input::Stdin,
+        ));
+        assert!(stats.is_success());
+
+        let mut stats = ResponseStats::new().strict(true);
+        stats.add(Response::new(
+            website("http://example.org/ok"),
+            Status::Ok(http::StatusCode::OK),
+            Input::Stdin,
+        ));
+        stats.add(Response::new(
+            website("http://example.org/moved"),
+            Status::Ok(http::StatusCode::MOVED_PERMANENTLY),
+            Input::Stdin,
+        ));
+        stats.add(Response::new(
+            website("http://example.org/teapot"),
+            Status::Ok(http::StatusCode::IM_A_TEAPOT),
+            Input::Stdin,
+        ));
+        assert!(!stats.is_success());
+        assert_eq!(stats.successful, 1);
+        assert_eq!(stats.redirects, 1);
+        assert_eq!(stats.failures, 1);
+    }
+
+    #[test]
+    fn test_required_hosts() {
+        let required = Excludes::compile_host_globs(&["*.example.org"]).unwrap();
+        let mut stats = ResponseStats::new().required_hosts(required);
+        stats.add(Response::new(
+            website("https://docs.example.org/ok"),
+            Status::Ok(http::StatusCode::OK),
+            Input::Stdin,
+        ));
+        stats.add(Response::new(
+            website("https://external.com/gone"),
+            Status::Failed(http::StatusCode::NOT_FOUND),
+            Input::Stdin,
+        ));
+        assert!(stats.is_success());
+        assert_eq!(stats.failures, 1);
+        assert_eq!(stats.not_required, 1);
+
+        stats.add(Response::new(
+            website("https://docs.example.org/gone"),
+            Status::Failed(http::StatusCode::NOT_FOUND),
+            Input::Stdin,
+        ));
+        assert!(!stats.is_success());
+
+        let required = Excludes::compile_host_globs(&["*.example.org"]).unwrap();
+        let mut stats = ResponseStats::new().required_hosts(required);
+        stats.add(Response::new(
+            website("file:///docs/missing.md"),
+            Status::Failed(http::StatusCode::NOT_FOUND),
+            Input::Stdin,
+        ));
+        assert!(!stats.is_success());
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let mut stats = ResponseStats::new();
+        stats.add(Response::new(
+            website("ftp://example.org/file.txt"),
+            Status::Unsupported("`ftp` links can't be checked".to_string()),
+            Input::Stdin,
+        ));
+        assert!(stats.is_success());
+        assert_eq!(stats.unsupported, 1);
+        assert!(stats.to_string().contains("Unsupported"));
+    }
+
+    #[test]
+    fn test_max_failures() {
+        let add = |stats: &mut ResponseStats, status: Status| {
+            stats.add(Response::new(
+                website(&format!("https://example.org/{}", stats.total)),
+                status,
+                Input::Stdin,
+            ));
+        };
+        let ok = || Status::Ok(http::StatusCode::OK);
+        let gone = || Status::Failed(http::StatusCode::NOT_FOUND);
+
+        let mut stats = ResponseStats::new().max_failures(Some(1));
+        add(&mut stats, ok());
+        add(&mut stats, gone());
+        assert!(stats.is_success());
+        assert_eq!(stats.fail_map[&Input::Stdin].len(), 1);
+        add(&mut stats, gone());
+        assert!(!stats.is_success());
+
+        // 1 out of 4 checked links, the excluded one doesn't count
+        let mut stats = ResponseStats::new().max_failure_percent(Some(25.0));
+        for _ in 0..3 {
+            add(&mut stats, ok());
+        }
+        add(&mut stats, gone());
+        add(&mut stats, Status::Excluded);
+        assert!(stats.is_success());
+        add(&mut stats, gone());
+        assert!(!stats.is_success());
+
+        // Both limits have to hold
+        let mut stats = ResponseStats::new()
+            .max_failures(Some(1))
+            .max_failure_percent(Some(10.0));
+        add(&mut stats, ok());
+        add(&mut stats, gone());
+        assert!(!stats.is_success());
+
+        // Strict mode ignores the budget
+        let mut stats = ResponseStats::new().strict(true).max_failures(Some(1));
+        add(&mut stats, gone());
+        assert!(!stats.is_success());
+    }
+
+    #[test]
+    fn test_own_host_links() {
+        let links = vec![OwnHostLink {
+            input: Input::FsPath("docs/guide.md".into()),
+            line: Some(12),
+            link: "https://docs.example.com/setup".to_string(),
+        }];
+
+        let mut stats = ResponseStats::new();
+        stats.add_own_host_links(links.clone());
+        assert!(stats.is_success());
+        assert!(stats
+            .to_string()
+            .contains("docs/guide.md:12: https://docs.example.com/setup"));
+
+        let mut stats = ResponseStats::new().fail_on_own_host_links(true);
+        stats.add_own_host_links(links);
+        assert!(!stats.is_success());
+    }
+
+    #[test]
+    fn test_skipped_per_host() {
+        let mut stats = ResponseStats::new();
+        stats.add_skipped_per_host(vec![("example.org".to_string(), 120)].into_iter().collect());
+        assert!(stats.is_success());
+        assert!(stats.to_string().contains("example.org: 120 links"));
+    }
+
+    #[test]
+    fn test_non_canonical_hosts() {
+        let mut response = Response::new(
+            website("http://example.org/docs"),
+            Status::Ok(http::StatusCode::OK),
+            Input::Stdin,
+        );
+        response.redirects = vec![kimchi::Redirect {
+            code: http::StatusCode::MOVED_PERMANENTLY,
+            url: reqwest::Url::parse("https://www.example.org/docs").unwrap(),
+        }];
+        let mut stats = ResponseStats::new();
+        stats.add(response);
+        assert!(stats.is_success());
+        assert_eq!(
+            stats.non_canonical_hosts.get("http://example.org/docs"),
+            Some(&"https://www.example.org/docs".to_string())
+        );
+        assert!(stats
+            .to_string()
+            .contains("http://example.org/docs → https://www.example.org/docs"));
+    }
+
+    #[test]
+    fn test_input_errors() {
+        let errors = vec![InputError {
+            input: Input::FsPath("docs/binary.md".into()),
+            reason: "stream did not contain valid UTF-8".to_string(),
+        }];
+
+        let mut stats = ResponseStats::new();
+        stats.add_input_errors(errors.clone());
+        assert!(stats.is_success());
+        assert!(stats.to_string().contains("docs/binary.md"));
+
+        let mut stats = ResponseStats::new().fail_on_input_errors(true);
+        stats.add_input_errors(errors.clone());
+        assert!(!stats.is_success());
+
+        let mut stats = ResponseStats::new().fail_on_input_errors(true);
+        stats.add_input_warnings(errors);
+        assert!(stats.is_success());
+        assert!(stats.to_string().contains("Input warnings"));
+    }
+
+    #[test]
+    fn test_auth_required() {
+        let protected = || {
+            Response::new(
+                website("http://example.org/admin"),
+                Status::AuthRequired(http::StatusCode::FORBIDDEN),
+                Input::Stdin,
+            )
+        };
+
+        let mut stats = ResponseStats::new();
+        stats.add(protected());
+        assert!(stats.is_success());
+        assert_eq!(stats.auth_required, 1);
+        assert!(stats.fail_map.is_empty());
+
+        let mut stats = ResponseStats::new().fail_on_auth_required(true);
+        stats.add(protected());
+        assert!(!stats.is_success());
+        assert_eq!(stats.failures, 1);
+    }
 }
@@ -0,0 +1,185 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+/// What is remembered about a link which worked in an earlier run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// `ETag` header of the last successful response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// `Last-Modified` header of the last successful response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// Status code of the last successful response, reported again when
+    /// the server answers a revalidation with `304 Not Modified`
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// When the link was last found working, in seconds since the Unix epoch
+    pub checked_at: u64,
+}
+
+/// Links found working in earlier runs, along with their validators
+/// (`ETag` and `Last-Modified`). The client revalidates them with a
+/// conditional request, so unchanged resources answer with a cheap
+/// `304 Not Modified` instead of the whole body.
+///
+/// The cache is kept as a JSON file between runs.
+/// Cloning is cheap; clones share their entries.
+#[derive(Debug, Clone, Default)]
+pub struct LinkCache {
+    entries: Arc<Mutex<HashMap<String, CacheEntry>>>,
+}
+
+impl LinkCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a cache file. A missing file gives an empty cache.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Cannot read cache `{}`", path.display()))
+            }
+        };
+        let entries: HashMap<String, CacheEntry> = serde_json::from_slice(&contents)
+            .with_context(|| format!("Invalid cache file `{}`", path.display()))?;
+        Ok(Self {
+            entries: Arc::new(Mutex::new(entries)),
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(&*self.entries.lock().unwrap())?;
+        fs::write(path, contents)
+            .with_context(|| format!("Cannot write cache `{}`", path.display()))
+    }
+
+    pub fn get(&self, url: &Url) -> Option<CacheEntry> {
+        self.entries.lock().unwrap().get(&key(url)).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remember a working link. Links without validators can't be
+    /// revalidated, so they are dropped instead.
+    pub(crate) fn store(
+        &self,
+        url: &Url,
+        status: http::StatusCode,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    ) {
+        if etag.is_none() && last_modified.is_none() {
+            self.remove(url);
+            return;
+        }
+        let entry = CacheEntry {
+            etag,
+            last_modified,
+            status: status.as_u16(),
+            checked_at: now(),
+        };
+        self.entries.lock().unwrap().insert(key(url), entry);
+    }
+
+    /// Mark a link as still working, keeping its validators
+    pub(crate) fn refresh(&self, url: &Url) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(&key(url)) {
+            entry.checked_at = now();
+        }
+    }
+
+    pub(crate) fn remove(&self, url: &Url) {
+        self.entries.lock().unwrap().remove(&key(url));
+    }
+}
+
+/// The fragment never gets sent to the server
+fn key(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url.to_string()
+}
+
+/// Entries written before the status was remembered were all `200 OK`
+fn default_status() -> u16 {
+    http::StatusCode::OK.as_u16()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_link_cache() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("cache.json");
+        let cache = LinkCache::load(&path)?;
+        assert!(cache.is_empty());
+
+        let url = Url::parse("https://example.org/page#section")?;
+        cache.store(
+            &url,
+            http::StatusCode::NO_CONTENT,
+            Some("\"v1\"".to_string()),
+            None,
+        );
+        cache.store(
+            &Url::parse("https://example.org/plain")?,
+            http::StatusCode::OK,
+            None,
+            None,
+        );
+        cache.save(&path)?;
+
+        let cache = LinkCache::load(&path)?;
+        assert_eq!(cache.len(), 1);
+        let entry = cache.get(&Url::parse("https://example.org/page")?).unwrap();
+        assert_eq!(entry.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(entry.status, 204);
+        assert!(entry.checked_at > 0);
+
+        cache.remove(&url);
+        assert!(cache.is_empty());
+
+        // Caches from before the status was remembered
+        fs::write(
+            &path,
+            r#"{"https://example.org/": {"etag": "\"v0\"", "checked_at": 1}}"#,
+        )?;
+        let entry = LinkCache::load(&path)?
+            .get(&Url::parse("https://example.org/")?)
+            .unwrap();
+        assert_eq!(entry.status, 200);
+
+        fs::write(&path, "not json")?;
+        assert!(LinkCache::load(&path).is_err());
+        Ok(())
+    }
+}
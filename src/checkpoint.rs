@@ -0,0 +1,354 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use url::Url;
+
+use crate::{
+    client::{REQUIRES_JAVASCRIPT, UNSUPPORTED_SCHEME},
+    collector::Input,
+    ErrorChain, Redirect, Request, Response, Status, TlsError, Uri,
+};
+
+/// Recorded responses get written to the file at least this often
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Whether the links of an input can be told apart from the links of other
+/// inputs in the checkpoint file. In-memory strings all share the same name,
+/// so their links are always checked again.
+fn is_recordable(input: &Input) -> bool {
+    !matches!(input, Input::String { .. })
+}
+
+/// A `Status` in a form which can be read back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum RecordedStatus {
+    Ok(u16),
+    Failed(u16),
+    Timeout(Option<u16>),
+    Redirected(u16),
+    Excluded,
+    Error(ErrorChain),
+    Tls(TlsError),
+    Invalid(String),
+    AuthRequired(u16),
+    Unsupported(String),
+    HeaderMismatch(String),
+    ContentMismatch(String),
+    RedirectLoop(Vec<Url>),
+}
+
+impl From<&Status> for RecordedStatus {
+    fn from(status: &Status) -> Self {
+        match status {
+            Status::Ok(code) => Self::Ok(code.as_u16()),
+            Status::Failed(code) => Self::Failed(code.as_u16()),
+            Status::Timeout(code) => Self::Timeout(code.map(|code| code.as_u16())),
+            Status::Redirected(code) => Self::Redirected(code.as_u16()),
+            Status::Excluded => Self::Excluded,
+            Status::Error(e) => Self::Error(e.clone()),
+            Status::Tls(e) => Self::Tls(e.clone()),
+            Status::Invalid(e) => Self::Invalid(e.clone()),
+            Status::AuthRequired(code) => Self::AuthRequired(code.as_u16()),
+            Status::Unsupported(e) => Self::Unsupported(e.clone()),
+            Status::HeaderMismatch(e) => Self::HeaderMismatch(e.clone()),
+            Status::ContentMismatch(e) => Self::ContentMismatch(e.clone()),
+            Status::RedirectLoop(chain) => Self::RedirectLoop(chain.clone()),
+        }
+    }
+}
+
+impl RecordedStatus {
+    /// `None` for invalid status codes, which can only come from a
+    /// tampered file
+    fn into_status(self) -> Option<Status> {
+        let code = |code| http::StatusCode::from_u16(code).ok();
+        Some(match self {
+            Self::Ok(c) => Status::Ok(code(c)?),
+            Self::Failed(c) => Status::Failed(code(c)?),
+            Self::Timeout(None) => Status::Timeout(None),
+            Self::Timeout(Some(c)) => Status::Timeout(Some(code(c)?)),
+            Self::Redirected(c) => Status::Redirected(code(c)?),
+            Self::Excluded => Status::Excluded,
+            Self::Error(e) => Status::Error(e),
+            Self::Tls(e) => Status::Tls(e),
+            Self::Invalid(e) => Status::Invalid(e),
+            Self::AuthRequired(c) => Status::AuthRequired(code(c)?),
+            Self::Unsupported(e) => Status::Unsupported(e),
+            Self::HeaderMismatch(e) => Status::HeaderMismatch(e),
+            Self::ContentMismatch(e) => Status::ContentMismatch(e),
+            Self::RedirectLoop(chain) => Status::RedirectLoop(chain),
+        })
+    }
+}
+
+/// A checked link, one per line of the checkpoint file
+#[derive(Debug, Serialize, Deserialize)]
+struct Recorded {
+    /// The input the link was found in
+    source: String,
+    /// The link as found in the input
+    link: String,
+    /// The checked URI, if the link got rewritten
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uri: Option<Uri>,
+    status: RecordedStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    redirects: Vec<(u16, Url)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    archived: Option<Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    excluded_reason: Option<String>,
+}
+
+impl Recorded {
+    fn new(response: &Response) -> Self {
+        let (link, uri) = match &response.rewritten_from {
+            Some(original) => (original.clone(), Some(response.uri.clone())),
+            None => (response.uri.to_string(), None),
+        };
+        Recorded {
+            source: response.source.to_string(),
+            link,
+            uri,
+            status: RecordedStatus::from(&response.status),
+            method: response.method.clone(),
+            duration_ms: response.duration.map(|d| d.as_millis() as u64),
+            redirects: response
+                .redirects
+                .iter()
+                .map(|redirect| (redirect.code.as_u16(), redirect.url.clone()))
+                .collect(),
+            archived: response.archived.clone(),
+            excluded_reason: response.excluded_reason.map(str::to_string),
+        }
+    }
+}
+
+/// Responses of a run in progress, kept in a file so the run can be resumed
+/// if it gets interrupted. Links recorded by an earlier, interrupted run are
+/// restored instead of checked again.
+///
+/// Responses get appended to the file as JSON lines. The last line of a
+/// killed run may be incomplete; lines which can't be read are skipped and
+/// their links checked again.
+///
+/// Headers and rate limits of the responses aren't recorded, nor are the
+/// links of in-memory string inputs.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    /// By input and link
+    recorded: HashMap<(String, String), Recorded>,
+    writer: BufWriter<File>,
+    flushed_at: Instant,
+}
+
+impl Checkpoint {
+    /// Open a checkpoint file, loading the responses recorded in it.
+    /// A missing file gets created.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = match fs::read(path) {
+            // A killed run may have left half a character behind
+            Ok(contents) => String::from_utf8_lossy(&contents).into_owned(),
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Cannot read checkpoint `{}`", path.display()))
+            }
+        };
+        let recorded = contents
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Recorded>(line).ok())
+            .map(|recorded| ((recorded.source.clone(), recorded.link.clone()), recorded))
+            .collect();
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Cannot write checkpoint `{}`", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        // Don't continue an incomplete last line
+        if !(contents.is_empty() || contents.ends_with('\n')) {
+            writer.write_all(b"\n")?;
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            recorded,
+            writer,
+            flushed_at: Instant::now(),
+        })
+    }
+
+    /// Number of links recorded by earlier runs
+    pub fn len(&self) -> usize {
+        self.recorded.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The response to a request, if an earlier run checked it
+    pub fn restore(&self, request: &Request) -> Option<Response> {
+        if !is_recordable(&request.source) {
+            return None;
+        }
+        let key = (request.source.to_string(), request.uri.to_string());
+        let recorded = self.recorded.get(&key)?;
+        let status = recorded.status.clone().into_status()?;
+        let uri = recorded.uri.clone().unwrap_or_else(|| request.uri.clone());
+        let mut response = Response::new(uri, status, request.source.clone());
+        response.kind = request.kind;
+        response.line = request.line;
+        response.method = recorded.method.clone();
+        response.duration = recorded.duration_ms.map(Duration::from_millis);
+        response.redirects = recorded
+            .redirects
+            .iter()
+            .filter_map(|(code, url)| {
+                Some(Redirect {
+                    code: http::StatusCode::from_u16(*code).ok()?,
+                    url: url.clone(),
+                })
+            })
+            .collect();
+        if recorded.uri.is_some() {
+            response.rewritten_from = Some(recorded.link.clone());
+        }
+        response.archived = recorded.archived.clone();
+        response.excluded_reason = match recorded.excluded_reason.as_deref() {
+            Some(UNSUPPORTED_SCHEME) => Some(UNSUPPORTED_SCHEME),
+            Some(REQUIRES_JAVASCRIPT) => Some(REQUIRES_JAVASCRIPT),
+            _ => None,
+        };
+        Some(response)
+    }
+
+    /// Record a checked link. Recorded links get written to the file every
+    /// few seconds and on `flush`.
+    pub fn record(&mut self, response: &Response) -> Result<()> {
+        if !is_recordable(&response.source) {
+            return Ok(());
+        }
+        serde_json::to_writer(&mut self.writer, &Recorded::new(response))?;
+        self.writer.write_all(b"\n")?;
+        if self.flushed_at.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.flushed_at = Instant::now();
+        self.writer
+            .flush()
+            .with_context(|| format!("Cannot write checkpoint `{}`", self.path.display()))
+    }
+
+    /// Delete the checkpoint file, e.g. once the run completed
+    pub fn remove(self) -> Result<()> {
+        let path = self.path;
+        drop(self.writer);
+        fs::remove_file(&path)
+            .with_context(|| format!("Cannot remove checkpoint `{}`", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{test_utils::website, FileType, LinkKind};
+    use http::StatusCode;
+
+    #[test]
+    fn test_checkpoint() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("checkpoint.jsonl");
+        let source = Input::FsPath("README.md".into());
+        let request = |url: &str| Request::new(website(url), source.clone());
+
+        let mut checkpoint = Checkpoint::open(&path)?;
+        assert!(checkpoint.is_empty());
+        let mut response = Response::new(
+            website("https://example.org/moved"),
+            Status::Ok(StatusCode::OK),
+            source.clone(),
+        );
+        response.redirects = vec![Redirect {
+            code: StatusCode::MOVED_PERMANENTLY,
+            url: Url::parse("https://example.org/new")?,
+        }];
+        checkpoint.record(&response)?;
+        checkpoint.record(&Response::new(
+            website("https://example.org/gone"),
+            Status::Failed(StatusCode::NOT_FOUND),
+            source.clone(),
+        ))?;
+        checkpoint.flush()?;
+        drop(checkpoint);
+
+        // Killed while writing the next line
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(b"{\"source\":\"README.md\",\"li")?;
+        drop(file);
+
+        let mut checkpoint = Checkpoint::open(&path)?;
+        assert_eq!(checkpoint.len(), 2);
+        let restored = checkpoint
+            .restore(&request("https://example.org/moved").with_kind(LinkKind::Image))
+            .unwrap();
+        assert_eq!(restored, response);
+        assert_eq!(restored.kind, LinkKind::Image);
+        assert_eq!(
+            restored.permanent_redirect(),
+            Some(Url::parse("https://example.org/new")?)
+        );
+        assert_eq!(
+            checkpoint
+                .restore(&request("https://example.org/gone"))
+                .unwrap()
+                .status,
+            Status::Failed(StatusCode::NOT_FOUND)
+        );
+        assert!(checkpoint
+            .restore(&request("https://example.org/new"))
+            .is_none());
+        let other_source = Request::new(website("https://example.org/gone"), Input::Stdin);
+        assert!(checkpoint.restore(&other_source).is_none());
+
+        // Strings can't be told apart, so they don't get recorded
+        let string = Input::from_string("https://example.org/gone", FileType::Plaintext);
+        checkpoint.record(&Response::new(
+            website("https://example.org/gone"),
+            Status::Ok(StatusCode::OK),
+            string.clone(),
+        ))?;
+        let string_source = Request::new(website("https://example.org/gone"), string);
+        assert!(checkpoint.restore(&string_source).is_none());
+
+        // Recording continues on a new line
+        checkpoint.record(&Response::new(
+            website("https://example.org/new"),
+            Status::Ok(StatusCode::OK),
+            source.clone(),
+        ))?;
+        checkpoint.flush()?;
+        assert_eq!(Checkpoint::open(&path)?.len(), 3);
+
+        checkpoint.remove()?;
+        assert!(!path.exists());
+        Ok(())
+    }
+}
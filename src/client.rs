@@ -1,31 +1,191 @@
 use anyhow::{anyhow, bail, Context, Result};
 use check_if_email_exists::{check_email, CheckEmailInput};
 use derive_builder::Builder;
+use futures::future::join_all;
 use headers::{HeaderMap, HeaderValue};
 use hubcaps::{Credentials, Github};
+use lazy_static::lazy_static;
 use regex::{Regex, RegexSet};
-use reqwest::header;
+use reqwest::{header, Certificate, Identity};
 use std::convert::TryInto;
-use std::{collections::HashSet, time::Duration};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
 use tokio::time::sleep;
+use tracing::{field, Instrument, Span};
 use url::Url;
 
-use crate::types::{Response, Status};
-use crate::uri::Uri;
+use crate::anchors::{contains_anchor, html_anchors, markdown_anchors, AnchorStyle};
+use crate::cache::LinkCache;
+use crate::collector::InputContent;
+use crate::dns::FallbackResolver;
+use crate::extract::{extract_links, FileType};
+use crate::github::{GithubBatch, GithubResource, GITHUB_API};
+use crate::host_policy::{
+    HostMethods, HostPolicy, HostThrottle, RateLimiter, RequestRate, DEFAULT_HOST_POLICIES,
+};
+use crate::oauth::{OAuth2, OAuth2Credentials};
+use crate::robots::{CrawlDelays, MAX_ROBOTS_REDIRECTS};
+use crate::types::{
+    ErrorChain, HttpVersion, LinkKind, RateLimit, Redirect, RedirectPolicy, Response, ResponseInfo,
+    Status,
+};
+use crate::uri::{mail_syntax_error, GitRemote, Uri};
 use crate::{excludes::Excludes, Request};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const DEFAULT_MAX_REDIRECTS: usize = 5;
+/// Status codes which usually indicate a temporary problem and get retried
+const DEFAULT_RETRY_STATUS: &[http::StatusCode] = &[
+    http::StatusCode::TOO_MANY_REQUESTS,
+    http::StatusCode::INTERNAL_SERVER_ERROR,
+    http::StatusCode::BAD_GATEWAY,
+    http::StatusCode::SERVICE_UNAVAILABLE,
+    http::StatusCode::GATEWAY_TIMEOUT,
+];
+/// Minimum body size of links expected to have content, see `ClientBuilder::expect_content`
+const DEFAULT_MIN_CONTENT_LENGTH: u64 = 1;
+/// Only the start of larger bodies is searched, see `ClientBuilder::require_content`
+const MAX_ASSERTED_BODY_SIZE: usize = 10 * 1024 * 1024;
+/// Upper bound for waiting on a host which asked to retry later
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+/// Schemes of links which can't be checked over HTTP
+const UNSUPPORTED_SCHEMES: &[&str] = &["ftp", "ftps", "sftp"];
+/// Schemes of links which aren't meant to be checked at all, but handed to
+/// another app (phone, chat, maps etc.), see `ClientBuilder::uncheckable_schemes`
+pub const DEFAULT_UNCHECKABLE_SCHEMES: &[&str] = &[
+    "tel", "sms", "mms", "callto", "facetime", "skype", "irc", "ircs", "xmpp", "geo", "magnet",
+    "webcal",
+];
+/// Reason for excluding links with one of the uncheckable schemes
+pub(crate) const UNSUPPORTED_SCHEME: &str = "unsupported scheme";
+/// Reason for excluding `javascript:` links, which only do something when
+/// run by a browser
+pub(crate) const REQUIRES_JAVASCRIPT: &str = "requires JavaScript";
+/// Availability API of the Internet Archive, see `ClientBuilder::wayback_fallback`
+const WAYBACK_API: &str = "https://archive.org/wayback/available";
+/// Timeout of requests the client sends on its own, see `ClientBuilder::aux_timeout`
+const DEFAULT_AUX_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Headers sent by a current desktop browser. Some firewalls check the
+// combination of headers, not just the user agent.
+const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+    (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+const BROWSER_ACCEPT: &str =
+    "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8";
+const BROWSER_ACCEPT_LANGUAGE: &str = "en-US,en;q=0.9";
+
+lazy_static! {
+    /// The title of an HTML page, see `ClientBuilder::soft_404_patterns`
+    static ref HTML_TITLE: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+}
+
+/// Details about the HTTP response of a website check.
+/// Only captured if requested via `ClientBuilder::capture_headers`.
+#[derive(Debug, Default)]
+struct ResponseDetails {
+    headers: Option<HeaderMap>,
+    final_url: Option<Url>,
+    /// Always captured, as it's small and helps explaining failures
+    rate_limit: Option<RateLimit>,
+    /// Always captured to back off from hosts with a policy
+    retry_after: Option<Duration>,
+    /// Validators for the link cache
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// The server confirmed that the cached link didn't change
+    revalidated: bool,
+    /// Request method of the last request made for the link
+    method: Option<reqwest::Method>,
+    /// Redirects of the link, in order
+    redirects: Vec<Redirect>,
+}
+
+/// Rewrites links before they get checked, see `ClientBuilder::url_rewriter`
+#[derive(Clone)]
+struct UrlRewriter(Arc<dyn Fn(Uri) -> Uri + Send + Sync>);
+
+impl fmt::Debug for UrlRewriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("UrlRewriter")
+    }
+}
+
+/// Decides the status of website responses, see `ClientBuilder::status_classifier`
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+struct StatusClassifier(Arc<dyn Fn(&ResponseInfo<'_>, Status) -> Status + Send + Sync>);
 
+impl fmt::Debug for StatusClassifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StatusClassifier")
+    }
+}
+
+/// Checks links. Create one with the `ClientBuilder`.
+/// Cloning is cheap; clones share their connection pool and caches.
 #[derive(Debug, Clone)]
 pub struct Client {
     reqwest_client: reqwest::Client,
     github: Option<Github>,
+    github_token: Option<String>,
+    gitlab_token: Option<String>,
+    /// GitHub resources already resolved through the API, and whether they
+    /// exist. Shared between all clones of a client, so every resource only
+    /// gets resolved once per run.
+    github_cache: Arc<Mutex<HashMap<GithubResource, bool>>>,
+    github_api: Url,
+    /// Sent along with every request to a website. Credentials among them
+    /// don't get passed on when redirected to another host.
+    custom_headers: HeaderMap,
+    max_redirects: usize,
+    redirect_policy: RedirectPolicy,
+    url_rewriter: Option<UrlRewriter>,
+    status_classifier: Option<StatusClassifier>,
     includes: Option<RegexSet>,
     excludes: Excludes,
+    uncheckable_schemes: HashSet<String>,
     scheme: Option<String>,
     method: reqwest::Method,
+    host_methods: Arc<HostMethods>,
     accepted: Option<HashSet<reqwest::StatusCode>>,
+    url_accepted: Option<UrlAccepted>,
+    retry_on_status: HashSet<http::StatusCode>,
+    detect_auth_required: bool,
+    check_mail_reachability: bool,
+    capture_headers: bool,
+    check_image_types: bool,
+    content_types: Option<ContentTypes>,
+    soft_404_patterns: Option<RegexSet>,
+    expect_content: Option<RegexSet>,
+    min_content_length: u64,
+    required_headers: Option<RequiredHeaders>,
+    content_assertions: Option<ContentAssertions>,
+    scheme_timeouts: HashMap<String, Duration>,
+    check_anchors: bool,
+    anchor_style: AnchorStyle,
+    anchors_ignore_case: bool,
+    /// Retries left for the whole run. Shared between all clones of a client.
+    /// `None` means retries are only limited per link.
+    retry_budget: Option<Arc<AtomicUsize>>,
+    /// Delays and concurrency limits for sensitive hosts.
+    /// Shared between all clones of a client.
+    host_throttle: Arc<HostThrottle>,
+    /// Shared between all clones of a client
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Shared between all clones of a client
+    crawl_delays: Option<Arc<CrawlDelays>>,
+    /// Shared between all clones of a client
+    oauth2: Option<Arc<OAuth2>>,
+    cache: Option<LinkCache>,
+    wayback_fallback: bool,
+    wayback_api: Url,
+    aux_timeout: Duration,
 }
 
 /// A link checker using an API token for Github links
@@ -39,10 +199,35 @@ pub struct ClientBuilderInternal {
     /// This allows for more requests before
     /// getting rate-limited.
     github_token: Option<String>,
+    /// Set an optional GitLab token, used to verify that
+    /// the repositories of git remotes on GitLab exist
+    gitlab_token: Option<String>,
+    /// Rewrite links before they get excluded or checked, see `url_rewriter`
+    #[builder(setter(custom))]
+    url_rewriter: Option<UrlRewriter>,
+    /// Decide the status of website responses, see `status_classifier`
+    #[builder(setter(custom))]
+    status_classifier: Option<StatusClassifier>,
     /// Check links matching this set of regular expressions
     includes: Option<RegexSet>,
     /// Exclude links matching this set of regular expressions
     excludes: Option<RegexSet>,
+    /// Exclude links whose host matches one of these glob patterns,
+    /// e.g. `*.example.com`. Unlike `excludes`, only the host is matched.
+    exclude_hosts: Vec<String>,
+    /// Exclude links to files with these extensions (e.g. `zip` or `tar.gz`),
+    /// to avoid downloading large assets
+    exclude_extensions: Vec<String>,
+    /// Exclude links with these schemes (e.g. `ftp`)
+    exclude_schemes: Vec<String>,
+    /// Links with these schemes (e.g. `tel`) are handled by other apps than
+    /// a browser. They are excluded with the reason "unsupported scheme"
+    /// instead of failing. `mailto` turns off checking mail addresses.
+    /// Defaults to `DEFAULT_UNCHECKABLE_SCHEMES`.
+    uncheckable_schemes: Vec<String>,
+    /// Exclusions built with `Excludes::builder()`. If given, they replace
+    /// the `excludes` and all `exclude_*` options.
+    exclusions: Excludes,
     /// Exclude all private network addresses
     exclude_all_private: bool,
     /// Exclude private IP addresses
@@ -53,12 +238,49 @@ pub struct ClientBuilderInternal {
     exclude_loopback_ips: bool,
     /// Don't check mail addresses
     exclude_mail: bool,
+    /// Also verify that mail addresses exist by asking their mail server.
+    /// This is slow and often blocked, so by default only the syntax of
+    /// mail addresses gets checked.
+    check_mail_reachability: bool,
     /// Maximum number of redirects before returning error
     max_redirects: usize,
+    /// Separate limits for permanent and temporary redirects.
+    /// Links exceeding them are reported as redirected.
+    redirect_policy: RedirectPolicy,
     /// User agent used for checking links
     user_agent: String,
+    /// Pretend to be a web browser by sending a browser's user agent along with
+    /// its `Accept` and `Accept-Language` headers. Overrides `user_agent`;
+    /// custom headers still take precedence.
+    browser_ua: bool,
     /// Ignore SSL errors
     allow_insecure: bool,
+    /// HTTP version to use. Defaults to `HttpVersion::Auto`, which uses
+    /// HTTP/2 if the server supports it. Negotiating HTTP/2 over TLS needs
+    /// ALPN, i.e. the `native-tls-alpn` or a `rustls-tls` feature of reqwest.
+    http_version: HttpVersion,
+    /// Additional root certificates to trust (e.g. for a private CA).
+    /// Unlike `allow_insecure`, certificate validation stays enabled.
+    #[builder(setter(custom))]
+    root_certificates: Vec<Certificate>,
+    /// Client certificate to present for mutual TLS authentication.
+    /// It's only sent to servers which request a client certificate.
+    identity: Option<Identity>,
+    /// Name servers (e.g. `1.1.1.1:53`) to ask if the system resolver
+    /// fails to resolve a host. Connection and TLS errors don't trigger
+    /// a second lookup.
+    fallback_dns: Vec<SocketAddr>,
+    /// Maximum number of idle connections kept open per host for reuse.
+    /// Unlimited by default. Together with a host policy allowing a single
+    /// concurrent request, `1` makes all requests to a host share one
+    /// connection, for hosts which penalize many connections.
+    pool_max_idle_per_host: usize,
+    /// How long idle connections are kept open for reuse.
+    /// Defaults to 90 seconds.
+    pool_idle_timeout: Duration,
+    /// Interval of TCP keep-alive probes, which keep idle connections from
+    /// getting dropped by the server or a firewall. Off by default.
+    tcp_keepalive: Option<Duration>,
     /// Allowed URI scheme (e.g. https, http).
     /// This excludes all links from checking, which
     /// don't specify that scheme in the URL.
@@ -67,27 +289,306 @@ pub struct ClientBuilderInternal {
     /// This allows working around validation issues
     /// on some websites.
     custom_headers: HeaderMap,
+    /// Authenticate with a bearer token from the OAuth2 client credentials
+    /// flow at the configured hosts. The token is renewed when it expires
+    /// or a host answers with `401 Unauthorized`.
+    oauth2: Option<OAuth2Credentials>,
     /// Request method (e.g. `GET` or `HEAD`)
     method: reqwest::Method,
+    /// Request methods for hosts matching a glob pattern, overriding `method`.
+    /// With several methods, a link has to pass the check with each of them.
+    #[builder(setter(custom))]
+    host_methods: Vec<(String, Vec<reqwest::Method>)>,
     /// Set of accepted return codes / status codes
     accepted: Option<HashSet<http::StatusCode>>,
+    /// Accepted status codes for links matching a pattern, overriding
+    /// `accepted`. See `accept_for_url`.
+    #[builder(setter(custom))]
+    url_accepted: Vec<(String, HashSet<http::StatusCode>)>,
+    /// Additional status codes to retry, e.g. `520` from Cloudflare.
+    /// Timeouts, connection errors, `429` and the common transient `5xx`
+    /// codes (`500`, `502`, `503` and `504`) always get retried.
+    retry_on_status: HashSet<http::StatusCode>,
+    /// Report `401 Unauthorized` and `403 Forbidden` as `Status::AuthRequired`
+    /// instead of a failure. Links behind a login usually aren't broken.
+    detect_auth_required: bool,
     /// Response timeout per request
     timeout: Option<Duration>,
+    /// Response timeouts overriding `timeout` for links with a given scheme
+    #[builder(setter(custom))]
+    scheme_timeouts: HashMap<String, Duration>,
+    /// Verify that the `#fragment` of a link exists as an anchor on the page
+    check_anchors: bool,
+    /// How headings are turned into anchors when checking fragments.
+    /// Defaults to `AnchorStyle::Github`.
+    anchor_style: AnchorStyle,
+    /// Match fragments against anchors case-insensitively
+    anchors_ignore_case: bool,
+    /// Keep the response headers and the final URL after redirects
+    /// in every `Response`. Off by default to save memory.
+    capture_headers: bool,
+    /// Verify that image links (e.g. `<img src>`) return an `image/*`
+    /// content type. Misconfigured servers often answer with an HTML
+    /// error page and a `200 OK` instead.
+    check_image_types: bool,
+    /// Content types expected from links matching a pattern.
+    /// See `expect_content_type`.
+    #[builder(setter(custom))]
+    content_types: Vec<(String, Vec<String>)>,
+    /// Maximum number of retries for all links combined.
+    /// Once exhausted, failures are reported without retrying.
+    /// This keeps a host that is down from causing a storm of doomed requests.
+    max_total_retries: Option<usize>,
+    /// Treat redirects to a URL matching one of these patterns as broken links,
+    /// e.g. sites redirecting missing pages to a generic "not found" page
+    /// with a `200 OK` ("soft 404"). If the URL doesn't match, the `<title>`
+    /// of the target page is checked against the patterns as well.
+    soft_404_patterns: Option<RegexSet>,
+    /// Links matching one of these patterns (e.g. release downloads) must
+    /// have a body of at least `min_content_length` bytes. Hosts sometimes
+    /// serve an empty or truncated body with a `200 OK`.
+    /// Off by default, as many valid pages are small.
+    expect_content: Option<RegexSet>,
+    /// Minimum body size in bytes for links matching `expect_content`.
+    /// Defaults to 1, i.e. only empty bodies fail.
+    min_content_length: u64,
+    /// Headers which responses to links matching a pattern must include,
+    /// optionally with an expected value. See `require_header`.
+    #[builder(setter(custom))]
+    required_headers: Vec<(String, header::HeaderName, Option<String>)>,
+    /// Text which the bodies of links matching a pattern must or mustn't
+    /// contain. See `require_content` and `forbid_content`.
+    #[builder(setter(custom))]
+    content_assertions: Vec<(String, Regex, bool)>,
+    /// Politeness rules for hosts matching a glob pattern (e.g. `*.example.com`).
+    /// They take precedence over the `DEFAULT_HOST_POLICIES`.
+    #[builder(setter(custom))]
+    host_policies: Vec<(String, HostPolicy)>,
+    /// Apply the `DEFAULT_HOST_POLICIES` for hosts known to rate limit
+    /// link checkers. Enabled by default.
+    default_host_policies: bool,
+    /// Maximum number of requests per time unit. Requests wait until
+    /// they are allowed, so they get spread evenly.
+    request_rate: Option<RequestRate>,
+    /// Apply `request_rate` to every host separately instead of to all requests
+    request_rate_per_host: bool,
+    /// Wait between requests to a host as long as the `Crawl-delay` of its
+    /// robots.txt asks for, up to 30 seconds. Costs one more request per host.
+    respect_crawl_delay: bool,
+    /// Revalidate links found working in earlier runs with conditional
+    /// requests and remember the validators of working links
+    cache: LinkCache,
+    /// Look up broken websites in the Internet Archive. If there is a
+    /// snapshot, it's added to the response, see `Response::archived`.
+    /// The link is still reported as broken.
+    wayback_fallback: bool,
+    /// Endpoint of the Wayback Machine availability API.
+    /// Defaults to the one of the Internet Archive.
+    wayback_api: Url,
+    /// Endpoint of the GitHub API, see `github_token`.
+    /// Defaults to `https://api.github.com`.
+    github_api: Url,
+    /// Response timeout of the requests supporting the checks rather than
+    /// checking a link: fetching `robots.txt` files, OAuth2 tokens and
+    /// Wayback Machine snapshots. Independent of `timeout`, so a slow
+    /// `robots.txt` can't hold up the links of its host for long.
+    /// Defaults to 5 seconds.
+    aux_timeout: Duration,
 }
 
 impl ClientBuilder {
-    fn build_excludes(&mut self) -> Excludes {
-        // exclude_all_private option turns on all "private" excludes,
-        // including private IPs, link-local IPs and loopback IPs
-        let enable_exclude = |opt| opt || self.exclude_all_private.unwrap_or_default();
+    /// Add a custom root certificate to the set of trusted certificates.
+    /// Can be called multiple times to trust several certificates.
+    pub fn add_root_certificate(&mut self, cert: Certificate) -> &mut Self {
+        self.root_certificates
+            .get_or_insert_with(Vec::new)
+            .push(cert);
+        self
+    }
+
+    /// Use a different response timeout for links with the given scheme
+    /// (e.g. `http`), overriding the global `timeout`.
+    pub fn timeout_for_scheme<S: Into<String>>(
+        &mut self,
+        scheme: S,
+        timeout: Duration,
+    ) -> &mut Self {
+        self.scheme_timeouts
+            .get_or_insert_with(HashMap::new)
+            .insert(scheme.into().to_lowercase(), timeout);
+        self
+    }
+
+    /// Limit the request rate for hosts matching the given glob pattern.
+    /// Policies added first take precedence if several patterns match a host.
+    pub fn host_policy<S: Into<String>>(&mut self, pattern: S, policy: HostPolicy) -> &mut Self {
+        self.host_policies
+            .get_or_insert_with(Vec::new)
+            .push((pattern.into(), policy));
+        self
+    }
+
+    /// Check links to hosts matching the given glob pattern with these
+    /// methods instead of `method`. With several methods (e.g. `HEAD` and
+    /// `GET`), a link is only reported as working if all of them succeed.
+    /// Patterns added first take precedence if several match a host.
+    pub fn methods_for_host<S: Into<String>>(
+        &mut self,
+        pattern: S,
+        methods: Vec<reqwest::Method>,
+    ) -> &mut Self {
+        self.host_methods
+            .get_or_insert_with(Vec::new)
+            .push((pattern.into(), methods));
+        self
+    }
+
+    /// Rewrite every link before it gets excluded or checked, e.g. to check
+    /// links to a production host against a staging host. Responses hold the
+    /// rewritten link and the original one, see `Response::rewritten_from`.
+    /// Collecting links with this client as `CollectorOptions::filter`
+    /// rewrites them right after extraction.
+    pub fn url_rewriter(&mut self, rewriter: Box<dyn Fn(Uri) -> Uri + Send + Sync>) -> &mut Self {
+        self.url_rewriter = Some(Some(UrlRewriter(Arc::from(rewriter))));
+        self
+    }
+
+    /// Decide the status of website responses with a custom policy, e.g.
+    /// to accept `418` on a single host. The classifier gets the response
+    /// along with the status the client would record otherwise, which it
+    /// can return to keep the built-in behavior. Checks of the content
+    /// (e.g. `check_anchors` or `expect_content`) still apply to responses
+    /// classified as successful.
+    #[allow(clippy::type_complexity)]
+    pub fn status_classifier(
+        &mut self,
+        classifier: Box<dyn Fn(&ResponseInfo<'_>, Status) -> Status + Send + Sync>,
+    ) -> &mut Self {
+        self.status_classifier = Some(Some(StatusClassifier(Arc::from(classifier))));
+        self
+    }
+
+    /// Require responses to links matching the regular expression `pattern`
+    /// to include the header `name`, e.g. `Content-Disposition` for downloads.
+    /// If `value` is given, the header must have that value (ignoring case).
+    /// Links lacking the header get a `Status::HeaderMismatch`.
+    pub fn require_header<S: Into<String>>(
+        &mut self,
+        pattern: S,
+        name: header::HeaderName,
+        value: Option<String>,
+    ) -> &mut Self {
+        self.required_headers
+            .get_or_insert_with(Vec::new)
+            .push((pattern.into(), name, value));
+        self
+    }
+
+    /// Expect links matching the regular expression `pattern` to return one
+    /// of the `content_types`, e.g. `application/pdf` for links to `.pdf`
+    /// files, which otherwise often lead to an HTML error page with a
+    /// `200 OK`. Types like `image/*` match all subtypes. Parameters of the
+    /// `Content-Type` (e.g. `charset`) are ignored.
+    /// Other content types get a `Status::HeaderMismatch`.
+    /// Patterns added first take precedence if several match a link.
+    pub fn expect_content_type<S: Into<String>>(
+        &mut self,
+        pattern: S,
+        content_types: Vec<String>,
+    ) -> &mut Self {
+        self.content_types
+            .get_or_insert_with(Vec::new)
+            .push((pattern.into(), content_types));
+        self
+    }
+
+    /// Require the bodies of links matching the regular expression `pattern`
+    /// to contain a match of `text`, e.g. to notice a docs page which still
+    /// works, but lost its content. Use `regex::escape` for plain text.
+    /// Links lacking the text get a `Status::ContentMismatch`.
+    /// Only the first 10 MiB of a body are searched, and `HEAD` requests
+    /// aren't checked as they have no body.
+    pub fn require_content<S: Into<String>>(&mut self, pattern: S, text: Regex) -> &mut Self {
+        self.content_assertions
+            .get_or_insert_with(Vec::new)
+            .push((pattern.into(), text, true));
+        self
+    }
+
+    /// Like `require_content`, but the bodies mustn't contain a match of
+    /// `text`, e.g. placeholder text or an error message.
+    pub fn forbid_content<S: Into<String>>(&mut self, pattern: S, text: Regex) -> &mut Self {
+        self.content_assertions
+            .get_or_insert_with(Vec::new)
+            .push((pattern.into(), text, false));
+        self
+    }
+
+    /// Accept exactly the status codes `accepted` for links matching the
+    /// regular expression `pattern`, instead of the global `accepted` codes
+    /// and all `2xx` codes. E.g. only accept `202 Accepted` for an API
+    /// endpoint. Patterns added first take precedence if several match a link.
+    pub fn accept_for_url<S: Into<String>>(
+        &mut self,
+        pattern: S,
+        accepted: HashSet<http::StatusCode>,
+    ) -> &mut Self {
+        self.url_accepted
+            .get_or_insert_with(Vec::new)
+            .push((pattern.into(), accepted));
+        self
+    }
+
+    fn build_host_throttle(&self) -> Result<HostThrottle> {
+        let mut policies = self.host_policies.clone().unwrap_or_default();
+        if self.default_host_policies.unwrap_or(true) {
+            policies.extend(
+                DEFAULT_HOST_POLICIES
+                    .iter()
+                    .map(|(pattern, delay, concurrency)| {
+                        (
+                            pattern.to_string(),
+                            HostPolicy::new(Duration::from_millis(*delay), *concurrency),
+                        )
+                    }),
+            );
+        }
+        HostThrottle::new(policies)
+    }
 
-        Excludes {
-            regex: self.excludes.clone().unwrap_or_default(),
-            private_ips: enable_exclude(self.exclude_private_ips.unwrap_or_default()),
-            link_local_ips: enable_exclude(self.exclude_link_local_ips.unwrap_or_default()),
-            loopback_ips: enable_exclude(self.exclude_loopback_ips.unwrap_or_default()),
-            mail: enable_exclude(self.exclude_mail.unwrap_or_default()),
+    fn build_excludes(&mut self) -> Result<Excludes> {
+        if let Some(excludes) = &self.exclusions {
+            return Ok(excludes.clone());
         }
+        let mut builder = Excludes::builder();
+        for pattern in self
+            .excludes
+            .clone()
+            .flatten()
+            .iter()
+            .flat_map(RegexSet::patterns)
+        {
+            builder.regex(pattern.as_str());
+        }
+        for host in self.exclude_hosts.iter().flatten() {
+            builder.host(host.as_str());
+        }
+        for extension in self.exclude_extensions.iter().flatten() {
+            builder.extension(extension.as_str());
+        }
+        for scheme in self.exclude_schemes.iter().flatten() {
+            builder.scheme(scheme.as_str());
+        }
+        // exclude_all_private option turns on all "private" excludes,
+        // including private IPs, link-local IPs and loopback IPs
+        builder
+            .exclude_all_private(self.exclude_all_private.unwrap_or_default())
+            .exclude_private_ips(self.exclude_private_ips.unwrap_or_default())
+            .exclude_link_local_ips(self.exclude_link_local_ips.unwrap_or_default())
+            .exclude_loopback_ips(self.exclude_loopback_ips.unwrap_or_default())
+            .exclude_mail(self.exclude_mail.unwrap_or_default())
+            .build()
+            .context("Invalid host pattern in `exclude_hosts`")
     }
 
     /// The build method instantiates the client.
@@ -96,43 +597,93 @@ impl ClientBuilder {
 
         // Faking the user agent is necessary for some websites, unfortunately.
         // Otherwise we get a 403 from the firewall (e.g. Sucuri/Cloudproxy on ldra.com).
-        let user_agent = self
-            .user_agent
-            .clone()
-            .unwrap_or_else(|| format!("kimchi/{}", VERSION));
+        let browser_ua = self.browser_ua.unwrap_or(false);
+        let user_agent = match browser_ua {
+            true => BROWSER_USER_AGENT.to_string(),
+            false => self
+                .user_agent
+                .clone()
+                .unwrap_or_else(|| format!("kimchi/{}", VERSION)),
+        };
 
         headers.insert(header::USER_AGENT, HeaderValue::from_str(&user_agent)?);
-        headers.insert(header::TRANSFER_ENCODING, HeaderValue::from_str("chunked")?);
-        if let Some(custom) = &self.custom_headers {
-            headers.extend(custom.clone());
+        if browser_ua {
+            headers.insert(header::ACCEPT, HeaderValue::from_static(BROWSER_ACCEPT));
+            headers.insert(
+                header::ACCEPT_LANGUAGE,
+                HeaderValue::from_static(BROWSER_ACCEPT_LANGUAGE),
+            );
         }
 
         let allow_insecure = self.allow_insecure.unwrap_or(false);
         let max_redirects = self.max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS);
 
-        let builder = reqwest::ClientBuilder::new()
-            .gzip(true)
-            .default_headers(headers)
-            .danger_accept_invalid_certs(allow_insecure)
-            .redirect(reqwest::redirect::Policy::limited(max_redirects));
+        // The same settings apply to the requests the client sends on its own,
+        // which may follow redirects, e.g. for robots.txt files
+        let reqwest_builder = |redirect: reqwest::redirect::Policy| -> Result<_> {
+            let mut builder = reqwest::ClientBuilder::new()
+                .gzip(true)
+                .default_headers(headers.clone())
+                .danger_accept_invalid_certs(allow_insecure)
+                .redirect(redirect);
+
+            for cert in self.root_certificates.clone().unwrap_or_default() {
+                builder = builder.add_root_certificate(cert);
+            }
+            if let Some(identity) = self.identity.clone().unwrap_or(None) {
+                builder = builder.identity(identity);
+            }
+            builder = match self.http_version.unwrap_or_default() {
+                HttpVersion::Auto => builder,
+                HttpVersion::Http1 => builder.http1_only(),
+                HttpVersion::Http2 => builder.http2_prior_knowledge(),
+            };
+            if let Some(max_idle) = self.pool_max_idle_per_host {
+                builder = builder.pool_max_idle_per_host(max_idle);
+            }
+            if let Some(idle_timeout) = self.pool_idle_timeout {
+                builder = builder.pool_idle_timeout(idle_timeout);
+            }
+            builder = builder.tcp_keepalive(self.tcp_keepalive.unwrap_or(None));
+            let fallback_dns = self.fallback_dns.clone().unwrap_or_default();
+            if !fallback_dns.is_empty() {
+                builder = builder.dns_resolver(Arc::new(FallbackResolver::new(&fallback_dns)?));
+            }
 
-        let builder = match self.timeout {
-            Some(t) => builder
-                .timeout(t.ok_or_else(|| anyhow!("cannot parse timeout: {:?}", self.timeout))?),
-            None => builder,
+            let builder = match self.timeout {
+                Some(t) => builder
+                    .timeout(t.ok_or_else(|| anyhow!("cannot parse timeout: {:?}", self.timeout))?),
+                None => builder,
+            };
+            Ok(builder)
+        };
+        // Redirects of links get followed by the client itself to detect loops
+        let reqwest_client = reqwest_builder(reqwest::redirect::Policy::none())?.build()?;
+
+        let aux_timeout = self.aux_timeout.unwrap_or(DEFAULT_AUX_TIMEOUT);
+        let crawl_delays = match self.respect_crawl_delay.unwrap_or(false) {
+            true => {
+                let robots_client =
+                    reqwest_builder(reqwest::redirect::Policy::limited(MAX_ROBOTS_REDIRECTS))?
+                        .build()?;
+                Some(Arc::new(CrawlDelays::new(
+                    robots_client,
+                    user_agent.clone(),
+                    aux_timeout,
+                )))
+            }
+            false => None,
         };
 
-        let reqwest_client = builder.build()?;
-
-        let token: Option<String> = self.github_token.clone().unwrap_or_default();
-        let github = match token {
+        let token: Option<String> = self
+            .github_token
+            .clone()
+            .unwrap_or_default()
+            .filter(|token| !token.is_empty());
+        let github = match &token {
             Some(token) => {
-                if token.is_empty() {
-                    None
-                } else {
-                    let github = Github::new(user_agent, Credentials::Token(token))?;
-                    Some(github)
-                }
+                let github = Github::new(user_agent, Credentials::Token(token.clone()))?;
+                Some(github)
             }
             None => None,
         };
@@ -143,11 +694,85 @@ impl ClientBuilder {
         Ok(Client {
             reqwest_client,
             github,
+            github_token: token,
+            gitlab_token: self
+                .gitlab_token
+                .clone()
+                .unwrap_or_default()
+                .filter(|token| !token.is_empty()),
+            github_cache: Arc::new(Mutex::new(HashMap::new())),
+            github_api: match &self.github_api {
+                Some(url) => url.clone(),
+                None => Url::parse(GITHUB_API)?,
+            },
+            custom_headers: self.custom_headers.clone().unwrap_or_default(),
+            max_redirects,
+            redirect_policy: self.redirect_policy.unwrap_or_default(),
+            url_rewriter: self.url_rewriter.clone().unwrap_or(None),
+            status_classifier: self.status_classifier.clone().unwrap_or(None),
             includes: self.includes.clone().unwrap_or(None),
-            excludes: self.build_excludes(),
+            excludes: self.build_excludes()?,
+            uncheckable_schemes: match &self.uncheckable_schemes {
+                Some(schemes) => schemes
+                    .iter()
+                    .map(|scheme| scheme.trim_end_matches(':').to_lowercase())
+                    .collect(),
+                None => DEFAULT_UNCHECKABLE_SCHEMES
+                    .iter()
+                    .map(|scheme| scheme.to_string())
+                    .collect(),
+            },
             scheme,
             method: self.method.clone().unwrap_or(reqwest::Method::GET),
+            host_methods: Arc::new(HostMethods::new(
+                self.host_methods.clone().unwrap_or_default(),
+            )?),
             accepted: self.accepted.clone().unwrap_or(None),
+            url_accepted: UrlAccepted::new(self.url_accepted.clone().unwrap_or_default())?,
+            retry_on_status: self.retry_on_status.clone().unwrap_or_default(),
+            detect_auth_required: self.detect_auth_required.unwrap_or(false),
+            check_mail_reachability: self.check_mail_reachability.unwrap_or(false),
+            capture_headers: self.capture_headers.unwrap_or(false),
+            check_image_types: self.check_image_types.unwrap_or(false),
+            content_types: ContentTypes::new(self.content_types.clone().unwrap_or_default())?,
+            soft_404_patterns: self.soft_404_patterns.clone().unwrap_or(None),
+            expect_content: self.expect_content.clone().unwrap_or(None),
+            min_content_length: self
+                .min_content_length
+                .unwrap_or(DEFAULT_MIN_CONTENT_LENGTH),
+            required_headers: RequiredHeaders::new(
+                self.required_headers.clone().unwrap_or_default(),
+            )?,
+            content_assertions: ContentAssertions::new(
+                self.content_assertions.clone().unwrap_or_default(),
+            )?,
+            scheme_timeouts: self.scheme_timeouts.clone().unwrap_or_default(),
+            check_anchors: self.check_anchors.unwrap_or(false),
+            anchor_style: self.anchor_style.unwrap_or_default(),
+            anchors_ignore_case: self.anchors_ignore_case.unwrap_or(false),
+            retry_budget: self
+                .max_total_retries
+                .unwrap_or(None)
+                .map(|max| Arc::new(AtomicUsize::new(max))),
+            host_throttle: Arc::new(self.build_host_throttle()?),
+            rate_limiter: self.request_rate.unwrap_or(None).map(|rate| {
+                Arc::new(RateLimiter::new(
+                    rate,
+                    self.request_rate_per_host.unwrap_or(false),
+                ))
+            }),
+            crawl_delays,
+            oauth2: match self.oauth2.clone().unwrap_or(None) {
+                Some(credentials) => Some(Arc::new(OAuth2::new(credentials, aux_timeout)?)),
+                None => None,
+            },
+            cache: self.cache.clone(),
+            wayback_fallback: self.wayback_fallback.unwrap_or(false),
+            wayback_api: match &self.wayback_api {
+                Some(url) => url.clone(),
+                None => Url::parse(WAYBACK_API)?,
+            },
+            aux_timeout,
         })
     }
 }
@@ -158,47 +783,234 @@ impl Client {
             Ok(request) => request,
             Err(_e) => bail!("Invalid URI:"),
         };
-        if self.excluded(&request) {
-            return Ok(Response::new(request.uri, Status::Excluded, request.source));
+        // `retries` and `status` get filled in while checking
+        let span = tracing::info_span!(
+            "check",
+            uri = %request.uri,
+            host = request.uri.domain().unwrap_or_default(),
+            retries = 0,
+            status = field::Empty,
+            duration_ms = field::Empty,
+        );
+        let start = Instant::now();
+        let mut response = self.check_request(request).instrument(span.clone()).await;
+        let duration = start.elapsed();
+        response.duration = Some(duration);
+        span.record("status", &field::display(&response.status));
+        span.record("duration_ms", &(duration.as_millis() as u64));
+        span.in_scope(|| tracing::debug!("Checked link"));
+        Ok(response)
+    }
+
+    async fn check_request(&self, request: Request) -> Response {
+        let request = self.rewrite(request);
+        if request.inline_ignore || self.excluded(&request) {
+            return Response::new(request.uri, Status::Excluded, request.source);
+        }
+        let excluded_reason = match &request.uri {
+            uri if self.uncheckable(uri) => Some(UNSUPPORTED_SCHEME),
+            Uri::Website(url) if url.scheme() == "javascript" => Some(REQUIRES_JAVASCRIPT),
+            _ => None,
+        };
+        if let Some(reason) = excluded_reason {
+            let mut response = Response::new(request.uri, Status::Excluded, request.source);
+            response.kind = request.kind;
+            response.line = request.line;
+            response.excluded_reason = Some(reason);
+            return response;
         }
-        let status = match request.uri {
-            Uri::Website(ref url) => self.check_website(&url).await,
+        let (status, details) = match request.uri {
+            Uri::Website(ref url) if request.anchor_found.is_some() => {
+                let status = match request.anchor_found {
+                    Some(true) => Status::Ok(http::StatusCode::OK),
+                    _ => Status::Invalid(format!(
+                        "Anchor `#{}` not found",
+                        url.fragment().unwrap_or_default()
+                    )),
+                };
+                (status, ResponseDetails::default())
+            }
+            Uri::Website(ref url) if url.scheme() == "file" => {
+                (check_file(url), ResponseDetails::default())
+            }
+            Uri::Website(ref url) if UNSUPPORTED_SCHEMES.contains(&url.scheme()) => (
+                Status::Unsupported(format!("`{}` links can't be checked", url.scheme())),
+                ResponseDetails::default(),
+            ),
+            Uri::Website(ref url) => {
+                let expect_image = self.check_image_types && request.kind == LinkKind::Image;
+                self.check_website_details(&url, expect_image).await
+            }
             Uri::Mail(ref address) => {
                 // TODO: We should not be using a HTTP status code for mail
-                match self.valid_mail(&address).await {
-                    true => Status::Ok(http::StatusCode::OK),
-                    false => Status::Error(format!("Invalid mail address: {}", address)),
+                let status = if let Some(error) = mail_syntax_error(address) {
+                    Status::Invalid(format!("Malformed mail address: {}", error))
+                } else if self.check_mail_reachability && !self.valid_mail(&address).await {
+                    Status::Error(format!("Invalid mail address: {}", address).into())
+                } else {
+                    Status::Ok(http::StatusCode::OK)
+                };
+                (status, ResponseDetails::default())
+            }
+            Uri::Git(ref remote) => {
+                let status = match request.uri.git_remote() {
+                    Some(remote) => self.check_git_remote(&remote).await,
+                    None => Status::Unsupported(format!("Unrecognized git remote {}", remote)),
+                };
+                (status, ResponseDetails::default())
+            }
+        };
+        let status = match status {
+            Status::Failed(code)
+                if self.detect_auth_required
+                    && matches!(
+                        code,
+                        http::StatusCode::UNAUTHORIZED | http::StatusCode::FORBIDDEN
+                    ) =>
+            {
+                Status::AuthRequired(code)
+            }
+            status => status,
+        };
+        let mut response = Response::new(request.uri, status, request.source);
+        response.kind = request.kind;
+        response.line = request.line;
+        response.headers = details.headers;
+        response.final_url = details.final_url;
+        response.rate_limit = details.rate_limit;
+        response.method = details.method.map(|method| method.to_string());
+        response.redirects = details.redirects;
+        response.rewritten_from = request.rewritten_from.map(|uri| uri.to_string());
+        if self.wayback_fallback && matches!(response.status, Status::Failed(_) | Status::Error(_))
+        {
+            if let Uri::Website(url) = &response.uri {
+                if url.scheme() != "file" {
+                    response.archived = self.wayback_snapshot(url).await;
                 }
             }
+        }
+        response
+    }
+
+    /// The closest snapshot of a website in the Wayback Machine, if any.
+    /// Errors of the archive itself are ignored, as the link is broken anyway.
+    async fn wayback_snapshot(&self, url: &Url) -> Option<Url> {
+        let mut api = self.wayback_api.clone();
+        api.query_pairs_mut().append_pair("url", url.as_str());
+        let response = self
+            .reqwest_client
+            .get(api)
+            .timeout(self.aux_timeout)
+            .send()
+            .await
+            .ok()?
+            .error_for_status()
+            .ok()?;
+        let body: serde_json::Value = serde_json::from_str(&response.text().await.ok()?).ok()?;
+        let closest = &body["archived_snapshots"]["closest"];
+        if closest["available"] != true {
+            return None;
+        }
+        Url::parse(closest["url"].as_str()?).ok()
+    }
+
+    /// Apply the `url_rewriter` to a request. Requests get rewritten only
+    /// once, so rewriting after collecting and again when checking is safe.
+    pub fn rewrite(&self, mut request: Request) -> Request {
+        let rewriter = match &self.url_rewriter {
+            Some(rewriter) if request.rewritten_from.is_none() => rewriter,
+            _ => return request,
         };
-        Ok(Response::new(request.uri, status, request.source))
+        let uri = (rewriter.0)(request.uri.clone());
+        if uri != request.uri {
+            request.rewritten_from = Some(std::mem::replace(&mut request.uri, uri));
+        }
+        request
     }
 
     pub async fn check_website(&self, url: &Url) -> Status {
+        self.check_website_details(url, false).await.0
+    }
+
+    async fn check_website_details(
+        &self,
+        url: &Url,
+        expect_image: bool,
+    ) -> (Status, ResponseDetails) {
+        // With a token at hand, well-known GitHub URLs get resolved through
+        // the API, which is much more lenient regarding rate limits.
+        // The API can't tell anything about the content type though.
+        if !expect_image {
+            if let Some(result) = self.check_github_api(url).await {
+                return result;
+            }
+        }
+
+        let methods = self
+            .host_methods
+            .get(url)
+            .unwrap_or_else(|| std::slice::from_ref(&self.method));
+        let mut result = self.check_with_method(url, expect_image, &methods[0]).await;
+        for method in &methods[1..] {
+            if !result.0.is_success() {
+                break;
+            }
+            result = self.check_with_method(url, expect_image, method).await;
+        }
+        result
+    }
+
+    /// Check a website with the given method, retrying transient failures
+    async fn check_with_method(
+        &self,
+        url: &Url,
+        expect_image: bool,
+        method: &reqwest::Method,
+    ) -> (Status, ResponseDetails) {
         let mut retries: i64 = 3;
         let mut wait: u64 = 1;
-        let status = loop {
-            let res = self.check_default(&url).await;
-            match res.is_success() {
-                true => return res,
-                false => {
-                    if retries > 0 {
-                        retries -= 1;
-                        sleep(Duration::from_secs(wait)).await;
-                        wait *= 2;
-                    } else {
-                        break res;
-                    }
+        let (status, details) = loop {
+            let permit = self.host_throttle.acquire(url).await;
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire(url).await;
+            }
+            if let Some(crawl_delays) = &self.crawl_delays {
+                crawl_delays.acquire(url).await;
+            }
+            let (res, details) = self.check_default(&url, expect_image, method).await;
+            drop(permit);
+            // Retrying won't change the content type
+            if res.is_success()
+                || res.is_invalid()
+                || res.is_header_mismatch()
+                || res.is_content_mismatch()
+            {
+                self.update_cache(url, &res, &details);
+                return (res, details);
+            }
+            if retries > 0 && self.is_transient(&res) && self.take_retry() {
+                retries -= 1;
+                Span::current().record("retries", &(3 - retries));
+                tracing::debug!(status = %res, "Retrying {}", url);
+                if let Some(retry_after) = details.retry_after {
+                    // Only hosts with a policy get held back for everyone
+                    self.host_throttle
+                        .back_off(url, retry_after.min(MAX_RETRY_AFTER));
                 }
+                sleep(Duration::from_secs(wait)).await;
+                wait *= 2;
+            } else {
+                break (res, details);
             }
         };
+        self.update_cache(url, &status, &details);
         // Pull out the heavy weapons in case of a failed normal request.
         // This could be a Github URL and we run into the rate limiter.
         if let Ok((owner, repo)) = self.extract_github(url.as_str()) {
-            return self.check_github(owner, repo).await;
+            return (self.check_github(owner, repo).await, details);
         }
 
-        status
+        (status, details)
     }
 
     async fn check_github(&self, owner: String, repo: String) -> Status {
@@ -206,29 +1018,582 @@ impl Client {
             Some(github) => {
                 let repo = github.repo(owner, repo).get().await;
                 match repo {
-                    Err(e) => Status::Error(format!("{}", e)),
+                    Err(e) => Status::Error(ErrorChain::from_error(&e)),
                     Ok(_) => Status::Ok(http::StatusCode::OK),
                 }
             }
             None => Status::Error(
                 "GitHub token not specified. To check GitHub links reliably, \
                 use `--github-token` flag / `GITHUB_TOKEN` env var."
-                    .to_string(),
+                    .into(),
             ),
         }
     }
 
-    async fn check_default(&self, url: &Url) -> Status {
-        let request = self
+    /// Resolve a GitHub URL through the REST API.
+    /// Returns `None` if there is no token, the URL shape isn't recognized,
+    /// or the API didn't give a definite answer (e.g. when rate-limited);
+    /// in that case the caller should fall back to a normal HTTP request.
+    async fn check_github_api(&self, url: &Url) -> Option<(Status, ResponseDetails)> {
+        let token = self.github_token.as_ref()?;
+        let resource = GithubResource::parse(url)?;
+
+        let cached = self.github_cache.lock().unwrap().get(&resource).copied();
+        match cached {
+            Some(true) => {
+                return Some((Status::Ok(http::StatusCode::OK), ResponseDetails::default()))
+            }
+            Some(false) => {
+                return Some((
+                    Status::Failed(http::StatusCode::NOT_FOUND),
+                    ResponseDetails::default(),
+                ))
+            }
+            None => {}
+        }
+
+        let response = self
             .reqwest_client
-            .request(self.method.clone(), url.as_str());
-        let res = request.send().await;
-        match res {
-            Ok(response) => Status::new(response.status(), self.accepted.clone()),
+            .get(&resource.api_url(&self.github_api))
+            .header(header::AUTHORIZATION, format!("token {}", token))
+            .header(header::ACCEPT, "application/vnd.github.v3+json")
+            .send()
+            .await
+            .ok()?;
+
+        let code = response.status();
+        let details = ResponseDetails {
+            rate_limit: RateLimit::from_headers(response.headers()),
+            ..ResponseDetails::default()
+        };
+        if code.is_success() {
+            self.github_cache.lock().unwrap().insert(resource, true);
+            return Some((Status::Ok(code), details));
+        }
+        // A missing repository looks exactly like a regular github.com page
+        // (e.g. `github.com/settings/tokens`), so only trust the API for
+        // more specific resources.
+        if code == http::StatusCode::NOT_FOUND && !matches!(resource, GithubResource::Repo { .. }) {
+            return Some((Status::Failed(code), details));
+        }
+        None
+    }
+
+    /// Resolve the GitHub issues and pull requests among `requests` ahead of
+    /// checking them, with one GraphQL query per repository instead of one
+    /// REST request per link. Only works with a GitHub token.
+    /// Links the queries can't resolve (e.g. because of a rate limit) get
+    /// resolved one by one when checked, like other GitHub links.
+    pub async fn resolve_github_links<'a, I>(&self, requests: I)
+    where
+        I: IntoIterator<Item = &'a Request>,
+    {
+        let token = match &self.github_token {
+            Some(token) => token,
+            None => return,
+        };
+        let resources: HashSet<GithubResource> = requests
+            .into_iter()
+            .map(|request| self.rewrite(request.clone()))
+            .filter(|request| !(request.inline_ignore || self.excluded(request)))
+            // Images get checked for their content type, see `check_website_details`
+            .filter(|request| !(self.check_image_types && request.kind == LinkKind::Image))
+            .filter_map(|request| match &request.uri {
+                Uri::Website(url) => GithubResource::parse(url),
+                _ => None,
+            })
+            .collect();
+        let endpoint = GithubBatch::endpoint(&self.github_api);
+        join_all(GithubBatch::group(resources).into_iter().map(|batch| {
+            let request = self
+                .reqwest_client
+                .post(endpoint.clone())
+                .bearer_auth(token)
+                .timeout(self.aux_timeout)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(batch.query().to_string());
+            async move {
+                let response = match request.send().await {
+                    Ok(response) if response.status().is_success() => response,
+                    _ => return,
+                };
+                let body = response.text().await.unwrap_or_default();
+                if let Ok(body) = serde_json::from_str::<serde_json::Value>(&body) {
+                    let mut cache = self.github_cache.lock().unwrap();
+                    cache.extend(batch.resolve(&body));
+                }
+            }
+        }))
+        .await;
+    }
+
+    /// Check that the repository of a git remote exists through the API
+    /// of GitHub or GitLab. Remotes on other hosts, or on hosts without a
+    /// token, can't be verified.
+    async fn check_git_remote(&self, remote: &GitRemote) -> Status {
+        let is_gitlab = remote.host == "gitlab.com" || remote.host.starts_with("gitlab.");
+        let request = match (&self.github_token, &self.gitlab_token) {
+            (Some(token), _) if remote.host == "github.com" => {
+                let resource = GithubResource::Repo {
+                    owner: remote.owner.clone(),
+                    repo: remote.repo.clone(),
+                };
+                self.reqwest_client
+                    .get(&resource.api_url(&self.github_api))
+                    .header(header::AUTHORIZATION, format!("token {}", token))
+                    .header(header::ACCEPT, "application/vnd.github.v3+json")
+            }
+            (_, Some(token)) if is_gitlab => {
+                let project = format!("{}/{}", remote.owner, remote.repo).replace('/', "%2F");
+                self.reqwest_client
+                    .get(&format!(
+                        "https://{}/api/v4/projects/{}",
+                        remote.host, project
+                    ))
+                    .header("PRIVATE-TOKEN", token.as_str())
+            }
+            _ => {
+                return Status::Unsupported(format!(
+                    "Unverifiable scheme: git remotes on {} can only be checked \
+                    on GitHub or GitLab with an API token",
+                    remote.host
+                ))
+            }
+        };
+        match request.send().await {
+            Ok(response) => Status::new(response.status(), None),
             Err(e) => e.into(),
         }
     }
 
+    /// Whether the link can be revalidated with a conditional request.
+    /// Checks which need the body or the content type can't be answered
+    /// by a `304 Not Modified`.
+    fn can_revalidate(&self, url: &Url, expect_image: bool) -> bool {
+        let check_anchor = self.check_anchors && url.fragment().map_or(false, |f| !f.is_empty());
+        let check_length = self
+            .expect_content
+            .as_ref()
+            .map_or(false, |patterns| patterns.is_match(url.as_str()));
+        let check_headers = self
+            .required_headers
+            .as_ref()
+            .map_or(false, |required| required.patterns.is_match(url.as_str()));
+        let check_content = self
+            .content_assertions
+            .as_ref()
+            .map_or(false, |assertions| {
+                assertions.patterns.is_match(url.as_str())
+            });
+        let check_type = self
+            .content_types
+            .as_ref()
+            .map_or(false, |types| types.patterns.is_match(url.as_str()));
+        !(expect_image
+            || check_type
+            || check_anchor
+            || check_length
+            || check_headers
+            || check_content
+            || self.soft_404_patterns.is_some())
+    }
+
+    fn update_cache(&self, url: &Url, status: &Status, details: &ResponseDetails) {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return,
+        };
+        match status {
+            Status::Ok(_) if details.revalidated => cache.refresh(url),
+            Status::Ok(code) => cache.store(
+                url,
+                *code,
+                details.etag.clone(),
+                details.last_modified.clone(),
+            ),
+            _ => cache.remove(url),
+        }
+    }
+
+    /// The status of a response, judged by the accepted codes of the first
+    /// pattern matching the link or else by the global `accepted` codes
+    fn status_for(&self, url: &Url, code: http::StatusCode) -> Status {
+        match self
+            .url_accepted
+            .as_ref()
+            .and_then(|accepted| accepted.get(url))
+        {
+            Some(accepted) if accepted.contains(&code) => Status::Ok(code),
+            Some(_) if code.is_redirection() => Status::Redirected(code),
+            Some(_) => Status::Failed(code),
+            None => Status::new(code, self.accepted.clone()),
+        }
+    }
+
+    /// Check if a failure might go away when retrying
+    fn is_transient(&self, status: &Status) -> bool {
+        match status {
+            Status::Timeout(_) | Status::Error(_) => true,
+            Status::Failed(code) => {
+                DEFAULT_RETRY_STATUS.contains(code) || self.retry_on_status.contains(code)
+            }
+            _ => false,
+        }
+    }
+
+    /// Take a retry from the run-wide retry budget.
+    /// Returns `false` if the budget is exhausted.
+    fn take_retry(&self) -> bool {
+        match &self.retry_budget {
+            None => true,
+            Some(budget) => budget
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .is_ok(),
+        }
+    }
+
+    async fn check_default(
+        &self,
+        url: &Url,
+        expect_image: bool,
+        method: &reqwest::Method,
+    ) -> (Status, ResponseDetails) {
+        let cached = match &self.cache {
+            Some(cache) if self.can_revalidate(url, expect_image) => cache.get(url),
+            _ => None,
+        };
+        let mut conditional = HeaderMap::new();
+        if let Some(cached) = &cached {
+            if let Some(etag) = cached.etag.as_deref().and_then(|v| v.parse().ok()) {
+                conditional.insert(header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) =
+                cached.last_modified.as_deref().and_then(|v| v.parse().ok())
+            {
+                conditional.insert(header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        match self
+            .send_following_redirects(url, method, conditional)
+            .await
+        {
+            Ok((response, redirects)) => {
+                let mut details = match self.capture_headers {
+                    true => ResponseDetails {
+                        headers: Some(response.headers().clone()),
+                        final_url: Some(response.url().clone()),
+                        ..ResponseDetails::default()
+                    },
+                    false => ResponseDetails::default(),
+                };
+                details.rate_limit = RateLimit::from_headers(response.headers());
+                details.retry_after = retry_after(response.headers());
+                let header_value = |name: header::HeaderName| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(String::from)
+                };
+                details.method = Some(method.clone());
+                details.redirects = redirects;
+                details.etag = header_value(header::ETAG);
+                details.last_modified = header_value(header::LAST_MODIFIED);
+                // The resource didn't change since it last worked,
+                // so it gets judged by the status it was cached with
+                let revalidated = cached
+                    .as_ref()
+                    .filter(|_| response.status() == http::StatusCode::NOT_MODIFIED)
+                    .and_then(|cached| http::StatusCode::from_u16(cached.status).ok());
+                let code = revalidated.unwrap_or_else(|| response.status());
+                let mut status = self.status_for(url, code);
+                if let Some(classifier) = &self.status_classifier {
+                    let info = ResponseInfo {
+                        url,
+                        status: code,
+                        headers: response.headers(),
+                        final_url: response.url(),
+                    };
+                    status = (classifier.0)(&info, status);
+                }
+                // Links get only revalidated if there's nothing in the body to check
+                if revalidated.is_some() {
+                    details.revalidated = true;
+                    return (status, details);
+                }
+                if expect_image && status.is_success() {
+                    if let Some(status) = check_image_content_type(response.headers()) {
+                        return (status, details);
+                    }
+                }
+                if let (Some(types), true) = (&self.content_types, status.is_success()) {
+                    if let Some(status) = types.check(url, response.headers()) {
+                        return (status, details);
+                    }
+                }
+                if let (Some(required), true) = (&self.required_headers, status.is_success()) {
+                    if let Some(status) = required.check(url, response.headers()) {
+                        return (status, details);
+                    }
+                }
+                if !(status.is_success() || matches!(status, Status::Redirected(_))) {
+                    return (status, details);
+                }
+
+                let target = redirect_target(url, &response);
+                let check_anchor = self.check_anchors
+                    && status.is_success()
+                    && url.fragment().map_or(false, |f| !f.is_empty());
+                let check_title =
+                    self.soft_404_patterns.is_some() && target.is_some() && status.is_success();
+                let check_length = status.is_success()
+                    && self
+                        .expect_content
+                        .as_ref()
+                        .map_or(false, |patterns| patterns.is_match(url.as_str()));
+                // Responses to `HEAD` requests have no body to search
+                let check_content = status.is_success()
+                    && *method != reqwest::Method::HEAD
+                    && self
+                        .content_assertions
+                        .as_ref()
+                        .map_or(false, |assertions| {
+                            assertions.patterns.is_match(url.as_str())
+                        });
+                let mut length = match check_length {
+                    true => declared_length(response.headers()),
+                    false => None,
+                };
+                // Responses to `HEAD` requests have no body to measure
+                let measure_body =
+                    check_length && length.is_none() && *method != reqwest::Method::HEAD;
+                let body = if check_content {
+                    let body = read_body(response, MAX_ASSERTED_BODY_SIZE).await;
+                    if check_length && length.is_none() {
+                        length = body.as_ref().map(|body| body.len() as u64);
+                    }
+                    body
+                } else if check_anchor || check_title {
+                    let body = response.text().await.ok();
+                    if check_length && length.is_none() {
+                        length = body.as_ref().map(|body| body.len() as u64);
+                    }
+                    body
+                } else if measure_body {
+                    length = body_length(response, self.min_content_length).await;
+                    None
+                } else {
+                    None
+                };
+
+                if let Some(status) = self.check_soft_404(target.as_ref(), body.as_deref()) {
+                    return (status, details);
+                }
+                if check_anchor {
+                    if let Some(status) = self.check_anchor(url, body.as_deref()) {
+                        return (status, details);
+                    }
+                }
+                if let (Some(assertions), true) = (&self.content_assertions, check_content) {
+                    if let Some(status) = assertions.check(url, body.as_deref().unwrap_or_default())
+                    {
+                        return (status, details);
+                    }
+                }
+                match length {
+                    Some(length) if check_length && length < self.min_content_length => {
+                        let status = Status::Invalid(format!(
+                            "Body of {} bytes is shorter than the expected {} bytes",
+                            length, self.min_content_length
+                        ));
+                        (status, details)
+                    }
+                    _ => (status, details),
+                }
+            }
+            Err(status) => (
+                status,
+                ResponseDetails {
+                    method: Some(method.clone()),
+                    ..ResponseDetails::default()
+                },
+            ),
+        }
+    }
+
+    /// Send a request and follow up to `max_redirects` redirects, keeping
+    /// track of the visited URLs to tell a redirect loop apart from a long
+    /// redirect chain. Returns the final response along with the redirects
+    /// followed, or the status if no final response was received.
+    /// A redirect beyond the limits of the `redirect_policy` is returned as
+    /// the final response.
+    async fn send_following_redirects(
+        &self,
+        url: &Url,
+        method: &reqwest::Method,
+        headers: HeaderMap,
+    ) -> Result<(reqwest::Response, Vec<Redirect>), Status> {
+        // Fragments never get sent to the server
+        let mut origin = url.clone();
+        origin.set_fragment(None);
+        let mut current = origin.clone();
+        let mut redirects: Vec<Redirect> = vec![];
+        loop {
+            let send = |bearer: Option<&str>| {
+                let mut request = self
+                    .reqwest_client
+                    .request(method.clone(), current.as_str())
+                    .headers(self.custom_headers_for(&origin, &current))
+                    .headers(headers.clone());
+                if let Some(bearer) = bearer {
+                    request = request.bearer_auth(bearer);
+                }
+                if let Some(timeout) = self.scheme_timeouts.get(current.scheme()) {
+                    request = request.timeout(*timeout);
+                }
+                request.send()
+            };
+            let bearer = self.oauth2_token(&current).await?;
+            let mut response = send(bearer.as_deref()).await?;
+            if let (Some(rejected), http::StatusCode::UNAUTHORIZED) = (&bearer, response.status()) {
+                // The token may have expired or been revoked mid-run
+                let oauth2 = self.oauth2.as_ref().unwrap();
+                oauth2.reject(rejected).await;
+                let bearer = self.oauth2_token(&current).await?;
+                response = send(bearer.as_deref()).await?;
+            }
+            let mut target = match redirect_location(&current, &response) {
+                Some(target) => target,
+                None => return Ok((response, redirects)),
+            };
+            if !self.redirect_policy.allows(response.status(), &redirects) {
+                return Ok((response, redirects));
+            }
+            target.set_fragment(None);
+            if target == origin || redirects.iter().any(|r| r.url == target) {
+                let mut chain = vec![origin];
+                chain.extend(redirects.into_iter().map(|r| r.url));
+                chain.push(target);
+                return Err(Status::RedirectLoop(chain));
+            }
+            // Following the same chain again won't end any sooner,
+            // so this mustn't be a transient `Status::Error`
+            if redirects.len() >= self.max_redirects {
+                return Err(Status::Invalid(format!(
+                    "Too many redirects: gave up after {} redirects, at {}",
+                    redirects.len(),
+                    current
+                )));
+            }
+            redirects.push(Redirect {
+                code: response.status(),
+                url: target.clone(),
+            });
+            current = target;
+        }
+    }
+
+    /// Fetch a page to crawl for more links, with the same headers,
+    /// certificates and OAuth2 tokens as the links on it get checked with.
+    /// Redirects are followed like for links. Times out after `aux_timeout`.
+    pub async fn fetch_page(&self, url: &Url) -> Result<reqwest::Response> {
+        let send = self.send_following_redirects(url, &reqwest::Method::GET, HeaderMap::new());
+        match tokio::time::timeout(self.aux_timeout, send).await? {
+            Ok((response, _)) => Ok(response),
+            Err(status) => bail!("Cannot fetch {}: {}", url, status),
+        }
+    }
+
+    /// The excludes links get checked against, see `excluded`
+    pub fn excludes(&self) -> &Excludes {
+        &self.excludes
+    }
+
+    /// The OAuth2 bearer token for `url`, if it's one of the OAuth2 hosts
+    async fn oauth2_token(&self, url: &Url) -> Result<Option<String>, Status> {
+        match &self.oauth2 {
+            Some(oauth2) => oauth2
+                .token_for(&self.reqwest_client, url)
+                .await
+                .map_err(|e| Status::Error(format!("{:#}", e).into())),
+            None => Ok(None),
+        }
+    }
+
+    /// Fetch the OAuth2 token, if any, to fail early on invalid credentials.
+    /// Otherwise the token is fetched with the first request needing it.
+    pub async fn authenticate(&self) -> Result<()> {
+        if let Some(oauth2) = &self.oauth2 {
+            oauth2.token_for_any(&self.reqwest_client).await?;
+        }
+        Ok(())
+    }
+
+    /// The custom headers to send to `url`. Credentials are only sent to
+    /// the host of the link, not to other hosts it redirects to.
+    fn custom_headers_for(&self, origin: &Url, url: &Url) -> HeaderMap {
+        let mut headers = self.custom_headers.clone();
+        let same_host = origin.host_str() == url.host_str()
+            && origin.port_or_known_default() == url.port_or_known_default();
+        if !same_host {
+            for name in &[
+                header::AUTHORIZATION,
+                header::PROXY_AUTHORIZATION,
+                header::COOKIE,
+                header::WWW_AUTHENTICATE,
+            ] {
+                headers.remove(name);
+            }
+        }
+        headers
+    }
+
+    /// Returns an `Invalid` status if the request got redirected to a page
+    /// matching the soft 404 patterns, `None` otherwise.
+    fn check_soft_404(&self, target: Option<&Url>, body: Option<&str>) -> Option<Status> {
+        let patterns = self.soft_404_patterns.as_ref()?;
+        let target = target?;
+        if patterns.is_match(target.as_str()) {
+            return Some(Status::Invalid(format!(
+                "Soft 404: redirected to {}",
+                target
+            )));
+        }
+
+        let title = HTML_TITLE
+            .captures(body?)?
+            .get(1)?
+            .as_str()
+            .trim()
+            .to_string();
+        match patterns.is_match(&title) {
+            true => Some(Status::Invalid(format!(
+                "Soft 404: redirected to {} titled \"{}\"",
+                target, title
+            ))),
+            false => None,
+        }
+    }
+
+    /// Returns an `Invalid` status if the page doesn't contain the anchor
+    /// referenced by the fragment of the URL, `None` otherwise.
+    fn check_anchor(&self, url: &Url, body: Option<&str>) -> Option<Status> {
+        let fragment = url.fragment()?;
+        let body = body?;
+        // Markdown files (e.g. on raw.githubusercontent.com) don't contain any ids,
+        // so their headings get slugified the way the renderer would do it
+        let anchors = match FileType::from(url.path()) {
+            FileType::Markdown => markdown_anchors(body, self.anchor_style),
+            _ => html_anchors(body, self.anchor_style),
+        };
+        match contains_anchor(&anchors, fragment, self.anchors_ignore_case) {
+            true => None,
+            false => Some(Status::Invalid(format!("Anchor `#{}` not found", fragment))),
+        }
+    }
+
     fn extract_github(&self, url: &str) -> Result<(String, String)> {
         let re = Regex::new(r"github\.com/([^/]*)/([^/]*)")?;
         let caps = re.captures(&url).context("Invalid capture")?;
@@ -260,62 +1625,413 @@ impl Client {
         if self.excludes.ip(&request.uri) {
             return true;
         }
+        if let Some(excluded) = self.match_patterns(&request.uri) {
+            return excluded;
+        }
+        if self.excludes.host(&request.uri)
+            || self.excludes.extension(&request.uri)
+            || self.excludes.scheme(&request.uri)
+        {
+            return true;
+        }
+        if self.scheme.is_none() {
+            return false;
+        }
+        request.uri.scheme() != self.scheme
+    }
+
+    /// Whether the link is handed to another app instead of a browser,
+    /// see `ClientBuilder::uncheckable_schemes`
+    fn uncheckable(&self, uri: &Uri) -> bool {
+        let scheme = match uri {
+            Uri::Website(url) => url.scheme(),
+            Uri::Mail(_) => "mailto",
+            Uri::Git(_) => return false,
+        };
+        self.uncheckable_schemes.contains(scheme)
+    }
+
+    /// Check if the include and exclude patterns rule out a URI.
+    /// Unlike `excluded`, this needs no other information than the URI itself,
+    /// so links can be dropped right after extraction.
+    pub fn excluded_by_patterns(&self, uri: &Uri) -> bool {
+        self.match_patterns(uri) == Some(true)
+    }
+
+    /// Decide on a URI based on the include and exclude patterns alone.
+    /// Returns `None` if the patterns don't settle it.
+    fn match_patterns(&self, uri: &Uri) -> Option<bool> {
         if let Some(includes) = &self.includes {
             if includes.is_empty() {
-                return false;
+                return Some(false);
             }
-            if includes.is_match(request.uri.as_str()) {
+            if includes.is_match(uri.as_str()) {
                 // Includes take precedence over excludes
-                return false;
+                return Some(false);
             } else {
                 // In case we have includes and no excludes,
                 // skip everything that was not included
                 if self.excludes.is_empty() {
-                    return true;
+                    return Some(true);
                 }
             }
         }
-        if self.excludes.regex(request.uri.as_str()) {
-            return true;
-        }
-        if self.scheme.is_none() {
-            return false;
+        if self.excludes.regex(uri.as_str()) {
+            return Some(true);
         }
-        request.uri.scheme() != self.scheme
+        None
     }
 }
 
-/// A convenience function to check a single URI
-/// This is the most simple link check and avoids having to create a client manually.
-/// For more complex scenarios, look into using the `ClientBuilder` instead.
-pub async fn check<T: TryInto<Request>>(request: T) -> Result<Response> {
-    let client = ClientBuilder::default().build()?;
-    Ok(client.check(request).await?)
+/// Accepted status codes for links matching a pattern,
+/// see `ClientBuilder::accept_for_url`
+#[derive(Debug, Clone)]
+struct UrlAccepted {
+    patterns: RegexSet,
+    accepted: Vec<HashSet<http::StatusCode>>,
 }
 
-#[cfg(test)]
-mod test {
-    use crate::collector::Input;
+impl UrlAccepted {
+    fn new(accepted: Vec<(String, HashSet<http::StatusCode>)>) -> Result<Option<Self>> {
+        if accepted.is_empty() {
+            return Ok(None);
+        }
+        let patterns = RegexSet::new(accepted.iter().map(|(pattern, _)| pattern))
+            .context("Invalid pattern for accepted status codes")?;
+        let accepted = accepted.into_iter().map(|(_, codes)| codes).collect();
+        Ok(Some(Self { patterns, accepted }))
+    }
 
-    use super::*;
-    use http::StatusCode;
-    use std::time::{Duration, Instant};
-    use url::Url;
-    use wiremock::matchers::method;
-    use wiremock::{Mock, MockServer, ResponseTemplate};
+    /// The accepted codes of the first pattern matching the URL
+    fn get(&self, url: &Url) -> Option<&HashSet<http::StatusCode>> {
+        let index = self.patterns.matches(url.as_str()).iter().next()?;
+        Some(&self.accepted[index])
+    }
+}
 
-    // Note: the standard library as of Rust stable 1.47.0 does not expose
-    // "link-local" or "private" IPv6 checks.  However, one might argue
-    // that these concepts do exist in IPv6, albeit the naming is different.
-    // See: https://en.wikipedia.org/wiki/Link-local_address#IPv6
-    // See: https://en.wikipedia.org/wiki/Private_network#IPv6
-    // See: https://doc.rust-lang.org/stable/std/net/struct.Ipv6Addr.html#method.is_unicast_link_local
-    const V4_PRIVATE_CLASS_A: &str = "http://10.0.0.1";
-    const V4_PRIVATE_CLASS_B: &str = "http://172.16.0.1";
-    const V4_PRIVATE_CLASS_C: &str = "http://192.168.0.1";
+/// Content types expected from links matching a pattern,
+/// see `ClientBuilder::expect_content_type`
+#[derive(Debug, Clone)]
+struct ContentTypes {
+    patterns: RegexSet,
+    /// Lowercase, e.g. `application/pdf` or `image/*`
+    types: Vec<Vec<String>>,
+}
 
-    const V4_LOOPBACK: &str = "http://127.0.0.1";
-    const V6_LOOPBACK: &str = "http://[::1]";
+impl ContentTypes {
+    fn new(expected: Vec<(String, Vec<String>)>) -> Result<Option<Self>> {
+        if expected.is_empty() {
+            return Ok(None);
+        }
+        let patterns = RegexSet::new(expected.iter().map(|(pattern, _)| pattern))
+            .context("Invalid pattern for expected content types")?;
+        let types = expected
+            .into_iter()
+            .map(|(_, types)| {
+                types
+                    .iter()
+                    .map(|content_type| content_type.trim().to_lowercase())
+                    .collect()
+            })
+            .collect();
+        Ok(Some(Self { patterns, types }))
+    }
+
+    /// Returns a `HeaderMismatch` status if the content type isn't one of
+    /// those expected by the first pattern matching the URL, `None` otherwise.
+    fn check(&self, url: &Url, headers: &HeaderMap) -> Option<Status> {
+        let index = self.patterns.matches(url.as_str()).iter().next()?;
+        let expected = &self.types[index];
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        let matches = |expected: &String| match expected.strip_suffix("/*") {
+            Some(kind) => essence.split('/').next() == Some(kind),
+            None => *expected == essence,
+        };
+        if expected.iter().any(matches) {
+            return None;
+        }
+        Some(Status::HeaderMismatch(match content_type {
+            "" => format!(
+                "Expected content type `{}`, but got none",
+                expected.join("` or `")
+            ),
+            content_type => format!(
+                "Expected content type `{}`, got `{}`",
+                expected.join("` or `"),
+                content_type
+            ),
+        }))
+    }
+}
+
+/// Headers required in responses to links matching a pattern,
+/// see `ClientBuilder::require_header`
+#[derive(Debug, Clone)]
+struct RequiredHeaders {
+    patterns: RegexSet,
+    headers: Vec<(header::HeaderName, Option<String>)>,
+}
+
+impl RequiredHeaders {
+    fn new(required: Vec<(String, header::HeaderName, Option<String>)>) -> Result<Option<Self>> {
+        if required.is_empty() {
+            return Ok(None);
+        }
+        let patterns = RegexSet::new(required.iter().map(|(pattern, _, _)| pattern))
+            .context("Invalid pattern for a required header")?;
+        let headers = required
+            .into_iter()
+            .map(|(_, name, value)| (name, value))
+            .collect();
+        Ok(Some(Self { patterns, headers }))
+    }
+
+    /// Returns a `HeaderMismatch` status for the first required header
+    /// which is missing or has another value, `None` if all match.
+    fn check(&self, url: &Url, headers: &HeaderMap) -> Option<Status> {
+        self.patterns
+            .matches(url.as_str())
+            .iter()
+            .find_map(|index| {
+                let (name, expected) = &self.headers[index];
+                let mut values = headers
+                    .get_all(name)
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .peekable();
+                if values.peek().is_none() {
+                    return Some(Status::HeaderMismatch(format!("Missing header `{}`", name)));
+                }
+                let expected = expected.as_ref()?;
+                let values: Vec<_> = values.collect();
+                match values
+                    .iter()
+                    .any(|value| value.trim().eq_ignore_ascii_case(expected))
+                {
+                    true => None,
+                    false => Some(Status::HeaderMismatch(format!(
+                        "Expected header `{}: {}`, got `{}`",
+                        name,
+                        expected,
+                        values.join(", ")
+                    ))),
+                }
+            })
+    }
+}
+
+/// Text required in or forbidden from the bodies of links matching a
+/// pattern, see `ClientBuilder::require_content`
+#[derive(Debug, Clone)]
+struct ContentAssertions {
+    patterns: RegexSet,
+    /// The text along with whether it is required or forbidden
+    assertions: Vec<(Regex, bool)>,
+}
+
+impl ContentAssertions {
+    fn new(assertions: Vec<(String, Regex, bool)>) -> Result<Option<Self>> {
+        if assertions.is_empty() {
+            return Ok(None);
+        }
+        let patterns = RegexSet::new(assertions.iter().map(|(pattern, _, _)| pattern))
+            .context("Invalid pattern for required content")?;
+        let assertions = assertions
+            .into_iter()
+            .map(|(_, text, required)| (text, required))
+            .collect();
+        Ok(Some(Self {
+            patterns,
+            assertions,
+        }))
+    }
+
+    /// Returns a `ContentMismatch` status for the first required text which
+    /// is missing or forbidden text which is found, `None` if all hold.
+    fn check(&self, url: &Url, body: &str) -> Option<Status> {
+        self.patterns
+            .matches(url.as_str())
+            .iter()
+            .find_map(|index| {
+                let (text, required) = &self.assertions[index];
+                match (text.is_match(body), required) {
+                    (false, true) => Some(Status::ContentMismatch(format!(
+                        "Missing required text `{}`",
+                        text
+                    ))),
+                    (true, false) => Some(Status::ContentMismatch(format!(
+                        "Contains forbidden text `{}`",
+                        text
+                    ))),
+                    _ => None,
+                }
+            })
+    }
+}
+
+/// Read up to `limit` bytes of the body. Invalid UTF-8 gets replaced,
+/// so a body cut off within a character can still be searched.
+async fn read_body(mut response: reqwest::Response, limit: usize) -> Option<String> {
+    let mut body = Vec::new();
+    while body.len() < limit {
+        match response.chunk().await.ok()? {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    body.truncate(limit);
+    Some(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// The body size announced in the `Content-Length` header
+fn declared_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Count the bytes of a response body. Stops once `limit` is reached,
+/// so large downloads don't get loaded completely.
+async fn body_length(mut response: reqwest::Response, limit: u64) -> Option<u64> {
+    let mut length = 0;
+    while length < limit {
+        match response.chunk().await.ok()? {
+            Some(chunk) => length += chunk.len() as u64,
+            None => break,
+        }
+    }
+    Some(length)
+}
+
+/// Parse a `Retry-After` header given in seconds.
+/// HTTP dates are rarely used for this and not supported.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers.get(header::RETRY_AFTER)?.to_str().ok()?;
+    seconds.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// The URL a redirect response points to, if it is one
+fn redirect_location(url: &Url, response: &reqwest::Response) -> Option<Url> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    let location = response.headers().get(header::LOCATION)?.to_str().ok()?;
+    url.join(location).ok()
+}
+
+/// The URL a request got redirected to, if any
+fn redirect_target(url: &Url, response: &reqwest::Response) -> Option<Url> {
+    // Unless redirects get followed, the target is only announced in the `Location` header
+    let mut target = match response.status().is_redirection() {
+        true => response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| url.join(location).ok())?,
+        false => response.url().clone(),
+    };
+    // Fragments never get sent to the server
+    let mut url = url.clone();
+    url.set_fragment(None);
+    target.set_fragment(None);
+    match target == url {
+        true => None,
+        false => Some(target),
+    }
+}
+
+/// Links to local files, e.g. relative links in HTML files, only need the
+/// file (or directory) to exist. Their fragment isn't checked.
+fn check_file(url: &Url) -> Status {
+    match url.to_file_path() {
+        Ok(path) if path.exists() => Status::Ok(http::StatusCode::OK),
+        Ok(_) => Status::Failed(http::StatusCode::NOT_FOUND),
+        Err(_) => Status::Invalid(format!("`{}` is no local file path", url)),
+    }
+}
+
+/// Returns an `Invalid` status if the response doesn't declare an image
+/// content type, `None` if it does.
+fn check_image_content_type(headers: &HeaderMap) -> Option<Status> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if content_type.trim().to_lowercase().starts_with("image/") {
+        return None;
+    }
+    Some(Status::Invalid(match content_type {
+        "" => "Expected an image, but got no content type".to_string(),
+        content_type => format!("Expected an image, got content type `{}`", content_type),
+    }))
+}
+
+/// A convenience function to check a single URI
+/// This is the most simple link check and avoids having to create a client manually.
+/// For more complex scenarios, look into using the `ClientBuilder` instead.
+pub async fn check<T: TryInto<Request>>(request: T) -> Result<Response> {
+    let client = ClientBuilder::default().build()?;
+    Ok(client.check(request).await?)
+}
+
+/// Extract all links from in-memory content and check them with the given client.
+/// Relative links get resolved against `base_url` if given, otherwise they are skipped.
+/// Nothing is read from disk, which makes this suitable for editor integrations
+/// checking unsaved files. Responses are sorted by URI.
+pub async fn check_content(
+    content: &str,
+    file_type: FileType,
+    base_url: Option<Url>,
+    client: &Client,
+) -> Vec<Response> {
+    let input_content = InputContent::from_string(content, file_type);
+    let requests = extract_links(&input_content, base_url);
+    client.resolve_github_links(&requests).await;
+    let mut responses: Vec<Response> = join_all(requests.into_iter().map(|r| client.check(r)))
+        .await
+        .into_iter()
+        // Extracted links are valid requests, so checking them can't fail
+        .filter_map(Result::ok)
+        .collect();
+    responses.sort_by(|a, b| a.uri.as_str().cmp(b.uri.as_str()));
+    responses
+}
+
+#[cfg(test)]
+mod test {
+    use crate::collector::Input;
+
+    use super::*;
+    use http::StatusCode;
+    use std::time::{Duration, Instant};
+    use url::Url;
+    use wiremock::matchers::{self, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // Note: the standard library as of Rust stable 1.47.0 does not expose
+    // "link-local" or "private" IPv6 checks.  However, one might argue
+    // that these concepts do exist in IPv6, albeit the naming is different.
+    // See: https://en.wikipedia.org/wiki/Link-local_address#IPv6
+    // See: https://en.wikipedia.org/wiki/Private_network#IPv6
+    // See: https://doc.rust-lang.org/stable/std/net/struct.Ipv6Addr.html#method.is_unicast_link_local
+    const V4_PRIVATE_CLASS_A: &str = "http://10.0.0.1";
+    const V4_PRIVATE_CLASS_B: &str = "http://172.16.0.1";
+    const V4_PRIVATE_CLASS_C: &str = "http://192.168.0.1";
+
+    const V4_LOOPBACK: &str = "http://127.0.0.1";
+    const V6_LOOPBACK: &str = "http://[::1]";
 
     const V4_LINK_LOCAL: &str = "http://169.254.0.1";
 
@@ -350,7 +2066,7 @@ mod test {
 
     #[tokio::test]
     async fn test_exponential_backoff() {
-        let template = ResponseTemplate::new(404);
+        let template = ResponseTemplate::new(500);
         let mock_server = MockServer::start().await;
         Mock::given(method("GET"))
             .respond_with(template)
@@ -398,6 +2114,76 @@ mod test {
         ));
     }
 
+    #[tokio::test]
+    async fn test_unsupported_links() {
+        let client = ClientBuilder::default().build().unwrap();
+        for link in &[
+            "git@github.com:wgalyen/kimchi.git",
+            "ssh://git@example.com/wgalyen/kimchi.git",
+            "ftp://ftp.example.com/pub/file.txt",
+        ] {
+            let res = client.check(*link).await.unwrap();
+            assert!(res.status.is_unsupported(), "{}: {}", link, res.status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uncheckable_schemes() {
+        let client = ClientBuilder::default().build().unwrap();
+        for link in &[
+            "tel:+1-555-0100",
+            "sms:+15550100",
+            "irc://irc.libera.chat/kimchi",
+        ] {
+            let res = client.check(*link).await.unwrap();
+            assert!(res.status.is_excluded(), "{}: {}", link, res.status);
+            assert_eq!(res.excluded_reason(), Some("unsupported scheme"));
+        }
+
+        let client = ClientBuilder::default()
+            .uncheckable_schemes(vec!["Slack:".to_string(), "mailto".to_string()])
+            .build()
+            .unwrap();
+        for link in &["slack://open?team=T123", "mailto:kimchi@example.org"] {
+            let res = client.check(*link).await.unwrap();
+            assert!(res.status.is_excluded(), "{}: {}", link, res.status);
+        }
+        let res = client.check("tel:+1-555-0100").await.unwrap();
+        assert!(!res.status.is_excluded());
+        assert_eq!(res.excluded_reason(), None);
+    }
+
+    #[tokio::test]
+    async fn test_javascript_links() {
+        let client = ClientBuilder::default().build().unwrap();
+        for link in &["javascript:void(0)", "JavaScript:openMenu('main')"] {
+            let res = client.check(*link).await.unwrap();
+            assert!(res.status.is_excluded(), "{}: {}", link, res.status);
+            assert_eq!(res.excluded_reason(), Some("requires JavaScript"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let page = dir.path().join("page.html");
+        std::fs::write(&page, "<h1>Page</h1>").unwrap();
+        let client = ClientBuilder::default().build().unwrap();
+        let check = |path: &std::path::Path| {
+            let mut url = Url::from_file_path(path).unwrap();
+            url.set_fragment(Some("unchecked"));
+            let client = client.clone();
+            async move { client.check(url.as_str()).await.unwrap().status }
+        };
+
+        assert!(check(&page).await.is_success());
+        assert!(check(dir.path()).await.is_success());
+        assert_eq!(
+            check(&dir.path().join("missing.html")).await,
+            Status::Failed(StatusCode::NOT_FOUND)
+        );
+    }
+
     #[tokio::test]
     async fn test_github_nonexistent() {
         let res = ClientBuilder::default()
@@ -501,21 +2287,1273 @@ mod test {
     }
 
     #[tokio::test]
-    async fn test_include_regex() {
-        let includes = RegexSet::new(&[r"foo.github.com"]).unwrap();
+    async fn test_timeout_for_scheme() {
+        let mock_delay = Duration::from_millis(200);
+        let template = ResponseTemplate::new(200).set_delay(mock_delay);
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(template)
+            .mount(&mock_server)
+            .await;
 
-        let client = ClientBuilder::default().includes(includes).build().unwrap();
+        let client = ClientBuilder::default()
+            .timeout(Duration::from_secs(10))
+            .timeout_for_scheme("http", Duration::from_millis(10))
+            // Don't wait for the retries of the timed out request
+            .max_total_retries(0_usize)
+            .build()
+            .unwrap();
+        let resp = client.check(website_url(&mock_server.uri())).await.unwrap();
+        assert!(matches!(resp.status, Status::Timeout(_)));
+    }
+
+    #[tokio::test]
+    async fn test_capture_headers() {
+        let template = ResponseTemplate::new(200).insert_header("x-kimchi", "spicy");
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(template)
+            .mount(&mock_server)
+            .await;
+
+        let res = ClientBuilder::default()
+            .build()
+            .unwrap()
+            .check(website_url(&mock_server.uri()))
+            .await
+            .unwrap();
+        assert!(res.headers().is_none());
+        assert!(res.final_url().is_none());
 
+        let res = ClientBuilder::default()
+            .capture_headers(true)
+            .build()
+            .unwrap()
+            .check(website_url(&mock_server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(res.headers().unwrap()["x-kimchi"], "spicy");
+        assert!(res.rate_limit().is_none());
         assert_eq!(
-            client.excluded(&website_url("https://foo.github.com")),
-            false
+            res.final_url().unwrap(),
+            &Url::parse(&mock_server.uri()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conditional_requests() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(matchers::header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).insert_header("etag", "\"v1\""))
+            .mount(&mock_server)
+            .await;
+
+        let cache = LinkCache::new();
+        let client = ClientBuilder::default()
+            .cache(cache.clone())
+            .build()
+            .unwrap();
+        let url = Url::parse(&mock_server.uri()).unwrap();
+
+        let res = client.check(website_url(url.as_str())).await.unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::OK));
+        assert_eq!(cache.get(&url).unwrap().etag.as_deref(), Some("\"v1\""));
+
+        // A revalidated link reports the status it was cached with
+        let res = client.check(website_url(url.as_str())).await.unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::OK));
+        assert_eq!(cache.len(), 1);
+
+        // The classifier also sees revalidated links
+        let res = ClientBuilder::default()
+            .cache(cache.clone())
+            .status_classifier(Box::new(|response, status| {
+                assert_eq!(response.status, StatusCode::OK);
+                match status {
+                    Status::Ok(code) => Status::Failed(code),
+                    status => status,
+                }
+            }))
+            .build()
+            .unwrap()
+            .check(website_url(url.as_str()))
+            .await
+            .unwrap();
+        assert_eq!(res.status, Status::Failed(StatusCode::OK));
+        assert!(cache.is_empty());
+
+        // Without a cache, no validators get sent
+        let res = ClientBuilder::default()
+            .build()
+            .unwrap()
+            .check(website_url(url.as_str()))
+            .await
+            .unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_methods_for_host() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let check = |methods: Vec<reqwest::Method>| {
+            let client = ClientBuilder::default()
+                .method(reqwest::Method::HEAD)
+                .methods_for_host("127.0.0.1", methods)
+                .build()
+                .unwrap();
+            let uri = website_url(&mock_server.uri());
+            async move { client.check(uri).await.unwrap() }
+        };
+
+        let res = check(vec![reqwest::Method::GET]).await;
+        assert!(res.status.is_success());
+        assert_eq!(res.method(), Some("GET"));
+
+        // All methods have to pass
+        let res = check(vec![reqwest::Method::GET, reqwest::Method::HEAD]).await;
+        assert_eq!(res.status, Status::Failed(StatusCode::METHOD_NOT_ALLOWED));
+        assert_eq!(res.method(), Some("HEAD"));
+
+        // Hosts without a policy use the default method
+        let res = check(vec![]).await;
+        assert_eq!(res.status, Status::Failed(StatusCode::METHOD_NOT_ALLOWED));
+    }
+
+    #[tokio::test]
+    async fn test_require_header() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/release.zip"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-disposition", "attachment")
+                    .insert_header("x-robots-tag", "all"),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let client = ClientBuilder::default()
+            .require_header(r"\.zip$", header::CONTENT_DISPOSITION, None)
+            .require_header(
+                r"\.zip$",
+                header::HeaderName::from_static("x-robots-tag"),
+                Some("noindex".to_string()),
+            )
+            .build()
+            .unwrap();
+        let check = |path: &str| {
+            let uri = website_url(&format!("{}{}", mock_server.uri(), path));
+            let client = client.clone();
+            async move { client.check(uri).await.unwrap().status }
+        };
+
+        assert_eq!(
+            check("/release.zip").await,
+            Status::HeaderMismatch(
+                "Expected header `x-robots-tag: noindex`, got `all`".to_string()
+            )
         );
         assert_eq!(
-            client.excluded(&website_url("https://bar.github.com")),
-            true
+            check("/other.zip").await,
+            Status::HeaderMismatch("Missing header `content-disposition`".to_string())
+        );
+        assert!(check("/page.html").await.is_success());
+    }
+
+    #[tokio::test]
+    async fn test_expect_content_type() {
+        let mock_server = MockServer::start().await;
+        let typed = |content_type: &str| {
+            ResponseTemplate::new(200).insert_header("content-type", content_type)
+        };
+        Mock::given(path("/manual.pdf"))
+            .respond_with(typed("application/pdf"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/moved.pdf"))
+            .respond_with(typed("text/html; charset=utf-8"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/logo.PDF"))
+            .respond_with(typed("Image/PNG"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/empty.pdf"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let client = ClientBuilder::default()
+            .expect_content_type(r"/logo\.", vec!["image/*".to_string()])
+            .expect_content_type(r"(?i)\.pdf$", vec!["application/pdf".to_string()])
+            .build()
+            .unwrap();
+        let check = |path: &str| {
+            let uri = website_url(&format!("{}{}", mock_server.uri(), path));
+            let client = client.clone();
+            async move { client.check(uri).await.unwrap().status }
+        };
+
+        assert!(check("/manual.pdf").await.is_success());
+        assert!(check("/logo.PDF").await.is_success());
+        assert_eq!(
+            check("/moved.pdf").await,
+            Status::HeaderMismatch(
+                "Expected content type `application/pdf`, got `text/html; charset=utf-8`"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check("/empty.pdf").await,
+            Status::HeaderMismatch(
+                "Expected content type `application/pdf`, but got none".to_string()
+            )
         );
     }
 
+    #[tokio::test]
+    async fn test_require_content() {
+        let mock_server = MockServer::start().await;
+        let page = |body: &str| ResponseTemplate::new(200).set_body_string(body);
+        Mock::given(path("/docs/"))
+            .respond_with(page("<h1>Getting Started</h1>"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/docs/gutted"))
+            .respond_with(page("<h1>Page moved</h1>"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/docs/draft"))
+            .respond_with(page("<h1>Getting Started</h1><p>Lorem ipsum</p>"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/blog/"))
+            .respond_with(page("Lorem ipsum"))
+            .mount(&mock_server)
+            .await;
+
+        let client = ClientBuilder::default()
+            .require_content("/docs/", Regex::new("Getting Started").unwrap())
+            .forbid_content("/docs/", Regex::new("(?i)lorem ipsum").unwrap())
+            .build()
+            .unwrap();
+        let check = |path: &str| {
+            let uri = website_url(&format!("{}{}", mock_server.uri(), path));
+            let client = client.clone();
+            async move { client.check(uri).await.unwrap().status }
+        };
+
+        assert!(check("/docs/").await.is_success());
+        assert_eq!(
+            check("/docs/gutted").await,
+            Status::ContentMismatch("Missing required text `Getting Started`".to_string())
+        );
+        assert_eq!(
+            check("/docs/draft").await,
+            Status::ContentMismatch("Contains forbidden text `(?i)lorem ipsum`".to_string())
+        );
+        assert!(check("/blog/").await.is_success());
+    }
+
+    #[tokio::test]
+    async fn test_redirects() {
+        let mock_server = MockServer::start().await;
+        let redirect = |to: &str| {
+            ResponseTemplate::new(301)
+                .insert_header("location", format!("{}{}", mock_server.uri(), to).as_str())
+        };
+        Mock::given(path("/old"))
+            .respond_with(redirect("/new"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/new"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/ping"))
+            .respond_with(redirect("/pong"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/pong"))
+            .respond_with(redirect("/ping"))
+            .mount(&mock_server)
+            .await;
+        for i in 0..3 {
+            Mock::given(path(format!("/chain/{}", i).as_str()))
+                .respond_with(redirect(&format!("/chain/{}", i + 1)))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        let client = ClientBuilder::default()
+            .max_redirects(2_usize)
+            .build()
+            .unwrap();
+        let url = |path: &str| Url::parse(&format!("{}{}", mock_server.uri(), path)).unwrap();
+        let check = |path: &str| client.check(website_url(url(path).as_str()));
+
+        let res = check("/old").await.unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::OK));
+        assert_eq!(
+            res.redirects,
+            vec![Redirect {
+                code: StatusCode::MOVED_PERMANENTLY,
+                url: url("/new")
+            }]
+        );
+        assert_eq!(res.permanent_redirect(), Some(url("/new")));
+
+        let res = check("/ping").await.unwrap();
+        assert_eq!(
+            res.status,
+            Status::RedirectLoop(vec![url("/ping"), url("/pong"), url("/ping")])
+        );
+
+        // A long chain is no loop, and doesn't get retried
+        let res = check("/chain/0").await.unwrap();
+        assert!(matches!(res.status, Status::Invalid(_)));
+        assert!(res.status.to_string().contains("Too many redirects"));
+    }
+
+    #[tokio::test]
+    async fn test_redirect_policy() {
+        let mock_server = MockServer::start().await;
+        let redirect = |code: u16, to: &str| {
+            ResponseTemplate::new(code)
+                .insert_header("location", format!("{}{}", mock_server.uri(), to).as_str())
+        };
+        Mock::given(path("/moved"))
+            .respond_with(redirect(301, "/temporary"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/temporary"))
+            .respond_with(redirect(302, "/final"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/final"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        let url = format!("{}/moved", mock_server.uri());
+        let check = |policy: RedirectPolicy| {
+            let client = ClientBuilder::default()
+                .redirect_policy(policy)
+                .build()
+                .unwrap();
+            let url = url.clone();
+            async move { client.check(website_url(&url)).await.unwrap() }
+        };
+
+        let res = check(RedirectPolicy::default()).await;
+        assert_eq!(res.status, Status::Ok(StatusCode::OK));
+        assert_eq!(res.redirects.len(), 2);
+
+        // Permanent redirects stay visible, temporary ones get followed
+        let res = check(RedirectPolicy {
+            max_permanent: Some(0),
+            max_temporary: None,
+        })
+        .await;
+        assert_eq!(
+            res.status,
+            Status::Redirected(StatusCode::MOVED_PERMANENTLY)
+        );
+        assert!(res.redirects.is_empty());
+
+        let res = check(RedirectPolicy {
+            max_permanent: Some(1),
+            max_temporary: Some(0),
+        })
+        .await;
+        assert_eq!(res.status, Status::Redirected(StatusCode::FOUND));
+        assert_eq!(res.redirects.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_accept_for_url() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/api/jobs"))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/api/health"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/gone"))
+            .respond_with(ResponseTemplate::new(410))
+            .mount(&mock_server)
+            .await;
+        let codes = |codes: &[u16]| -> HashSet<StatusCode> {
+            codes
+                .iter()
+                .map(|code| StatusCode::from_u16(*code).unwrap())
+                .collect()
+        };
+        let client = ClientBuilder::default()
+            .accepted(codes(&[410]))
+            .accept_for_url("/api/jobs$", codes(&[202]))
+            .accept_for_url("/api/", codes(&[204]))
+            .build()
+            .unwrap();
+        let check =
+            |path: &str| client.check(website_url(&format!("{}{}", mock_server.uri(), path)));
+
+        // The first matching pattern wins
+        let res = check("/api/jobs").await.unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::ACCEPTED));
+        // Only the accepted codes of the pattern count, not any 2xx
+        let res = check("/api/health").await.unwrap();
+        assert_eq!(res.status, Status::Failed(StatusCode::OK));
+        // Other links use the global accepted codes
+        let res = check("/gone").await.unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::GONE));
+    }
+
+    #[tokio::test]
+    async fn test_respect_crawl_delay() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/robots.txt"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string("User-agent: *\nCrawl-delay: 0.2\n"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/page"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        let client = ClientBuilder::default()
+            .respect_crawl_delay(true)
+            .build()
+            .unwrap();
+        let url = format!("{}/page", mock_server.uri());
+
+        let start = Instant::now();
+        let responses = join_all((0..3).map(|_| client.check(website_url(&url)))).await;
+        assert!(start.elapsed() >= Duration::from_millis(400));
+        for res in responses {
+            assert!(res.unwrap().status.is_success());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crawl_delay_of_moved_robots_txt() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/robots.txt"))
+            .respond_with(ResponseTemplate::new(301).insert_header(
+                "location",
+                format!("{}/moved.txt", mock_server.uri()).as_str(),
+            ))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/moved.txt"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string("User-agent: *\nCrawl-delay: 0.2\n"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/page"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        let client = ClientBuilder::default()
+            .respect_crawl_delay(true)
+            .build()
+            .unwrap();
+        let url = format!("{}/page", mock_server.uri());
+
+        let start = Instant::now();
+        let responses = join_all((0..2).map(|_| client.check(website_url(&url)))).await;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        for res in responses {
+            assert!(res.unwrap().status.is_success());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aux_timeout() {
+        let mock_server = MockServer::start().await;
+        // A robots.txt taking longer than the timeout counts as missing
+        Mock::given(path("/robots.txt"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("User-agent: *\nCrawl-delay: 5\n")
+                    .set_delay(Duration::from_secs(5)),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/page"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        let client = ClientBuilder::default()
+            .respect_crawl_delay(true)
+            .aux_timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let url = format!("{}/page", mock_server.uri());
+
+        let start = Instant::now();
+        let responses = join_all((0..2).map(|_| client.check(website_url(&url)))).await;
+        assert!(start.elapsed() < Duration::from_secs(2));
+        for res in responses {
+            assert!(res.unwrap().status.is_success());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wayback_fallback() {
+        let mock_server = MockServer::start().await;
+        let gone = format!("{}/gone", mock_server.uri());
+        Mock::given(path("/gone"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/wayback/available"))
+            .and(matchers::query_param("url", gone.as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "url": gone,
+                "archived_snapshots": {
+                    "closest": {
+                        "status": "200",
+                        "available": true,
+                        "url": "http://web.archive.org/web/20210101000000/https://example.org/gone",
+                        "timestamp": "20210101000000"
+                    }
+                }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/wayback/available"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "archived_snapshots": {} })),
+            )
+            .mount(&mock_server)
+            .await;
+        let client = ClientBuilder::default()
+            .wayback_fallback(true)
+            .wayback_api(Url::parse(&format!("{}/wayback/available", mock_server.uri())).unwrap())
+            .build()
+            .unwrap();
+
+        let res = client.check(website_url(&gone)).await.unwrap();
+        assert_eq!(res.status, Status::Failed(StatusCode::NOT_FOUND));
+        assert_eq!(
+            res.archived().map(Url::as_str),
+            Some("http://web.archive.org/web/20210101000000/https://example.org/gone")
+        );
+
+        // No snapshot
+        let res = client
+            .check(website_url(&format!("{}/never", mock_server.uri())))
+            .await
+            .unwrap();
+        assert_eq!(res.status, Status::Failed(StatusCode::NOT_FOUND));
+        assert_eq!(res.archived(), None);
+    }
+
+    #[tokio::test]
+    async fn test_http_version() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        for version in &[HttpVersion::Auto, HttpVersion::Http1] {
+            let res = ClientBuilder::default()
+                .http_version(*version)
+                .build()
+                .unwrap()
+                .check(website_url(&mock_server.uri()))
+                .await
+                .unwrap();
+            assert_eq!(res.status, Status::Ok(StatusCode::OK), "{:?}", version);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_pool() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+        let client = ClientBuilder::default()
+            .pool_max_idle_per_host(1_usize)
+            .pool_idle_timeout(Duration::from_secs(30))
+            .tcp_keepalive(Duration::from_secs(60))
+            .host_policy("127.0.0.1", HostPolicy::new(Duration::from_millis(0), 1))
+            .build()
+            .unwrap();
+        for page in &["a", "b", "c"] {
+            let res = client
+                .check(website_url(&format!("{}/{}", mock_server.uri(), page)))
+                .await
+                .unwrap();
+            assert_eq!(res.status, Status::Ok(StatusCode::OK));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_url_rewriter() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        let staging = Url::parse(&mock_server.uri()).unwrap();
+        let client = ClientBuilder::default()
+            .url_rewriter(Box::new(move |uri: Uri| match uri {
+                Uri::Website(mut url) if url.host_str() == Some("docs.internal") => {
+                    url.set_host(staging.host_str()).unwrap();
+                    url.set_port(staging.port()).unwrap();
+                    url.set_scheme("http").unwrap();
+                    Uri::Website(url)
+                }
+                uri => uri,
+            }))
+            .build()
+            .unwrap();
+
+        let request = client.rewrite(website_url("https://docs.internal/setup"));
+        assert_eq!(request.uri.domain(), Some("127.0.0.1"));
+        // Rewriting only happens once
+        let res = client.check(request).await.unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::OK));
+        assert_eq!(res.rewritten_from(), Some("https://docs.internal/setup"));
+        assert!(res
+            .to_string()
+            .ends_with("(rewritten from https://docs.internal/setup)"));
+    }
+
+    #[tokio::test]
+    async fn test_status_classifier() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/teapot"))
+            .respond_with(ResponseTemplate::new(418))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/maintenance"))
+            .respond_with(ResponseTemplate::new(200).insert_header("x-maintenance", "1"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/gone"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        let client = ClientBuilder::default()
+            .status_classifier(Box::new(|response, status| {
+                if response.status.as_u16() == 418 {
+                    Status::Ok(response.status)
+                } else if response.headers.contains_key("x-maintenance") {
+                    Status::Invalid("Under maintenance".to_string())
+                } else {
+                    status
+                }
+            }))
+            .build()
+            .unwrap();
+        let check =
+            |path: &str| client.check(website_url(&format!("{}{}", mock_server.uri(), path)));
+
+        let res = check("/teapot").await.unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::IM_A_TEAPOT));
+        let res = check("/maintenance").await.unwrap();
+        assert_eq!(res.status, Status::Invalid("Under maintenance".to_string()));
+        // The built-in status is kept
+        let res = check("/gone").await.unwrap();
+        assert_eq!(res.status, Status::Failed(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_redirect_credentials() {
+        let other_host = MockServer::start().await;
+        Mock::given(matchers::header_exists("authorization"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&other_host)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&other_host)
+            .await;
+        let mock_server = MockServer::start().await;
+        Mock::given(matchers::header_exists("authorization"))
+            .respond_with(
+                ResponseTemplate::new(302).insert_header("location", other_host.uri().as_str()),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let mut custom = HeaderMap::new();
+        custom.insert(header::AUTHORIZATION, "Basic c2VjcmV0".parse().unwrap());
+        let res = ClientBuilder::default()
+            .custom_headers(custom)
+            .build()
+            .unwrap()
+            .check(website_url(&mock_server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_oauth2() {
+        let other_host = MockServer::start().await;
+        Mock::given(matchers::header_exists("authorization"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&other_host)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&other_host)
+            .await;
+        let mock_server = MockServer::start().await;
+        let token = |token: &str| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": token,
+                "token_type": "Bearer",
+                "expires_in": 3600
+            }))
+        };
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(matchers::header_exists("authorization"))
+            .respond_with(token("revoked"))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(token("fresh"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/docs"))
+            .and(matchers::header("authorization", "Bearer fresh"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/docs"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&mock_server)
+            .await;
+        // Tokens must not follow redirects to other hosts
+        let other = other_host.uri().replace("127.0.0.1", "localhost");
+        Mock::given(path("/moved"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", other.as_str()))
+            .mount(&mock_server)
+            .await;
+
+        let client = ClientBuilder::default()
+            .oauth2(OAuth2Credentials {
+                token_url: Url::parse(&format!("{}/token", mock_server.uri())).unwrap(),
+                client_id: "kimchi".to_string(),
+                client_secret: "secret".to_string(),
+                scopes: vec!["docs.read".to_string()],
+                hosts: vec!["127.0.0.1".to_string()],
+            })
+            .build()
+            .unwrap();
+        client.authenticate().await.unwrap();
+
+        // The first token got revoked, so it gets renewed after a 401
+        let res = client
+            .check(website_url(&format!("{}/docs", mock_server.uri())))
+            .await
+            .unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::OK));
+
+        let res = client
+            .check(website_url(&format!("{}/moved", mock_server.uri())))
+            .await
+            .unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_check_image_types() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/logo.png"))
+            .respond_with(ResponseTemplate::new(200).insert_header("content-type", "image/png"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/broken.png"))
+            .respond_with(ResponseTemplate::new(200).insert_header("content-type", "text/html"))
+            .mount(&mock_server)
+            .await;
+
+        let client = ClientBuilder::default()
+            .check_image_types(true)
+            .build()
+            .unwrap();
+        let image = |p: &str| {
+            website_url(&format!("{}{}", mock_server.uri(), p)).with_kind(LinkKind::Image)
+        };
+
+        let res = client.check(image("/logo.png")).await.unwrap();
+        assert!(matches!(res.status, Status::Ok(_)));
+        let res = client.check(image("/broken.png")).await.unwrap();
+        assert!(matches!(res.status, Status::Invalid(_)));
+
+        // Regular links don't need to be images
+        let res = client
+            .check(website_url(&format!("{}/broken.png", mock_server.uri())))
+            .await
+            .unwrap();
+        assert!(matches!(res.status, Status::Ok(_)));
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_status() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(520))
+            .mount(&mock_server)
+            .await;
+
+        // Unknown status codes are final
+        let start = Instant::now();
+        let res = ClientBuilder::default()
+            .build()
+            .unwrap()
+            .check(website_url(&mock_server.uri()))
+            .await
+            .unwrap();
+        assert!(matches!(res.status, Status::Failed(_)));
+        assert!(start.elapsed().as_secs() < 1);
+
+        let retry_on_status: HashSet<_> = [StatusCode::from_u16(520).unwrap()]
+            .iter()
+            .cloned()
+            .collect();
+        let start = Instant::now();
+        let res = ClientBuilder::default()
+            .retry_on_status(retry_on_status)
+            .max_total_retries(1_usize)
+            .build()
+            .unwrap()
+            .check(website_url(&mock_server.uri()))
+            .await
+            .unwrap();
+        assert!(matches!(res.status, Status::Failed(_)));
+        assert!(start.elapsed().as_secs() >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_mail_syntax() {
+        // Only the syntax gets checked by default, which needs no network
+        let client = ClientBuilder::default().build().unwrap();
+        let check = |address: &str| {
+            client.check(Request::new(Uri::Mail(address.to_string()), Input::Stdin))
+        };
+        let res = check("mail@example.org").await.unwrap();
+        assert_eq!(res.status, Status::Ok(StatusCode::OK));
+        let res = check("mail@example").await.unwrap();
+        assert_eq!(
+            res.status,
+            Status::Invalid(
+                "Malformed mail address: domain `example` has no top-level domain".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detect_auth_required() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&mock_server)
+            .await;
+
+        let res = ClientBuilder::default()
+            .build()
+            .unwrap()
+            .check(website_url(&mock_server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(res.status, Status::Failed(StatusCode::UNAUTHORIZED));
+
+        let res = ClientBuilder::default()
+            .detect_auth_required(true)
+            .build()
+            .unwrap()
+            .check(website_url(&mock_server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(res.status, Status::AuthRequired(StatusCode::UNAUTHORIZED));
+    }
+
+    #[tokio::test]
+    async fn test_expect_content() {
+        let mock_server = MockServer::start().await;
+        for empty in &["/empty.tar.gz", "/empty"] {
+            Mock::given(method("GET"))
+                .and(path(*empty))
+                .respond_with(ResponseTemplate::new(200))
+                .mount(&mock_server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("binary"))
+            .mount(&mock_server)
+            .await;
+
+        let client = ClientBuilder::default()
+            .expect_content(RegexSet::new(&[r"\.tar\.gz$"]).unwrap())
+            .min_content_length(4_u64)
+            .build()
+            .unwrap();
+        let check = |path: &str| {
+            let url = website_url(&format!("{}{}", mock_server.uri(), path));
+            let client = client.clone();
+            async move { client.check(url).await.unwrap().status }
+        };
+
+        assert!(matches!(check("/empty.tar.gz").await, Status::Invalid(_)));
+        assert!(matches!(check("/release.tar.gz").await, Status::Ok(_)));
+        // Only links matching the patterns are expected to have content
+        assert!(matches!(check("/empty").await, Status::Ok(_)));
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget() {
+        let mock_server = MockServer::start().await;
+        let template = ResponseTemplate::new(503);
+        Mock::given(method("GET"))
+            .respond_with(template)
+            .mount(&mock_server)
+            .await;
+
+        let client = ClientBuilder::default()
+            .max_total_retries(1_usize)
+            .build()
+            .unwrap();
+        // The clone shares the budget with the original client
+        let other = client.clone();
+
+        let start = Instant::now();
+        let res = client.check(website_url(&mock_server.uri())).await.unwrap();
+        assert!(matches!(res.status, Status::Failed(_)));
+        let res = other.check(website_url(&mock_server.uri())).await.unwrap();
+        assert!(matches!(res.status, Status::Failed(_)));
+        let end = start.elapsed();
+
+        // A single retry waits one second, the budget prevents any others
+        assert!(end.as_secs() >= 1);
+        assert!(end.as_secs() < 2);
+    }
+
+    #[tokio::test]
+    async fn test_soft_404() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/old"))
+            .respond_with(ResponseTemplate::new(302).insert_header("Location", "/not-found"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/moved"))
+            .respond_with(ResponseTemplate::new(302).insert_header("Location", "/landing"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/not-found"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/landing"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("<html><title>Page Not Found</title></html>"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let client = ClientBuilder::default()
+            .soft_404_patterns(RegexSet::new(&["/not-found$", "(?i)not found"]).unwrap())
+            .build()
+            .unwrap();
+        let check = |p: &str| client.check(website_url(&format!("{}{}", mock_server.uri(), p)));
+
+        // Matched by URL
+        let res = check("/old").await.unwrap();
+        assert!(matches!(res.status, Status::Invalid(_)));
+        // Matched by title
+        let res = check("/moved").await.unwrap();
+        assert!(matches!(res.status, Status::Invalid(_)));
+        // Without a redirect, the page itself is fine
+        let res = check("/landing").await.unwrap();
+        assert!(matches!(res.status, Status::Ok(_)));
+    }
+
+    #[tokio::test]
+    async fn test_browser_ua() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(matchers::header("user-agent", BROWSER_USER_AGENT))
+            .and(matchers::header("accept-language", BROWSER_ACCEPT_LANGUAGE))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&mock_server)
+            .await;
+
+        let res = ClientBuilder::default()
+            .user_agent("kimchi")
+            .browser_ua(true)
+            .build()
+            .unwrap()
+            .check(website_url(&mock_server.uri()))
+            .await
+            .unwrap();
+        assert!(matches!(res.status, Status::Ok(_)));
+    }
+
+    #[tokio::test]
+    async fn test_no_transfer_encoding() {
+        // Requests without a body must not announce a chunked one,
+        // some servers and proxies reject them with `400 Bad Request`
+        let mock_server = MockServer::start().await;
+        Mock::given(matchers::header_exists("transfer-encoding"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let res = ClientBuilder::default()
+            .build()
+            .unwrap()
+            .check(website_url(&mock_server.uri()))
+            .await
+            .unwrap();
+        assert!(matches!(res.status, Status::Ok(_)));
+    }
+
+    #[tokio::test]
+    async fn test_check_anchors() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string("<html><h2>Getting Started</h2></html>"),
+            )
+            .mount(&mock_server)
+            .await;
+        let check = |client: Client, fragment: &str| {
+            let url = format!("{}/#{}", mock_server.uri(), fragment);
+            async move { client.check(website_url(&url)).await.unwrap().status }
+        };
+
+        let client = ClientBuilder::default()
+            .check_anchors(true)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            check(client.clone(), "getting-started").await,
+            Status::Ok(_)
+        ));
+        assert!(matches!(
+            check(client.clone(), "Getting-Started").await,
+            Status::Invalid(_)
+        ));
+        assert!(matches!(
+            check(client, "installation").await,
+            Status::Invalid(_)
+        ));
+
+        let client = ClientBuilder::default()
+            .check_anchors(true)
+            .anchors_ignore_case(true)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            check(client, "Getting-Started").await,
+            Status::Ok(_)
+        ));
+
+        let client = ClientBuilder::default()
+            .check_anchors(true)
+            .anchor_style(AnchorStyle::Sphinx)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            check(client, "getting-started").await,
+            Status::Ok(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_check_content() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/ok"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        let content = format!("[ok]({}/ok) and [relative](missing)", mock_server.uri());
+        let base_url = Url::parse(&format!("{}/", mock_server.uri())).unwrap();
+        let client = ClientBuilder::default()
+            .max_total_retries(0_usize)
+            .build()
+            .unwrap();
+
+        let responses = check_content(&content, FileType::Markdown, Some(base_url), &client).await;
+        assert_eq!(responses.len(), 2);
+        assert!(responses[0].uri.as_str().ends_with("/missing"));
+        assert!(matches!(responses[0].status, Status::Failed(_)));
+        assert!(responses[1].uri.as_str().ends_with("/ok"));
+        assert!(matches!(responses[1].status, Status::Ok(_)));
+    }
+
+    #[tokio::test]
+    async fn test_github_batch() {
+        let mock_server = MockServer::start().await;
+        let body = r#"{"data": {"repository": {
+            "n1": {"__typename": "Issue"},
+            "n2": {"__typename": "Issue"},
+            "n3": null
+        }}}"#;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let content = "[1](https://github.com/o/r/issues/1) \
+                       [2](https://github.com/o/r/issues/2) \
+                       [3](https://github.com/o/r/issues/3)";
+        let client = ClientBuilder::default()
+            .github_token(Some("token".to_string()))
+            .github_api(Url::parse(&mock_server.uri()).unwrap())
+            .max_total_retries(0_usize)
+            .build()
+            .unwrap();
+
+        let responses = check_content(content, FileType::Markdown, None, &client).await;
+        assert_eq!(responses.len(), 3);
+        assert!(matches!(responses[0].status, Status::Ok(_)));
+        assert!(matches!(responses[1].status, Status::Ok(_)));
+        assert!(matches!(
+            responses[2].status,
+            Status::Failed(StatusCode::NOT_FOUND)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let template = ResponseTemplate::new(200)
+            .insert_header("x-ratelimit-remaining", "7")
+            .insert_header("x-ratelimit-limit", "60");
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(template)
+            .mount(&mock_server)
+            .await;
+
+        let res = ClientBuilder::default()
+            .build()
+            .unwrap()
+            .check(website_url(&mock_server.uri()))
+            .await
+            .unwrap();
+        let rate_limit = res.rate_limit().unwrap();
+        assert_eq!(rate_limit.remaining, 7);
+        assert_eq!(rate_limit.limit, Some(60));
+    }
+
+    #[tokio::test]
+    async fn test_include_regex() {
+        let includes = RegexSet::new(&[r"foo.github.com"]).unwrap();
+
+        let client = ClientBuilder::default().includes(includes).build().unwrap();
+
+        assert_eq!(
+            client.excluded(&website_url("https://foo.github.com")),
+            false
+        );
+        assert_eq!(
+            client.excluded(&website_url("https://bar.github.com")),
+            true
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exclude_hosts() {
+        let client = ClientBuilder::default()
+            .exclude_hosts(vec![
+                "*.internal.example.com".to_string(),
+                "example.*".to_string(),
+            ])
+            .build()
+            .unwrap();
+
+        assert!(client.excluded(&website_url("https://docs.internal.example.com/a")));
+        assert!(client.excluded(&website_url("https://A.B.Internal.Example.com")));
+        assert!(client.excluded(&website_url("https://example.org")));
+        assert!(!client.excluded(&website_url("https://internal.example.com")));
+        // Only the host is matched, not the rest of the URL
+        assert!(!client.excluded(&website_url("https://foo.org/example.org")));
+    }
+
+    #[tokio::test]
+    async fn test_exclude_extensions() {
+        let client = ClientBuilder::default()
+            .exclude_extensions(vec![".zip".to_string(), "TAR.GZ".to_string()])
+            .build()
+            .unwrap();
+
+        assert!(client.excluded(&website_url("https://example.org/release.zip")));
+        assert!(client.excluded(&website_url(
+            "https://example.org/release.zip?token=abc#top"
+        )));
+        assert!(client.excluded(&website_url("https://example.org/v1/Source.Tar.Gz")));
+        assert!(!client.excluded(&website_url("https://example.org/archive.gz")));
+        assert!(!client.excluded(&website_url("https://example.org/zip")));
+        assert!(!client.excluded(&website_url("https://example.org/zip/")));
+        assert!(!client.excluded(&website_url("https://example.org/page?format=zip")));
+    }
+
     #[tokio::test]
     async fn test_includes_and_excludes_empty() {
         // This is the pre-configured, empty set of excludes for a client
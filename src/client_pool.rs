@@ -1,13 +1,71 @@
 use client::Client;
 use deadpool::unmanaged::Pool;
-use tokio::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, Semaphore};
 
 use crate::{client, types};
 
+/// Number of concurrent requests at the start of a run
+const INITIAL_CONCURRENCY: usize = 2;
+
+/// Adapts the number of concurrent requests to how well the checked hosts
+/// cope with them. A run starts with a few requests and doubles them
+/// whenever a full round of requests went through, up to the pool size.
+/// Responses hinting at an overloaded server (`429 Too Many Requests`
+/// and `5xx`) halve the concurrency again.
+#[derive(Debug)]
+struct ConcurrencyRamp {
+    limit: usize,
+    max: usize,
+    /// Successful responses since the limit last changed
+    successes: usize,
+    /// Permits to take out of circulation after backing off
+    owed: usize,
+}
+
+impl ConcurrencyRamp {
+    fn new(max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            limit: INITIAL_CONCURRENCY.min(max),
+            max,
+            successes: 0,
+            owed: 0,
+        }
+    }
+
+    /// Record the status of a response and return the new limit
+    fn record(&mut self, status: &types::Status) -> usize {
+        if is_overloaded(status) {
+            let limit = (self.limit / 2).max(1);
+            self.owed += self.limit - limit;
+            self.limit = limit;
+            self.successes = 0;
+        } else {
+            self.successes += 1;
+            if self.successes >= self.limit && self.limit < self.max {
+                self.limit = (self.limit * 2).min(self.max);
+                self.successes = 0;
+            }
+        }
+        self.limit
+    }
+}
+
+fn is_overloaded(status: &types::Status) -> bool {
+    match status {
+        types::Status::Failed(code) => {
+            *code == http::StatusCode::TOO_MANY_REQUESTS || code.is_server_error()
+        }
+        _ => false,
+    }
+}
+
 pub struct ClientPool {
     tx: mpsc::Sender<types::Response>,
     rx: mpsc::Receiver<types::Request>,
     pool: deadpool::unmanaged::Pool<client::Client>,
+    max_concurrency: usize,
 }
 
 impl ClientPool {
@@ -16,16 +74,50 @@ impl ClientPool {
         rx: mpsc::Receiver<types::Request>,
         clients: Vec<Client>,
     ) -> Self {
+        let max_concurrency = clients.len();
         let pool = Pool::from(clients);
-        ClientPool { tx, rx, pool }
+        ClientPool {
+            tx,
+            rx,
+            pool,
+            max_concurrency,
+        }
     }
 
+    /// Check incoming requests until the request channel gets closed.
+    /// Concurrency ramps up gradually instead of hitting the hosts with
+    /// all clients at once, see `ConcurrencyRamp`.
     pub async fn listen(&mut self) {
+        let ramp = Arc::new(Mutex::new(ConcurrencyRamp::new(self.max_concurrency)));
+        let permits = Arc::new(Semaphore::new(ramp.lock().unwrap().limit));
         while let Some(req) = self.rx.recv().await {
+            let permit = permits
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("Concurrency semaphore closed");
             let client = self.pool.get().await;
             let tx = self.tx.clone();
+            let ramp = ramp.clone();
+            let permits = permits.clone();
             tokio::spawn(async move {
                 let resp = client.check(req).await.expect("Invalid URI");
+                {
+                    let mut ramp = ramp.lock().unwrap();
+                    let limit = ramp.limit;
+                    let new_limit = ramp.record(&resp.status);
+                    if new_limit > limit {
+                        // Permits still owed from backing off need not be handed out
+                        let grow = new_limit - limit;
+                        let settled = grow.min(ramp.owed);
+                        ramp.owed -= settled;
+                        permits.add_permits(grow - settled);
+                    }
+                    if ramp.owed > 0 {
+                        ramp.owed -= 1;
+                        permit.forget();
+                    }
+                }
                 tx.send(resp)
                     .await
                     .expect("Cannot send response to channel");
@@ -33,3 +125,45 @@ impl ClientPool {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Status;
+    use http::StatusCode;
+
+    #[test]
+    fn test_concurrency_ramp() {
+        let ok = Status::Ok(StatusCode::OK);
+        let mut ramp = ConcurrencyRamp::new(8);
+        assert_eq!(ramp.limit, 2);
+
+        // A full round of successes doubles the concurrency
+        assert_eq!(ramp.record(&ok), 2);
+        assert_eq!(ramp.record(&ok), 4);
+        for _ in 0..4 {
+            ramp.record(&ok);
+        }
+        assert_eq!(ramp.limit, 8);
+        for _ in 0..16 {
+            ramp.record(&ok);
+        }
+        assert_eq!(ramp.limit, 8);
+
+        // Client errors are no sign of an overloaded server
+        assert_eq!(ramp.record(&Status::Failed(StatusCode::NOT_FOUND)), 8);
+
+        assert_eq!(
+            ramp.record(&Status::Failed(StatusCode::TOO_MANY_REQUESTS)),
+            4
+        );
+        assert_eq!(
+            ramp.record(&Status::Failed(StatusCode::SERVICE_UNAVAILABLE)),
+            2
+        );
+        assert_eq!(ramp.owed, 6);
+        assert_eq!(ramp.record(&Status::Failed(StatusCode::BAD_GATEWAY)), 1);
+        assert_eq!(ramp.record(&Status::Failed(StatusCode::BAD_GATEWAY)), 1);
+        assert_eq!(ramp.owed, 7);
+    }
+}
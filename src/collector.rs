@@ -1,28 +1,54 @@
 use crate::{
-    extract::{extract_links, FileType},
-    Request,
+    extract::{
+        extract_links_with_options, same_document_links, sitemap_children, ExtractOptions,
+        FileType, PlaintextFinder,
+    },
+    AnchorStyle, Client, ClientBuilder, Excludes, LinkKind, Request, Uri,
 };
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, Result};
+use encoding_rs::{Encoding, WINDOWS_1252};
+use flate2::read::GzDecoder;
+use futures::future::join_all;
+use futures::stream::{self, StreamExt};
 use glob::glob_with;
+use regex::RegexSet;
 use reqwest::Url;
 use serde::Serialize;
 use shellexpand::tilde;
-use std::path::Path;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 use std::{collections::HashSet, fmt::Display};
-use tokio::fs::read_to_string;
+use tokio::fs::read;
 use tokio::io::{stdin, AsyncReadExt};
+use tokio::sync::Semaphore;
 
 const STDIN: &str = "-";
+const DEFAULT_MAX_CONCURRENCY: usize = 128;
+const DEFAULT_MAX_FETCH_CONCURRENCY: usize = 16;
+/// A sitemap index may list up to 50,000 sitemaps
+const MAX_SITEMAP_CHILDREN: usize = 50_000;
+/// HTML documents declare their charset within the first 1024 bytes
+const CHARSET_PRESCAN_LENGTH: usize = 1024;
 
+/// A source of links to check.
+/// Use the `from_*` constructors to build an input from library code.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum Input {
+    /// Remote document, fetched over HTTP
     RemoteUrl(Url),
+    /// Glob pattern matching files on the local filesystem
     FsGlob { pattern: String, ignore_case: bool },
+    /// Single file on the local filesystem
     FsPath(PathBuf),
+    /// Standard input
     Stdin,
-    String(String),
+    /// In-memory content of the given file type
+    String {
+        content: String,
+        file_type: FileType,
+    },
 }
 
 impl Serialize for Input {
@@ -44,30 +70,56 @@ impl Display for Input {
             } => write!(f, "{}", pattern),
             Input::FsPath(path) => write!(f, "{}", path.to_str().unwrap_or_default()),
             Input::Stdin => write!(f, "stdin"),
-            Input::String(_) => write!(f, "raw input string"),
+            Input::String { .. } => write!(f, "raw input string"),
         }
     }
 }
 
+/// The loaded content of an `Input`, ready for link extraction.
 #[derive(Debug)]
 pub struct InputContent {
     pub input: Input,
     pub file_type: FileType,
     pub content: String,
+    /// The encoding the content got decoded from, if it wasn't valid UTF-8
+    pub encoding: Option<&'static str>,
 }
 
 impl InputContent {
+    /// Wrap in-memory content of the given file type
     pub fn from_string(s: &str, file_type: FileType) -> Self {
         // TODO: consider using Cow (to avoid one .clone() for String types)
         Self {
-            input: Input::String(s.to_owned()),
+            input: Input::from_string(s, file_type),
             file_type,
             content: s.to_owned(),
+            encoding: None,
         }
     }
 }
 
 impl Input {
+    /// Create an input for a file on the local filesystem.
+    pub fn from_path<P: Into<PathBuf>>(path: P) -> Self {
+        Self::FsPath(path.into())
+    }
+
+    /// Create an input for a remote document.
+    pub fn from_url(url: Url) -> Self {
+        Self::RemoteUrl(url)
+    }
+
+    /// Create an input from in-memory content, e.g. a page stored in a CMS.
+    /// The file type determines how links get extracted from the content.
+    pub fn from_string<S: Into<String>>(content: S, file_type: FileType) -> Self {
+        Self::String {
+            content: content.into(),
+            file_type,
+        }
+    }
+
+    /// Parse an input from a command-line style value.
+    /// This can be `-` for stdin, a URL, a glob pattern or a file path.
     pub fn new(value: &str, glob_ignore_case: bool) -> Self {
         if value == STDIN {
             Self::Stdin
@@ -91,161 +143,757 @@ impl Input {
         }
     }
 
+    /// Load the contents of this input.
+    /// Fails on the first file which can't be read; use `load` to get
+    /// unreadable files reported as `InputError`s instead.
     pub async fn get_contents(
         &self,
         file_type_hint: Option<FileType>,
         skip_missing: bool,
     ) -> Result<Vec<InputContent>> {
+        let (contents, errors) = self.load(file_type_hint, skip_missing, false).await?;
+        match errors.into_iter().next() {
+            Some(error) => Err(anyhow!(error)),
+            None => Ok(contents),
+        }
+    }
+
+    /// Load the contents of this input, along with the files which
+    /// exist but can't be read, e.g. because of missing permissions.
+    /// Files which don't exist at all are still an error, unless
+    /// `skip_missing` is set.
+    ///
+    /// Files and stdin which aren't valid UTF-8 get decoded from the
+    /// encoding given by their byte order mark or HTML `charset`, falling
+    /// back to Windows-1252; see `InputContent::encoding`.
+    /// With `strict_encoding`, they can't be read instead.
+    pub async fn load(
+        &self,
+        file_type_hint: Option<FileType>,
+        skip_missing: bool,
+        strict_encoding: bool,
+    ) -> Result<(Vec<InputContent>, Vec<InputError>)> {
         use Input::*;
 
         match self {
             // TODO: should skip_missing also affect URLs?
-            RemoteUrl(url) => Ok(vec![Self::url_contents(url).await?]),
+            RemoteUrl(url) => Ok((vec![Self::url_contents(url).await?], vec![])),
             FsGlob {
                 pattern,
                 ignore_case,
-            } => Ok(Self::glob_contents(pattern, *ignore_case).await?),
-            FsPath(path) => {
-                let content = Self::path_content(&path).await.with_context(|| {
-                    format!(
-                        "Failed to read file: `{}`",
-                        path.to_str().unwrap_or("<MALFORMED PATH>")
-                    )
-                });
-                match content {
-                    Ok(input_content) => Ok(vec![input_content]),
-                    Err(_) if skip_missing => Ok(vec![]),
-                    Err(arg) => Err(anyhow!(arg)),
-                }
-            }
-            Stdin => Ok(vec![Self::stdin_content(file_type_hint).await?]),
-            String(s) => Ok(vec![Self::string_content(s, file_type_hint)]),
+            } => Ok(Self::glob_contents(pattern, *ignore_case, strict_encoding).await?),
+            FsPath(path) => match Self::path_content(&path, strict_encoding).await {
+                Ok(input_content) => Ok((vec![input_content], vec![])),
+                Err(_) if skip_missing => Ok((vec![], vec![])),
+                Err(e) if is_not_found(&e) => Err(e.context(format!(
+                    "Failed to read file: `{}`",
+                    path.to_str().unwrap_or("<MALFORMED PATH>")
+                ))),
+                Err(e) => Ok((vec![], vec![InputError::new(self.clone(), &e)])),
+            },
+            Stdin => Ok((
+                vec![Self::stdin_content(file_type_hint, strict_encoding).await?],
+                vec![],
+            )),
+            String { content, file_type } => Ok((
+                vec![InputContent {
+                    input: self.clone(),
+                    file_type: *file_type,
+                    content: content.clone(),
+                    encoding: None,
+                }],
+                vec![],
+            )),
         }
     }
 
     async fn url_contents(url: &Url) -> Result<InputContent> {
         let res = reqwest::get(url.clone()).await?;
+        let content_type = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
         let content = res.text().await?;
+        // URLs often have no extension, or one which tells nothing about
+        // the content (e.g. `.php`)
+        let file_type = match FileType::from(url.path()) {
+            FileType::Plaintext => FileType::from_content(&content, content_type.as_deref()),
+            file_type => file_type,
+        };
         let input_content = InputContent {
             input: Input::RemoteUrl(url.clone()),
-            file_type: FileType::from(url.as_str()),
+            file_type,
             content,
+            encoding: None,
         };
 
         Ok(input_content)
     }
 
-    async fn glob_contents(path_glob: &str, ignore_case: bool) -> Result<Vec<InputContent>> {
-        let mut contents = vec![];
-        let glob_expanded = tilde(&path_glob);
-        let mut match_opts = glob::MatchOptions::new();
+    /// A page found while crawling. Unlike inputs, pages which can't be
+    /// fetched or aren't HTML are skipped, as they are links as well and
+    /// get reported when checking.
+    async fn crawled_page(url: Url, client: &Client) -> Option<InputContent> {
+        let res = client
+            .fetch_page(&url)
+            .await
+            .ok()?
+            .error_for_status()
+            .ok()?;
+        let is_html = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map_or(false, |content_type| content_type.contains("html"));
+        if !is_html {
+            return None;
+        }
+        Some(InputContent {
+            input: Input::RemoteUrl(url),
+            file_type: FileType::Html,
+            content: res.text().await.ok()?,
+            encoding: None,
+        })
+    }
 
-        match_opts.case_sensitive = !ignore_case;
+    async fn sitemap_contents(url: Url) -> Result<InputContent> {
+        let res = reqwest::get(url.clone()).await?.error_for_status()?;
+        Ok(InputContent {
+            input: Input::RemoteUrl(url),
+            file_type: FileType::Sitemap,
+            content: res.text().await?,
+            encoding: None,
+        })
+    }
 
-        for entry in glob_with(&glob_expanded, match_opts)? {
-            match entry {
-                Ok(path) => {
-                    let content = Self::path_content(&path).await?;
-                    contents.push(content);
-                }
-                Err(e) => println!("{:?}", e),
+    async fn glob_contents(
+        path_glob: &str,
+        ignore_case: bool,
+        strict_encoding: bool,
+    ) -> Result<(Vec<InputContent>, Vec<InputError>)> {
+        let mut contents = vec![];
+        let (paths, mut errors) = expand_glob(path_glob, ignore_case)?;
+        for path in paths {
+            match Self::path_content(&path, strict_encoding).await {
+                Ok(content) => contents.push(content),
+                Err(e) => errors.push(InputError::new(Input::FsPath(path), &e)),
             }
         }
-
-        Ok(contents)
+        Ok((contents, errors))
     }
 
-    async fn path_content<P: Into<PathBuf> + AsRef<Path>>(path: P) -> Result<InputContent> {
+    async fn path_content<P: Into<PathBuf> + AsRef<Path>>(
+        path: P,
+        strict_encoding: bool,
+    ) -> Result<InputContent> {
+        let bytes = match path.as_ref().extension() {
+            Some(ext) if ext == "gz" => {
+                let mut bytes = vec![];
+                GzDecoder::new(read(&path).await?.as_slice()).read_to_end(&mut bytes)?;
+                bytes
+            }
+            _ => read(&path).await?,
+        };
+        let (content, encoding) = decode(bytes, strict_encoding)?;
+        let file_type = match path.as_ref().extension() {
+            Some(_) => FileType::from(path.as_ref()),
+            None => FileType::from_content(&content, None),
+        };
         let input_content = InputContent {
-            file_type: FileType::from(path.as_ref()),
-            content: read_to_string(&path).await?,
+            file_type,
+            content,
             input: Input::FsPath(path.into()),
+            encoding,
         };
 
         Ok(input_content)
     }
 
-    async fn stdin_content(file_type_hint: Option<FileType>) -> Result<InputContent> {
-        let mut content = String::new();
+    async fn stdin_content(
+        file_type_hint: Option<FileType>,
+        strict_encoding: bool,
+    ) -> Result<InputContent> {
+        let mut bytes = vec![];
         let mut stdin = stdin();
-        stdin.read_to_string(&mut content).await?;
+        stdin.read_to_end(&mut bytes).await?;
+        let (content, encoding) = decode(bytes, strict_encoding)?;
 
         let input_content = InputContent {
             input: Input::Stdin,
             file_type: file_type_hint.unwrap_or_default(),
             content,
+            encoding,
         };
 
         Ok(input_content)
     }
+}
+
+/// Decode the contents of a file, along with the name of its encoding if it
+/// isn't valid UTF-8. Such contents are decoded from the encoding given by
+/// their byte order mark or HTML `charset`, falling back to Windows-1252
+/// (a superset of Latin-1). Bytes which are invalid in that encoding turn
+/// into replacement characters.
+fn decode(bytes: Vec<u8>, strict: bool) -> Result<(String, Option<&'static str>)> {
+    let bytes = match String::from_utf8(bytes) {
+        Ok(content) => return Ok((content, None)),
+        Err(_) if strict => {
+            return Err(anyhow!("stream did not contain valid UTF-8"));
+        }
+        Err(e) => e.into_bytes(),
+    };
+    let encoding = Encoding::for_bom(&bytes)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| declared_charset(&bytes))
+        .unwrap_or(WINDOWS_1252);
+    let (content, encoding, _) = encoding.decode(&bytes);
+    Ok((content.into_owned(), Some(encoding.name())))
+}
+
+/// The encoding declared by a `charset` at the start of an HTML document,
+/// e.g. `<meta charset="shift_jis">`
+fn declared_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let start = &bytes[..bytes.len().min(CHARSET_PRESCAN_LENGTH)];
+    let start = String::from_utf8_lossy(start).to_ascii_lowercase();
+    let value = &start[start.find("charset=")? + "charset=".len()..];
+    let label = value
+        .trim_start_matches(|c: char| c == '"' || c == '\'')
+        .split(|c: char| c == '"' || c == '\'' || c == ';' || c == '>' || c.is_whitespace())
+        .next()?;
+    Encoding::for_label(label.as_bytes())
+}
+
+/// The paths matching a glob, along with the entries which can't be read
+fn expand_glob(path_glob: &str, ignore_case: bool) -> Result<(Vec<PathBuf>, Vec<InputError>)> {
+    let mut paths = vec![];
+    let mut errors = vec![];
+    let glob_expanded = tilde(&path_glob);
+    let mut match_opts = glob::MatchOptions::new();
+
+    match_opts.case_sensitive = !ignore_case;
+
+    for entry in glob_with(&glob_expanded, match_opts)? {
+        match entry {
+            Ok(path) => paths.push(path),
+            Err(e) => errors.push(InputError {
+                input: Input::FsPath(e.path().to_path_buf()),
+                reason: e.error().to_string(),
+            }),
+        }
+    }
+    Ok((paths, errors))
+}
+
+/// An input waiting to be collected. The files matched by a glob are
+/// collected one by one, so they get read and extracted concurrently.
+enum Pending {
+    Input(Input),
+    GlobMatch(PathBuf),
+    CrawledPage(Url),
+}
 
-    fn string_content(s: &str, file_type_hint: Option<FileType>) -> InputContent {
-        InputContent::from_string(s, file_type_hint.unwrap_or_default())
+impl Pending {
+    /// Load the contents, fetching remote documents once `fetches` has a
+    /// permit left
+    async fn load(
+        self,
+        skip_missing: bool,
+        strict_encoding: bool,
+        fetches: &Semaphore,
+        crawl_client: Option<&Client>,
+    ) -> Result<(Vec<InputContent>, Vec<InputError>)> {
+        match self {
+            Pending::Input(input @ Input::RemoteUrl(_)) => {
+                let _permit = fetches.acquire().await?;
+                input.load(None, skip_missing, strict_encoding).await
+            }
+            Pending::Input(input) => input.load(None, skip_missing, strict_encoding).await,
+            // Like in `Input::glob_contents`, unreadable matches aren't fatal
+            Pending::GlobMatch(path) => match Input::path_content(&path, strict_encoding).await {
+                Ok(content) => Ok((vec![content], vec![])),
+                Err(e) => Ok((vec![], vec![InputError::new(Input::FsPath(path), &e)])),
+            },
+            Pending::CrawledPage(url) => {
+                let client = crawl_client.ok_or_else(|| anyhow!("No client to crawl {}", url))?;
+                let _permit = fetches.acquire().await?;
+                Ok((
+                    Input::crawled_page(url, client).await.into_iter().collect(),
+                    vec![],
+                ))
+            }
+        }
     }
 }
 
-/// Fetch all unique links from a slice of inputs
+fn is_not_found(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .map_or(false, |e| e.kind() == std::io::ErrorKind::NotFound)
+}
+
+/// An input which exists, but couldn't be read,
+/// e.g. a file matched by a glob pattern without read permissions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InputError {
+    pub input: Input,
+    pub reason: String,
+}
+
+impl InputError {
+    fn new(input: Input, error: &anyhow::Error) -> Self {
+        Self {
+            input,
+            reason: error.to_string(),
+        }
+    }
+}
+
+impl Display for InputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.input, self.reason)
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Links found in the inputs, along with the inputs which couldn't be read
+#[derive(Debug, Default)]
+pub struct Collected {
+    pub links: HashSet<Request>,
+    pub input_errors: Vec<InputError>,
+    /// Inputs which got read despite a problem, e.g. files which aren't
+    /// valid UTF-8 and got decoded from another encoding
+    pub input_warnings: Vec<InputError>,
+    /// Number of input contents the links were extracted from, e.g. the
+    /// files matching a glob and the child sitemaps of a sitemap index
+    pub inputs_read: usize,
+}
+
+/// Options for collecting links from inputs.
+///
+/// Construct it with struct update syntax to stay compatible with
+/// options added in the future:
+///
+/// ```
+/// use kimchi::collector::CollectorOptions;
+///
+/// let opts = CollectorOptions {
+///     skip_missing_inputs: true,
+///     ..CollectorOptions::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct CollectorOptions {
+    /// Skip input files which don't exist instead of returning an error
+    pub skip_missing_inputs: bool,
+    /// Treat files which aren't valid UTF-8 as input errors instead of
+    /// decoding them from another encoding
+    pub strict_encoding: bool,
+    /// Maximum number of inputs processed concurrently
+    pub max_concurrency: usize,
+    /// Maximum number of remote documents fetched concurrently, i.e. remote
+    /// inputs, sitemaps listed by sitemap indexes and crawled pages.
+    /// Separate from `max_concurrency`, as fetching a document takes longer
+    /// than reading a file, while its host may be one of the checked hosts.
+    pub max_fetch_concurrency: usize,
+    /// How to find links in plaintext inputs
+    pub plaintext_finder: PlaintextFinder,
+    /// Additional HTML attributes holding a URL, e.g. `data-permalink`.
+    /// See `extract::DATA_LINK_ATTRIBUTES` for the ones known by default.
+    pub link_attributes: Vec<String>,
+    /// Don't collect links of these kinds, e.g. to not check images
+    pub skip_kinds: Vec<LinkKind>,
+    /// Exclude links whose text matches one of these patterns, e.g. links
+    /// titled "example". The text of a Markdown link or image, or of an HTML
+    /// `<a>` element is matched. A link still gets checked if it's used
+    /// elsewhere with another text.
+    pub exclude_link_text: Option<RegexSet>,
+    /// Base URLs for the inputs starting with a prefix, overriding the global
+    /// base URL. A prefix is a path (matching the files in that directory),
+    /// a URL, or `-` for stdin. The longest matching prefix wins.
+    pub input_base_urls: Vec<(String, Url)>,
+    /// Check links to anchors in the same document (e.g. `#usage`) against
+    /// its headings and ids. Works for local files and remote documents.
+    pub check_anchors: bool,
+    /// How headings are turned into anchors, see `check_anchors`
+    pub anchor_style: AnchorStyle,
+    /// Match same-document anchors case-insensitively
+    pub anchors_ignore_case: bool,
+    /// Drop links ruled out by the include and exclude patterns of this client
+    /// right after extraction, so they never get collected.
+    /// Other excludes (e.g. private IPs) still get applied when checking.
+    /// Links get rewritten by the `url_rewriter` of the client first.
+    pub filter: Option<Client>,
+    /// Follow the links of remote inputs to HTML pages of the same origin
+    /// up to this many levels deep, collecting their links as well.
+    /// Links to other origins are collected, but not followed.
+    /// Relative links of remote pages get resolved against the page itself.
+    /// `0` (the default) only collects the links of the inputs.
+    pub crawl_depth: usize,
+    /// Stop following links once this many links got collected
+    pub crawl_max_links: Option<usize>,
+    /// Don't follow links ruled out by these excludes, e.g. links to
+    /// private IPs or to excluded hosts. Use `Client::excludes` to crawl
+    /// only what gets checked.
+    pub excludes: Excludes,
+    /// Fetch the pages to crawl with this client, so they get the same
+    /// user agent, headers, certificates and timeout as the links.
+    /// Without one, a client with the default settings is used.
+    pub crawl_client: Option<Client>,
+}
+
+impl CollectorOptions {
+    /// The base URL for relative links in `input`, if it has its own
+    fn input_base_url(&self, input: &Input) -> Option<&Url> {
+        self.input_base_urls
+            .iter()
+            .filter(|(prefix, _)| has_prefix(input, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, base_url)| base_url)
+    }
+}
+
+fn has_prefix(input: &Input, prefix: &str) -> bool {
+    // `./docs` and `docs` are the same directory
+    let normalize = |path: &Path| -> PathBuf {
+        path.components()
+            .filter(|component| *component != Component::CurDir)
+            .collect()
+    };
+    match input {
+        Input::FsPath(path) => normalize(path).starts_with(normalize(Path::new(prefix))),
+        Input::RemoteUrl(url) => url.as_str().starts_with(prefix),
+        Input::Stdin => prefix == STDIN,
+        _ => false,
+    }
+}
+
+impl Default for CollectorOptions {
+    fn default() -> Self {
+        Self {
+            skip_missing_inputs: false,
+            strict_encoding: false,
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            max_fetch_concurrency: DEFAULT_MAX_FETCH_CONCURRENCY,
+            plaintext_finder: PlaintextFinder::default(),
+            link_attributes: vec![],
+            skip_kinds: vec![],
+            exclude_link_text: None,
+            input_base_urls: vec![],
+            check_anchors: false,
+            anchor_style: AnchorStyle::default(),
+            anchors_ignore_case: false,
+            filter: None,
+            crawl_depth: 0,
+            crawl_max_links: None,
+            excludes: Excludes::default(),
+            crawl_client: None,
+        }
+    }
+}
+
+/// Fetch all unique links from the given inputs
 /// All relative URLs get prefixed with `base_url` if given.
-pub async fn collect_links(
-    inputs: &[Input],
+/// The input each link was found in isn't kept; use `collect` for that.
+/// This is handy for feeding links into other tools, e.g. a custom checker.
+///
+/// Inputs don't have to live on disk; in-memory content works just as well:
+///
+/// ```
+/// use kimchi::{
+///     collector::{collect_links, CollectorOptions},
+///     extract::FileType,
+///     Input,
+/// };
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let inputs = vec![Input::from_string(
+///         "Read the [docs](https://example.org/docs).",
+///         FileType::Markdown,
+///     )];
+///     let links = collect_links(inputs, None, &CollectorOptions::default()).await?;
+///     assert_eq!(links.len(), 1);
+///     Ok(())
+/// }
+/// ```
+pub async fn collect_links<T: IntoIterator<Item = Input>>(
+    inputs: T,
+    base_url: Option<String>,
+    opts: &CollectorOptions,
+) -> Result<HashSet<Uri>> {
+    let collected = collect(inputs, base_url, opts).await?;
+    match collected.input_errors.into_iter().next() {
+        Some(error) => Err(anyhow!(error)),
+        None => Ok(collected
+            .links
+            .into_iter()
+            .map(|request| request.uri)
+            .collect()),
+    }
+}
+
+/// Like `collect_links`, but every returned `Request` holds the link as well
+/// as the input it was found in. Inputs which can't be read don't abort the
+/// collection; they are returned as `InputError`s next to the links instead.
+///
+/// Up to `max_concurrency` inputs get read and extracted at the same time,
+/// of which up to `max_fetch_concurrency` are fetched over the network.
+/// Reading is asynchronous, while the extraction runs on the blocking
+/// thread pool, as parsing large documents keeps a thread busy.
+/// With a `crawl_depth`, the pages linked by remote inputs get collected
+/// level by level; every page is read only once.
+pub async fn collect<T: IntoIterator<Item = Input>>(
+    inputs: T,
     base_url: Option<String>,
-    skip_missing_inputs: bool,
-    max_concurrency: usize,
-) -> Result<HashSet<Request>> {
+    opts: &CollectorOptions,
+) -> Result<Collected> {
     let base_url = match base_url {
         Some(url) => Some(Url::parse(&url)?),
         _ => None,
     };
+    let mut opts = opts.clone();
+    if opts.crawl_depth > 0 && opts.crawl_client.is_none() {
+        opts.crawl_client = Some(ClientBuilder::default().build()?);
+    }
+    let opts = &opts;
 
-    let (contents_tx, mut contents_rx) = tokio::sync::mpsc::channel(max_concurrency);
-
-    // extract input contents
-    for input in inputs.iter().cloned() {
-        let sender = contents_tx.clone();
+    let mut input_errors = vec![];
+    let mut pending = vec![];
+    for input in inputs {
+        match input {
+            Input::FsGlob {
+                pattern,
+                ignore_case,
+            } => {
+                let (paths, errors) = expand_glob(&pattern, ignore_case)?;
+                pending.extend(paths.into_iter().map(Pending::GlobMatch));
+                input_errors.extend(errors);
+            }
+            input => pending.push(Pending::Input(input)),
+        }
+    }
 
-        tokio::spawn(async move {
-            let contents = input.get_contents(None, skip_missing_inputs).await;
-            sender.send(contents).await
-        });
+    let mut visited: HashSet<Url> = HashSet::new();
+    for pending in &pending {
+        if let Pending::Input(Input::RemoteUrl(url)) = pending {
+            visited.insert(page_key(url));
+        }
     }
+    // Shared by all levels of crawling
+    let fetches = Arc::new(Semaphore::new(opts.max_fetch_concurrency.max(1)));
+    let (mut collected_links, errors, mut input_warnings, mut inputs_read) =
+        collect_pending(pending, base_url.as_ref(), opts, &fetches).await?;
+    input_errors.extend(errors);
 
-    // receiver will get None once all tasks are done
-    drop(contents_tx);
+    let mut frontier = collected_links.iter().cloned().collect::<Vec<_>>();
+    for _ in 0..opts.crawl_depth {
+        let mut pages = vec![];
+        for request in &frontier {
+            if matches!(opts.crawl_max_links, Some(max) if collected_links.len() >= max) {
+                break;
+            }
+            if let Some(page) = crawl_target(request, &opts.excludes) {
+                if visited.insert(page_key(&page)) {
+                    pages.push(Pending::CrawledPage(page));
+                }
+            }
+        }
+        if pages.is_empty() {
+            break;
+        }
+        let (links, _, warnings, read) =
+            collect_pending(pages, base_url.as_ref(), opts, &fetches).await?;
+        frontier = links
+            .into_iter()
+            .filter(|request| collected_links.insert(request.clone()))
+            .collect();
+        input_warnings.extend(warnings);
+        inputs_read += read;
+    }
 
-    // extract links from input contents
-    let mut extract_links_handles = vec![];
+    Ok(Collected {
+        links: collected_links,
+        input_errors,
+        input_warnings,
+        inputs_read,
+    })
+}
 
-    while let Some(result) = contents_rx.recv().await {
-        for input_content in result? {
-            let base_url = base_url.clone();
-            let handle =
-                tokio::task::spawn_blocking(move || extract_links(&input_content, base_url));
-            extract_links_handles.push(handle);
+/// The page to crawl for a link: links (not images, scripts etc.) found in
+/// a remote page pointing to another page of the same origin, unless excluded
+fn crawl_target(request: &Request, excludes: &Excludes) -> Option<Url> {
+    let page = match &request.source {
+        Input::RemoteUrl(page) => page,
+        _ => return None,
+    };
+    match &request.uri {
+        Uri::Website(url)
+            if request.kind == LinkKind::Link
+                && !request.inline_ignore
+                && url.origin() == page.origin()
+                && !is_excluded(excludes, &request.uri) =>
+        {
+            Some(url.clone())
         }
+        _ => None,
     }
+}
+
+fn is_excluded(excludes: &Excludes, uri: &Uri) -> bool {
+    excludes.regex(uri.as_str())
+        || excludes.host(uri)
+        || excludes.extension(uri)
+        || excludes.scheme(uri)
+        || excludes.ip(uri)
+}
+
+/// Pages differing only in the fragment are the same page
+fn page_key(url: &Url) -> Url {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url
+}
+
+/// Read and extract the pending inputs, up to `max_concurrency` at a time.
+/// Remote documents only get fetched with a permit of `fetches`.
+/// Returns the links, input errors, input warnings and number of inputs read.
+async fn collect_pending(
+    pending: Vec<Pending>,
+    base_url: Option<&Url>,
+    opts: &CollectorOptions,
+    fetches: &Arc<Semaphore>,
+) -> Result<(HashSet<Request>, Vec<InputError>, Vec<InputError>, usize)> {
+    let skip_missing_inputs = opts.skip_missing_inputs;
+    let strict_encoding = opts.strict_encoding;
+    let mut results = stream::iter(pending)
+        .map(|pending| {
+            let opts = opts.clone();
+            let base_url = base_url.cloned();
+            let fetches = fetches.clone();
+            async move {
+                let (contents, errors) = pending
+                    .load(
+                        skip_missing_inputs,
+                        strict_encoding,
+                        &fetches,
+                        opts.crawl_client.as_ref(),
+                    )
+                    .await?;
+                let contents = with_sitemap_children(contents, &fetches).await;
+                let read = contents.len();
+                let warnings = contents
+                    .iter()
+                    .filter_map(|content| {
+                        content.encoding.map(|encoding| InputError {
+                            input: content.input.clone(),
+                            reason: format!("Not valid UTF-8, decoded as {}", encoding),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let links = tokio::task::spawn_blocking(move || {
+                    contents
+                        .iter()
+                        .flat_map(|content| {
+                            extract_content_links(content, base_url.as_ref(), &opts)
+                        })
+                        .collect::<HashSet<Request>>()
+                })
+                .await?;
+                Ok::<_, anyhow::Error>((links, errors, warnings, read))
+            }
+        })
+        .buffer_unordered(opts.max_concurrency.max(1));
 
     // Note: we could dispatch links to be checked as soon as we get them,
     //       instead of building a HashSet with all links.
     //       This optimization would speed up cases where there's
     //       a lot of inputs and/or the inputs are large (e.g. big files).
     let mut collected_links: HashSet<Request> = HashSet::new();
-
-    for handle in extract_links_handles {
-        let links = handle.await?;
+    let mut input_errors = vec![];
+    let mut input_warnings = vec![];
+    let mut inputs_read = 0;
+    while let Some(result) = results.next().await {
+        let (links, errors, warnings, read) = result?;
         collected_links.extend(links);
+        input_errors.extend(errors);
+        input_warnings.extend(warnings);
+        inputs_read += read;
     }
+    Ok((collected_links, input_errors, input_warnings, inputs_read))
+}
 
-    Ok(collected_links)
+/// Extract the links of a single input content, including the links to
+/// anchors in the same document and applying the filter of the options
+fn extract_content_links(
+    input_content: &InputContent,
+    base_url: Option<&Url>,
+    opts: &CollectorOptions,
+) -> HashSet<Request> {
+    let crawled_page = match &input_content.input {
+        Input::RemoteUrl(url) if opts.crawl_depth > 0 => Some(url),
+        _ => None,
+    };
+    let base_url = opts
+        .input_base_url(&input_content.input)
+        .or(crawled_page)
+        .or(base_url)
+        .cloned();
+    let extract_opts = ExtractOptions {
+        plaintext_finder: Some(&opts.plaintext_finder),
+        link_attributes: &opts.link_attributes,
+        skip_kinds: &opts.skip_kinds,
+        exclude_text: opts.exclude_link_text.as_ref(),
+        check_anchors: opts.check_anchors,
+    };
+    let mut links = extract_links_with_options(input_content, base_url, &extract_opts);
+    if opts.check_anchors {
+        links.extend(same_document_links(
+            input_content,
+            opts.anchor_style,
+            opts.anchors_ignore_case,
+        ));
+    }
+    match &opts.filter {
+        Some(client) => links
+            .into_iter()
+            .map(|request| client.rewrite(request))
+            .filter(|request| !client.excluded_by_patterns(&request.uri))
+            .collect(),
+        None => links,
+    }
+}
+
+/// Add the child sitemaps listed in sitemap indexes.
+/// This only goes one level deep, so sitemap indexes referencing each other
+/// can't cause an endless recursion. Child sitemaps which can't be loaded
+/// get skipped; they are links of the index and get reported when checking.
+async fn with_sitemap_children(
+    mut contents: Vec<InputContent>,
+    fetches: &Semaphore,
+) -> Vec<InputContent> {
+    let children: HashSet<Url> = contents
+        .iter()
+        .filter(|content| content.file_type == FileType::Sitemap)
+        .flat_map(|content| sitemap_children(&content.content))
+        .filter_map(|loc| Url::parse(&loc).ok())
+        .take(MAX_SITEMAP_CHILDREN)
+        .collect();
+    let children = join_all(children.into_iter().map(|url| async move {
+        let _permit = fetches.acquire().await?;
+        Input::sitemap_contents(url).await
+    }))
+    .await;
+    contents.extend(children.into_iter().filter_map(Result::ok));
+    contents
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{
-        test_utils::{get_mock_server_with_content, website},
-        Uri,
-    };
-    use std::fs::File;
+    use crate::test_utils::{get_mock_server_with_content, website};
+    use std::fs::{self, File};
     use std::io::Write;
     use std::str::FromStr;
 
@@ -273,20 +921,16 @@ mod test {
         let mock_server = get_mock_server_with_content(http::StatusCode::OK, Some(TEST_URL)).await;
 
         let inputs = vec![
-            Input::String(TEST_STRING.to_string()),
-            Input::RemoteUrl(Url::from_str(&mock_server.uri())?),
-            Input::FsPath(file_path),
+            Input::from_string(TEST_STRING, FileType::Plaintext),
+            Input::from_url(Url::from_str(&mock_server.uri())?),
+            Input::from_path(file_path),
             Input::FsGlob {
                 pattern: dir.path().join("glob*").to_str().unwrap().to_string(),
                 ignore_case: true,
             },
         ];
 
-        let responses = collect_links(&inputs, None, false, 8).await?;
-        let links = responses
-            .into_iter()
-            .map(|r| r.uri)
-            .collect::<HashSet<Uri>>();
+        let links = collect_links(inputs, None, &CollectorOptions::default()).await?;
 
         let mut expected_links: HashSet<Uri> = HashSet::new();
         expected_links.insert(website(TEST_STRING));
@@ -299,4 +943,442 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_collect_links_gzip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file_path = dir.path().join("archived.md.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            File::create(&file_path)?,
+            flate2::Compression::default(),
+        );
+        write!(encoder, "[archive]({})", TEST_FILE)?;
+        encoder.finish()?;
+
+        let contents = Input::from_path(&file_path)
+            .get_contents(None, false)
+            .await?;
+        assert_eq!(contents[0].file_type, FileType::Markdown);
+
+        let links = collect_links(
+            vec![Input::from_path(file_path)],
+            None,
+            &CollectorOptions::default(),
+        )
+        .await?;
+        let expected_links: HashSet<Uri> = [website(TEST_FILE)].iter().cloned().collect();
+        assert_eq!(links, expected_links);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_links_sitemap_index() -> Result<()> {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let index = Url::parse(&format!("{}/sitemap.xml", mock_server.uri()))?;
+        let child = Url::parse(&format!("{}/sitemap-pages.xml", mock_server.uri()))?;
+        // The index lists itself, which must not lead to an endless recursion
+        let index_body = format!(
+            "<sitemapindex><sitemap><loc>{}</loc></sitemap><sitemap><loc>{}</loc></sitemap></sitemapindex>",
+            index, child
+        );
+        Mock::given(path("/sitemap.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(index_body))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/sitemap-pages.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                "<urlset><url><loc>{}</loc></url></urlset>",
+                TEST_URL
+            )))
+            .mount(&mock_server)
+            .await;
+
+        let links = collect_links(
+            vec![Input::from_url(index.clone())],
+            None,
+            &CollectorOptions::default(),
+        )
+        .await?;
+
+        let expected_links: HashSet<Uri> =
+            [Uri::Website(index), Uri::Website(child), website(TEST_URL)]
+                .iter()
+                .cloned()
+                .collect();
+        assert_eq!(links, expected_links);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sniff_file_type() -> Result<()> {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/docs/"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/html; charset=utf-8")
+                    .set_body_string("<p>Hello</p>"),
+            )
+            .mount(&mock_server)
+            .await;
+        let url = Url::parse(&format!("{}/docs/", mock_server.uri()))?;
+        let contents = Input::from_url(url).get_contents(None, false).await?;
+        assert_eq!(contents[0].file_type, FileType::Html);
+
+        let dir = tempfile::tempdir()?;
+        let file_path = dir.path().join("README");
+        fs::write(&file_path, "# Readme\n\nSee [the docs](docs/index.md).\n")?;
+        let contents = Input::from_path(file_path)
+            .get_contents(None, false)
+            .await?;
+        assert_eq!(contents[0].file_type, FileType::Markdown);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_links_concurrently() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for i in 0..20 {
+            fs::write(
+                dir.path().join(format!("{}.md", i)),
+                format!("[page](https://example.org/{})", i),
+            )?;
+        }
+        let opts = CollectorOptions {
+            max_concurrency: 3,
+            ..CollectorOptions::default()
+        };
+        let inputs = vec![Input::FsGlob {
+            pattern: dir.path().join("*.md").to_str().unwrap().to_string(),
+            ignore_case: false,
+        }];
+        let collected = collect(inputs, None, &opts).await?;
+        assert_eq!(collected.inputs_read, 20);
+        let links: HashSet<Uri> = collected.links.into_iter().map(|link| link.uri).collect();
+        let expected_links: HashSet<Uri> = (0..20)
+            .map(|i| website(&format!("https://example.org/{}", i)))
+            .collect();
+        assert_eq!(links, expected_links);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_links_fetch_concurrency() -> Result<()> {
+        use std::time::{Duration, Instant};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let delay = Duration::from_millis(200);
+        Mock::given(wiremock::matchers::method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(TEST_URL)
+                    .set_delay(delay),
+            )
+            .expect(6)
+            .mount(&mock_server)
+            .await;
+
+        let opts = CollectorOptions {
+            max_fetch_concurrency: 2,
+            ..CollectorOptions::default()
+        };
+        let inputs = (0..6).map(|i| {
+            Input::from_url(Url::parse(&format!("{}/{}.txt", mock_server.uri(), i)).unwrap())
+        });
+        let start = Instant::now();
+        let collected = collect(inputs, None, &opts).await?;
+        // Three rounds of two requests each
+        assert!(start.elapsed() >= delay * 3);
+        assert_eq!(collected.inputs_read, 6);
+        assert_eq!(
+            collected.links.into_iter().next().map(|link| link.uri),
+            Some(website(TEST_URL))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_links_filtered() -> Result<()> {
+        let client = crate::ClientBuilder::default()
+            .excludes(regex::RegexSet::new(&[r"tracking\."])?)
+            .build()?;
+        let opts = CollectorOptions {
+            filter: Some(client),
+            ..CollectorOptions::default()
+        };
+        let inputs = vec![Input::from_string(
+            "https://example.org https://tracking.example.org/pixel.gif",
+            FileType::Plaintext,
+        )];
+
+        let links = collect_links(inputs, None, &opts).await?;
+
+        let expected_links: HashSet<Uri> =
+            [website("https://example.org")].iter().cloned().collect();
+        assert_eq!(links, expected_links);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_input_base_urls() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let docs = dir.path().join("docs");
+        let blog = dir.path().join("blog");
+        std::fs::create_dir_all(docs.join("api"))?;
+        std::fs::create_dir_all(&blog)?;
+        writeln!(File::create(docs.join("index.md"))?, "[Intro](intro.html)")?;
+        writeln!(
+            File::create(docs.join("api/index.md"))?,
+            "[Types](types.html)"
+        )?;
+        writeln!(File::create(blog.join("index.md"))?, "[Post](post.html)")?;
+
+        let opts = CollectorOptions {
+            input_base_urls: vec![
+                (
+                    docs.to_str().unwrap().to_string(),
+                    Url::parse("https://docs.example.com/")?,
+                ),
+                (
+                    docs.join("api").to_str().unwrap().to_string(),
+                    Url::parse("https://api.example.com/")?,
+                ),
+            ],
+            ..CollectorOptions::default()
+        };
+        let inputs = vec![Input::FsGlob {
+            pattern: dir.path().join("**/*.md").to_str().unwrap().to_string(),
+            ignore_case: false,
+        }];
+        let links = collect_links(inputs, Some("https://example.com/".to_string()), &opts).await?;
+
+        let expected_links: HashSet<Uri> = [
+            website("https://docs.example.com/intro.html"),
+            website("https://api.example.com/types.html"),
+            website("https://example.com/post.html"),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(links, expected_links);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_input_errors() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let valid_path = dir.path().join("valid.md");
+        let binary_path = dir.path().join("binary.md");
+        writeln!(File::create(&valid_path)?, "{}", TEST_FILE)?;
+        File::create(&binary_path)?.write_all(&[0xff, 0xfe, 0xfd])?;
+
+        let inputs = vec![Input::FsGlob {
+            pattern: dir.path().join("*.md").to_str().unwrap().to_string(),
+            ignore_case: false,
+        }];
+        let opts = CollectorOptions {
+            strict_encoding: true,
+            ..CollectorOptions::default()
+        };
+        let collected = collect(inputs.clone(), None, &opts).await?;
+
+        assert_eq!(collected.links.len(), 1);
+        assert_eq!(collected.input_errors.len(), 1);
+        assert_eq!(collected.input_errors[0].input, Input::FsPath(binary_path));
+
+        // Without a way to report them, unreadable inputs are still fatal
+        assert!(collect_links(inputs, None, &opts).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_non_utf8_inputs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let latin1_path = dir.path().join("latin1.md");
+        File::create(&latin1_path)?.write_all(b"Caf\xe9: [menu](https://example.org/menu)")?;
+
+        let inputs = vec![Input::FsPath(latin1_path.clone())];
+        let collected = collect(inputs.clone(), None, &CollectorOptions::default()).await?;
+        assert_eq!(collected.links.len(), 1);
+        assert!(collected.input_errors.is_empty());
+        assert_eq!(
+            collected.input_warnings,
+            vec![InputError {
+                input: Input::FsPath(latin1_path),
+                reason: "Not valid UTF-8, decoded as windows-1252".to_string(),
+            }]
+        );
+
+        let opts = CollectorOptions {
+            strict_encoding: true,
+            ..CollectorOptions::default()
+        };
+        let collected = collect(inputs, None, &opts).await?;
+        assert!(collected.links.is_empty());
+        assert_eq!(collected.input_errors.len(), 1);
+        assert!(collected.input_warnings.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode() -> Result<()> {
+        assert_eq!(
+            decode(b"plain".to_vec(), true)?,
+            ("plain".to_string(), None)
+        );
+        assert_eq!(
+            decode(b"Caf\xe9".to_vec(), false)?,
+            ("Café".to_string(), Some("windows-1252"))
+        );
+        assert!(decode(b"Caf\xe9".to_vec(), true).is_err());
+
+        let html = b"<html><head><meta charset='iso-8859-2'></head>\xb1</html>".to_vec();
+        assert_eq!(
+            decode(html, false)?,
+            (
+                "<html><head><meta charset='iso-8859-2'></head>ą</html>".to_string(),
+                Some("ISO-8859-2")
+            )
+        );
+
+        let utf16 = vec![0xff, 0xfe, b'h', 0, b'i', 0];
+        assert_eq!(decode(utf16, false)?, ("hi".to_string(), Some("UTF-16LE")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_links_crawl() -> Result<()> {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let page = |body: &str| {
+            ResponseTemplate::new(200).set_body_raw(body.as_bytes().to_vec(), "text/html")
+        };
+        // The docs link back to the start page, which must not be read again
+        Mock::given(path("/"))
+            .respond_with(page(
+                r#"<a href="/docs#intro">Docs</a><img src="/logo.png">"#,
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/docs"))
+            .respond_with(page(&format!(
+                r#"<a href="/">Home</a><a href="deep">Deep</a><a href="{}">Elsewhere</a>"#,
+                TEST_URL
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/deep"))
+            .respond_with(page(r#"<a href="/deeper">Deeper</a>"#))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/logo.png"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let url = |path: &str| website(&format!("{}{}", mock_server.uri(), path));
+        let opts = CollectorOptions {
+            crawl_depth: 1,
+            ..CollectorOptions::default()
+        };
+        let root = Url::parse(&format!("{}/", mock_server.uri()))?;
+        let collected = collect(vec![Input::from_url(root)], None, &opts).await?;
+        let links: HashSet<Uri> = collected
+            .links
+            .into_iter()
+            .map(|request| request.uri)
+            .collect();
+
+        let expected_links: HashSet<Uri> = [
+            url("/docs#intro"),
+            url("/logo.png"),
+            url("/"),
+            url("/deep"),
+            website(TEST_URL),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(links, expected_links);
+        assert_eq!(collected.inputs_read, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_links_crawl_excludes() -> Result<()> {
+        use wiremock::matchers::{header, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let page = |body: &str| {
+            ResponseTemplate::new(200).set_body_raw(body.as_bytes().to_vec(), "text/html")
+        };
+        Mock::given(path("/"))
+            .respond_with(page(
+                r#"<a href="/docs">Docs</a><a href="/private">Private</a>"#,
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/docs"))
+            .and(header("user-agent", "crawler"))
+            .respond_with(page(r#"<a href="/deep">Deep</a>"#))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/private"))
+            .respond_with(page(r#"<a href="/secret">Secret</a>"#))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let mut excludes = Excludes::builder();
+        excludes.regex("/private$");
+        let opts = CollectorOptions {
+            crawl_depth: 1,
+            excludes: excludes.build()?,
+            crawl_client: Some(
+                ClientBuilder::default()
+                    .user_agent("crawler".to_string())
+                    .build()?,
+            ),
+            ..CollectorOptions::default()
+        };
+        let root = Url::parse(&format!("{}/", mock_server.uri()))?;
+        let collected = collect(vec![Input::from_url(root)], None, &opts).await?;
+        let url = |path: &str| website(&format!("{}{}", mock_server.uri(), path));
+        let links: HashSet<Uri> = collected
+            .links
+            .into_iter()
+            .map(|request| request.uri)
+            .collect();
+
+        // Excluded pages are still links, they just don't get crawled
+        let expected_links: HashSet<Uri> = [url("/docs"), url("/private"), url("/deep")]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(links, expected_links);
+
+        Ok(())
+    }
 }
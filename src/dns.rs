@@ -0,0 +1,62 @@
+use anyhow::Result;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::{
+    net::{SocketAddr, ToSocketAddrs},
+    sync::Arc,
+};
+use trust_dns_resolver::{
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+    TokioAsyncResolver,
+};
+
+/// Resolves hosts with the system resolver and asks the fallback name
+/// servers if that fails. Some (e.g. corporate) resolvers intermittently
+/// answer `NXDOMAIN` for valid hosts, which would make links look dead.
+/// Only failed lookups are retried, connection or TLS errors are not
+/// affected.
+pub(crate) struct FallbackResolver {
+    fallback: Arc<TokioAsyncResolver>,
+}
+
+impl FallbackResolver {
+    pub(crate) fn new(name_servers: &[SocketAddr]) -> Result<Self> {
+        let mut group = NameServerConfigGroup::new();
+        for name_server in name_servers {
+            group.merge(NameServerConfigGroup::from_ips_clear(
+                &[name_server.ip()],
+                name_server.port(),
+                true,
+            ));
+        }
+        let config = ResolverConfig::from_parts(None, vec![], group);
+        let fallback = TokioAsyncResolver::tokio(config, ResolverOpts::default())?;
+        Ok(Self {
+            fallback: Arc::new(fallback),
+        })
+    }
+}
+
+impl Resolve for FallbackResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let fallback = self.fallback.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let system_host = host.clone();
+            // The system resolver blocks, like the default resolver of reqwest
+            let system = tokio::task::spawn_blocking(move || {
+                (system_host.as_str(), 0)
+                    .to_socket_addrs()
+                    .map(|addrs| addrs.collect::<Vec<_>>())
+            })
+            .await;
+            if let Ok(Ok(addrs)) = system {
+                if !addrs.is_empty() {
+                    return Ok(Box::new(addrs.into_iter()) as Addrs);
+                }
+            }
+            let lookup = fallback.lookup_ip(host.as_str()).await?;
+            let addrs: Vec<SocketAddr> = lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
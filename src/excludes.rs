@@ -1,14 +1,25 @@
 use std::net::IpAddr;
 
-use regex::RegexSet;
+use anyhow::{Context, Result};
+use derive_builder::Builder;
+use regex::{RegexSet, RegexSetBuilder};
 
 use crate::Uri;
 
 /// Exclude configuration for the link checker.
 /// You can ignore links based on regex patterns or pre-defined IP ranges.
+/// Create one with `Excludes::builder()`.
 #[derive(Clone, Debug)]
 pub struct Excludes {
     pub regex: Option<RegexSet>,
+    /// Hostname patterns, matched against the host of a URL only.
+    /// See `Excludes::compile_host_globs`
+    pub hosts: Option<RegexSet>,
+    /// File extensions of the URL path, lowercase and without the leading dot.
+    /// Example: `tar.gz`
+    pub extensions: Vec<String>,
+    /// URL schemes, lowercase. Example: `ftp`
+    pub schemes: Vec<String>,
     /// Example: 192.168.0.1
     pub private_ips: bool,
     /// Example: 169.254.0.0
@@ -24,6 +35,9 @@ impl Default for Excludes {
     fn default() -> Self {
         Self {
             regex: None,
+            hosts: None,
+            extensions: vec![],
+            schemes: vec![],
             private_ips: false,
             link_local_ips: false,
             loopback_ips: false,
@@ -32,7 +46,118 @@ impl Default for Excludes {
     }
 }
 
+/// Builds `Excludes` independently of a `Client`, e.g.
+///
+/// ```
+/// use kimchi::Excludes;
+///
+/// let excludes = Excludes::builder()
+///     .regex(r"^https://example\.com/private")
+///     .exclude_private_ips(true)
+///     .scheme("ftp")
+///     .build()?;
+/// assert!(excludes.regex("https://example.com/private/keys"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Builder, Debug)]
+#[builder(build_fn(skip))]
+#[builder(setter(into))]
+#[builder(name = "ExcludesBuilder")]
+pub struct ExcludesBuilderInternal {
+    /// Exclude links matching one of these regular expressions
+    #[builder(setter(custom))]
+    regex: Vec<String>,
+    /// Exclude links whose host matches one of these glob patterns,
+    /// see `Excludes::compile_host_globs`
+    #[builder(setter(custom))]
+    hosts: Vec<String>,
+    /// Exclude links to files with these extensions
+    #[builder(setter(custom))]
+    extensions: Vec<String>,
+    /// Exclude links with these schemes
+    #[builder(setter(custom))]
+    schemes: Vec<String>,
+    /// Exclude all private network addresses
+    exclude_all_private: bool,
+    /// Exclude private IP addresses
+    exclude_private_ips: bool,
+    /// Exclude link-local IPs
+    exclude_link_local_ips: bool,
+    /// Exclude loopback IP addresses (e.g. 127.0.0.1)
+    exclude_loopback_ips: bool,
+    /// Don't check mail addresses
+    exclude_mail: bool,
+}
+
+impl ExcludesBuilder {
+    /// Exclude links matching the regular expression.
+    /// Can be called multiple times.
+    pub fn regex<S: Into<String>>(&mut self, pattern: S) -> &mut Self {
+        self.regex.get_or_insert_with(Vec::new).push(pattern.into());
+        self
+    }
+
+    /// Exclude links whose host matches the glob pattern (e.g. `*.example.com`).
+    /// Can be called multiple times.
+    pub fn host<S: Into<String>>(&mut self, pattern: S) -> &mut Self {
+        self.hosts.get_or_insert_with(Vec::new).push(pattern.into());
+        self
+    }
+
+    /// Exclude links to files with the extension (e.g. `zip` or `.tar.gz`).
+    /// Can be called multiple times.
+    pub fn extension<S: Into<String>>(&mut self, extension: S) -> &mut Self {
+        self.extensions
+            .get_or_insert_with(Vec::new)
+            .push(extension.into());
+        self
+    }
+
+    /// Exclude links with the scheme (e.g. `ftp`). Can be called multiple times.
+    pub fn scheme<S: Into<String>>(&mut self, scheme: S) -> &mut Self {
+        self.schemes
+            .get_or_insert_with(Vec::new)
+            .push(scheme.into());
+        self
+    }
+
+    /// Compile the patterns. Fails if a regular expression or host pattern is invalid.
+    pub fn build(&self) -> Result<Excludes> {
+        let regex = self.regex.clone().unwrap_or_default();
+        let regex = match regex.is_empty() {
+            true => None,
+            false => Some(RegexSet::new(&regex).context("Invalid exclude pattern")?),
+        };
+        let hosts = self.hosts.clone().unwrap_or_default();
+        let normalize = |values: &Option<Vec<String>>, trim: &[char]| -> Vec<String> {
+            values
+                .iter()
+                .flatten()
+                .map(|value| value.trim().trim_matches(trim).to_lowercase())
+                .filter(|value| !value.is_empty())
+                .collect()
+        };
+        let all_private = self.exclude_all_private.unwrap_or_default();
+        let enable_exclude = |opt: Option<bool>| opt.unwrap_or_default() || all_private;
+
+        Ok(Excludes {
+            regex,
+            hosts: Excludes::compile_host_globs(&hosts).context("Invalid host pattern")?,
+            extensions: normalize(&self.extensions, &['.']),
+            schemes: normalize(&self.schemes, &[':', '/']),
+            private_ips: enable_exclude(self.exclude_private_ips),
+            link_local_ips: enable_exclude(self.exclude_link_local_ips),
+            loopback_ips: enable_exclude(self.exclude_loopback_ips),
+            mail: enable_exclude(self.exclude_mail),
+        })
+    }
+}
+
 impl Excludes {
+    pub fn builder() -> ExcludesBuilder {
+        ExcludesBuilder::default()
+    }
+
     pub fn regex(&self, input: &str) -> bool {
         if let Some(excludes) = &self.regex {
             if excludes.is_match(input) {
@@ -42,6 +167,65 @@ impl Excludes {
         false
     }
 
+    /// Compile hostname glob patterns (e.g. `*.example.com` or `example.*`)
+    /// into a single matcher. A `*` matches any sequence of characters,
+    /// including dots, so `*.example.com` also matches `a.b.example.com`,
+    /// but not `example.com` itself. Matching is case-insensitive.
+    pub fn compile_host_globs<S: AsRef<str>>(
+        patterns: &[S],
+    ) -> Result<Option<RegexSet>, regex::Error> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+        let regexes = patterns.iter().map(|pattern| {
+            let pattern = pattern.as_ref().trim().trim_end_matches('.');
+            let parts: Vec<String> = pattern.split('*').map(regex::escape).collect();
+            format!("^{}$", parts.join(".*"))
+        });
+        let set = RegexSetBuilder::new(regexes)
+            .case_insensitive(true)
+            .build()?;
+        Ok(Some(set))
+    }
+
+    pub fn host(&self, uri: &Uri) -> bool {
+        match (&self.hosts, uri) {
+            (Some(hosts), Uri::Website(url)) => url
+                .host_str()
+                .map(|host| hosts.is_match(host.trim_end_matches('.')))
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// Check the extension of the last path segment, ignoring the query
+    /// and fragment. Extensions may span several dots, e.g. `tar.gz`.
+    pub fn extension(&self, uri: &Uri) -> bool {
+        let url = match uri {
+            Uri::Website(url) if !self.extensions.is_empty() => url,
+            _ => return false,
+        };
+        let file_name = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or_default()
+            .to_lowercase();
+        self.extensions.iter().any(|extension| {
+            file_name.len() > extension.len() + 1
+                && file_name.ends_with(extension.as_str())
+                && file_name[..file_name.len() - extension.len()].ends_with('.')
+        })
+    }
+
+    pub fn scheme(&self, uri: &Uri) -> bool {
+        match uri {
+            Uri::Website(url) => self.schemes.iter().any(|scheme| scheme == url.scheme()),
+            Uri::Mail(_) => self.schemes.iter().any(|scheme| scheme == "mailto"),
+            // Git remotes are reached over SSH, see `Uri::scheme`
+            Uri::Git(_) => self.schemes.iter().any(|scheme| scheme == "ssh"),
+        }
+    }
+
     pub fn ip(&self, uri: &Uri) -> bool {
         if let Some(ipaddr) = uri.host_ip() {
             if self.loopback_ips && ipaddr.is_loopback() {
@@ -71,9 +255,129 @@ impl Excludes {
     }
 
     pub fn is_empty(&self) -> bool {
-        match &self.regex {
+        let is_empty = |set: &Option<RegexSet>| match set {
             None => true,
             Some(regex_set) => regex_set.is_empty(),
+        };
+        is_empty(&self.regex)
+            && is_empty(&self.hosts)
+            && self.extensions.is_empty()
+            && self.schemes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::website;
+    use std::convert::TryFrom;
+
+    fn excluded(excludes: &Excludes, uri: &Uri) -> bool {
+        (matches!(uri, Uri::Mail(_)) && excludes.is_mail_excluded())
+            || excludes.regex(uri.as_str())
+            || excludes.host(uri)
+            || excludes.extension(uri)
+            || excludes.scheme(uri)
+            || excludes.ip(uri)
+    }
+
+    #[test]
+    fn test_excludes_builder_categories() {
+        let private = website("http://192.168.0.1");
+        let link_local = website("http://169.254.0.1");
+        let loopback = website("http://127.0.0.1");
+        let mail = Uri::try_from("mailto:octocat@github.com").unwrap();
+        let regex = website("https://example.com/private/keys");
+        let host = website("https://docs.example.org");
+        let extension = website("https://example.com/release.tar.gz");
+        let scheme = website("ftp://example.com");
+        let all = [
+            &private,
+            &link_local,
+            &loopback,
+            &mail,
+            &regex,
+            &host,
+            &extension,
+            &scheme,
+        ];
+
+        let default = Excludes::builder().build().unwrap();
+        assert!(default.is_empty());
+        assert!(all.iter().all(|uri| !excluded(&default, uri)));
+
+        let only = |excludes: Excludes, expected: &Uri| {
+            for uri in &all {
+                assert_eq!(excluded(&excludes, uri), *uri == expected, "{}", uri);
+            }
+        };
+        only(
+            Excludes::builder()
+                .exclude_private_ips(true)
+                .build()
+                .unwrap(),
+            &private,
+        );
+        only(
+            Excludes::builder()
+                .exclude_link_local_ips(true)
+                .build()
+                .unwrap(),
+            &link_local,
+        );
+        only(
+            Excludes::builder()
+                .exclude_loopback_ips(true)
+                .build()
+                .unwrap(),
+            &loopback,
+        );
+        only(
+            Excludes::builder().exclude_mail(true).build().unwrap(),
+            &mail,
+        );
+        only(
+            Excludes::builder()
+                .regex(r"^https://example\.com/private/")
+                .build()
+                .unwrap(),
+            &regex,
+        );
+        only(
+            Excludes::builder().host("*.example.org").build().unwrap(),
+            &host,
+        );
+        only(
+            Excludes::builder().extension(".TAR.GZ").build().unwrap(),
+            &extension,
+        );
+        only(Excludes::builder().scheme("FTP:").build().unwrap(), &scheme);
+    }
+
+    #[test]
+    fn test_excludes_builder_combined() {
+        let excludes = Excludes::builder()
+            .regex("private")
+            .regex("secret")
+            .scheme("ftp")
+            .exclude_all_private(true)
+            .build()
+            .unwrap();
+        assert!(!excludes.is_empty());
+        for url in &[
+            "https://example.com/private",
+            "https://example.com/secret",
+            "ftp://example.com",
+            "http://192.168.0.1",
+            "http://169.254.0.1",
+            "http://127.0.0.1",
+        ] {
+            assert!(excluded(&excludes, &website(url)), "{}", url);
         }
+        assert!(excludes.is_mail_excluded());
+        assert!(!excluded(&excludes, &website("https://example.com/public")));
+
+        assert!(Excludes::builder().regex("(").build().is_err());
+        assert!(Excludes::builder().host("").build().is_ok());
     }
 }
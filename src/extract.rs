@@ -1,19 +1,29 @@
+use crate::anchors::{contains_anchor, html_anchors, markdown_anchors, AnchorStyle};
 use crate::uri::Uri;
-use crate::{collector::InputContent, Request};
+use crate::{
+    collector::{Input, InputContent},
+    LinkKind, Request,
+};
 use html5ever::parse_document;
 use html5ever::tendril::{StrTendril, TendrilSink};
+use lazy_static::lazy_static;
 use linkify::LinkFinder;
 use markup5ever_rcdom::{Handle, NodeData, RcDom};
 use pulldown_cmark::{Event as MDEvent, Parser, Tag};
+use regex::{Regex, RegexSet};
 use std::path::Path;
-use std::{collections::HashSet, convert::TryFrom};
+use std::{collections::HashSet, convert::TryFrom, ops::Range};
 use url::Url;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum FileType {
     Html,
     Markdown,
     Plaintext,
+    /// An XML sitemap (`<urlset>`) or sitemap index (`<sitemapindex>`)
+    Sitemap,
+    /// A Jupyter notebook, of which only the Markdown cells are checked
+    Notebook,
 }
 
 impl Default for FileType {
@@ -24,12 +34,19 @@ impl Default for FileType {
 
 impl<P: AsRef<Path>> From<P> for FileType {
     /// Detect if the given path points to a Markdown, HTML, or plaintext file.
+    /// Gzip-compressed files are detected by their inner extension, e.g. `foo.md.gz`.
     fn from(p: P) -> FileType {
         let path = p.as_ref();
         match path.extension() {
             Some(ext) => match ext {
+                _ if ext == "gz" => path
+                    .file_stem()
+                    .map(|stem| FileType::from(Path::new(stem)))
+                    .unwrap_or_default(),
                 _ if (ext == "md" || ext == "markdown") => FileType::Markdown,
                 _ if (ext == "htm" || ext == "html") => FileType::Html,
+                _ if ext == "xml" && is_sitemap_name(path) => FileType::Sitemap,
+                _ if ext == "ipynb" => FileType::Notebook,
                 _ => FileType::Plaintext,
             },
             None => FileType::Plaintext,
@@ -37,57 +54,283 @@ impl<P: AsRef<Path>> From<P> for FileType {
     }
 }
 
+lazy_static! {
+    /// Constructs which are common in Markdown, but rare in plaintext:
+    /// headings, code fences, setext underlines and inline links
+    static ref MARKDOWN_SIGNATURE: Regex =
+        Regex::new(r"(?m)^(#{1,6} \S|```|~~~|={3,}\s*$)|\[[^\]\n]+\]\([^)\s]+\)").unwrap();
+}
+
+impl FileType {
+    /// Detect the type of a document without a telling extension, e.g. an
+    /// extensionless `README` or a URL like `https://example.com/docs/`.
+    /// The `Content-Type` of an HTTP response is used if it's specific,
+    /// otherwise the content gets sniffed for HTML, sitemap and Markdown
+    /// signatures, falling back to plaintext.
+    pub fn from_content(content: &str, content_type: Option<&str>) -> FileType {
+        let essence = content_type
+            .and_then(|content_type| content_type.split(';').next())
+            .map(|essence| essence.trim().to_lowercase());
+        match essence.as_deref() {
+            Some("text/html") | Some("application/xhtml+xml") => return FileType::Html,
+            Some("text/markdown") | Some("text/x-markdown") => return FileType::Markdown,
+            Some("application/x-ipynb+json") => return FileType::Notebook,
+            _ => {}
+        }
+
+        let head: String = content.trim_start().chars().take(1024).collect();
+        let head = head.to_lowercase();
+        if head.starts_with("<!doctype html")
+            || head.starts_with("<html")
+            || (head.starts_with('<') && (head.contains("<head") || head.contains("<body")))
+        {
+            FileType::Html
+        } else if is_sitemap(&head) {
+            FileType::Sitemap
+        } else if MARKDOWN_SIGNATURE.is_match(content) {
+            FileType::Markdown
+        } else {
+            FileType::Plaintext
+        }
+    }
+}
+
+/// Sitemaps are conventionally named e.g. `sitemap.xml` or `sitemap-posts.xml`
+fn is_sitemap_name(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map_or(false, |stem| stem.to_lowercase().starts_with("sitemap"))
+}
+
+/// Detect a sitemap by its root element, for sitemaps with unusual names
+pub(crate) fn is_sitemap(input: &str) -> bool {
+    input.contains("<urlset") || input.contains("<sitemapindex")
+}
+
 // Use LinkFinder here to offload the actual link searching in plaintext.
 fn find_links(input: &str) -> Vec<linkify::Link> {
     let finder = LinkFinder::new();
     finder.links(input).collect()
 }
 
+/// `data-*` attributes which conventionally hold a URL, e.g. for lazy loading.
+/// Other `data-*` attributes are only searched for URLs like plaintext,
+/// as they can hold anything.
+pub const DATA_LINK_ATTRIBUTES: &[&str] = &["data-src", "data-href", "data-url"];
+
+/// Comment directive which excludes the link right after it from checking,
+/// e.g. `<!-- kimchi-ignore -->`
+pub const INLINE_IGNORE_DIRECTIVE: &str = "kimchi-ignore";
+
+/// A link as it was found in a document, before it gets resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RawLink {
+    url: String,
+    kind: LinkKind,
+    /// Preceded by an inline ignore directive
+    ignored: bool,
+    /// Byte range of the link in the document, see `locate_links`
+    span: Option<Range<usize>>,
+    /// Text of a Markdown link or image, or of an HTML `<a>` element
+    text: Option<String>,
+}
+
+impl From<(String, LinkKind)> for RawLink {
+    fn from((url, kind): (String, LinkKind)) -> Self {
+        RawLink {
+            url,
+            kind,
+            ignored: false,
+            span: None,
+            text: None,
+        }
+    }
+}
+
+/// Add the links found in one place of a document (e.g. an element),
+/// marking them as ignored if an ignore directive came right before
+fn push_links<I: IntoIterator<Item = RawLink>>(
+    links: &mut Vec<RawLink>,
+    found: I,
+    ignore_next: &mut bool,
+) {
+    let start = links.len();
+    links.extend(found);
+    if *ignore_next && links.len() > start {
+        for link in &mut links[start..] {
+            link.ignored = true;
+        }
+        *ignore_next = false;
+    }
+}
+
+fn is_ignore_directive(comment: &str) -> bool {
+    comment.trim().eq_ignore_ascii_case(INLINE_IGNORE_DIRECTIVE)
+}
+
 /// Extract unparsed URL strings from a markdown string.
-fn extract_links_from_markdown(input: &str) -> Vec<String> {
-    let parser = Parser::new(input);
-    parser
-        .flat_map(|event| match event {
-            MDEvent::Start(tag) => match tag {
-                Tag::Link(_, url, _) | Tag::Image(_, url, _) => vec![url.to_string()],
-                _ => vec![],
+fn extract_links_from_markdown(input: &str, link_attributes: &[String]) -> Vec<RawLink> {
+    let mut links = vec![];
+    // Contiguous HTML events get parsed at once, as the parser splits up
+    // HTML (e.g. a tag with attributes on multiple lines) at line breaks
+    let mut html = String::new();
+    // An ignore directive in HTML applies to the next link, which may
+    // well be a markdown link
+    let mut ignore_next = false;
+    let mut in_ignored_link = false;
+    // Links and images whose text is being read, by their index in `links`
+    let mut open: Vec<(usize, String)> = vec![];
+    for (event, range) in Parser::new(input).into_offset_iter() {
+        match event {
+            MDEvent::Html(fragment) => {
+                html.push_str(&fragment);
+                continue;
+            }
+            MDEvent::SoftBreak | MDEvent::HardBreak if !html.is_empty() => {
+                html.push('\n');
+                continue;
+            }
+            _ => {}
+        }
+        if !html.is_empty() {
+            walk_html_fragment(&mut links, &html, link_attributes, &mut ignore_next);
+            html.clear();
+        }
+        match &event {
+            MDEvent::Text(txt) | MDEvent::Code(txt) => {
+                for (_, text) in &mut open {
+                    text.push_str(txt);
+                }
+            }
+            MDEvent::End(Tag::Link(..)) | MDEvent::End(Tag::Image(..)) => {
+                if let Some((index, text)) = open.pop() {
+                    links[index].text = Some(normalize_text(&text));
+                }
+            }
+            _ => {}
+        }
+        let opens = matches!(event, MDEvent::Start(_));
+        let (found, is_link) = match &event {
+            MDEvent::Start(Tag::Link(_, url, _)) => (
+                vec![markdown_destination(input, &range, url, LinkKind::Link)],
+                true,
+            ),
+            MDEvent::Start(Tag::Image(_, url, _)) => (
+                vec![markdown_destination(input, &range, url, LinkKind::Image)],
+                false,
+            ),
+            MDEvent::End(Tag::Link(..)) => {
+                in_ignored_link = false;
+                continue;
+            }
+            MDEvent::Text(txt) => (text_links(input, &range, txt), false),
+            _ => continue,
+        };
+        // The text of an ignored link gets ignored along with it,
+        // e.g. the URL of an autolink like `<https://example.org>`
+        if in_ignored_link {
+            links.extend(found.into_iter().map(|link| RawLink {
+                ignored: true,
+                ..link
+            }));
+        } else {
+            if is_link {
+                in_ignored_link = ignore_next;
+            }
+            push_links(&mut links, found, &mut ignore_next);
+        }
+        // Only links and images get this far with a start tag
+        if opens {
+            open.push((links.len() - 1, String::new()));
+        }
+    }
+    if !html.is_empty() {
+        walk_html_fragment(&mut links, &html, link_attributes, &mut ignore_next);
+    }
+    links
+}
+
+/// The destination of a Markdown link or image, located in the source of
+/// the element. The destination comes after the text, so it's searched for
+/// from the end. Destinations of reference links are defined elsewhere and
+/// are left to `locate_links`.
+fn markdown_destination(input: &str, range: &Range<usize>, url: &str, kind: LinkKind) -> RawLink {
+    let mut link = RawLink::from((url.to_string(), kind));
+    if !url.is_empty() {
+        link.span = input[range.clone()]
+            .rfind(url)
+            .map(|offset| range.start + offset..range.start + offset + url.len());
+    }
+    link
+}
+
+/// The links in `text`, which was read from `range` of `input`. They are
+/// only located if the text is verbatim in the input, e.g. if the Markdown
+/// parser didn't unescape anything.
+fn text_links(input: &str, range: &Range<usize>, text: &str) -> Vec<RawLink> {
+    let verbatim = input.get(range.clone()) == Some(text);
+    find_links(text)
+        .iter()
+        .map(|link| RawLink {
+            span: match verbatim {
+                true => Some(range.start + link.start()..range.start + link.end()),
+                false => None,
             },
-            MDEvent::Text(txt) => extract_links_from_plaintext(&txt.to_string()),
-            MDEvent::Html(html) => extract_links_from_html(&html.to_string()),
-            _ => vec![],
+            ..RawLink::from((link.as_str().to_string(), LinkKind::Link))
         })
         .collect()
 }
 
 /// Extract unparsed URL strings from a HTML string.
-fn extract_links_from_html(input: &str) -> Vec<String> {
+/// `link_attributes` are additional attributes which hold a URL.
+fn extract_links_from_html(input: &str, link_attributes: &[String]) -> Vec<RawLink> {
+    let mut urls = Vec::new();
+    walk_html_fragment(&mut urls, input, link_attributes, &mut false);
+    urls
+}
+
+/// Parse a piece of HTML and add its links to `urls`.
+/// `ignore_next` carries a pending ignore directive across fragments.
+fn walk_html_fragment(
+    urls: &mut Vec<RawLink>,
+    input: &str,
+    link_attributes: &[String],
+    ignore_next: &mut bool,
+) {
     let tendril = StrTendril::from(input);
     let rc_dom = parse_document(RcDom::default(), Default::default()).one(tendril);
 
-    let mut urls = Vec::new();
-
     // we pass mutable urls reference to avoid extra allocations in each
     // recursive descent
-    walk_html_links(&mut urls, &rc_dom.document);
-
-    urls
+    walk_html_links(urls, &rc_dom.document, link_attributes, ignore_next);
 }
 
 /// Recursively walk links in a HTML document, aggregating URL strings in `urls`.
-fn walk_html_links(mut urls: &mut Vec<String>, node: &Handle) {
+fn walk_html_links(
+    mut urls: &mut Vec<RawLink>,
+    node: &Handle,
+    link_attributes: &[String],
+    ignore_next: &mut bool,
+) {
+    let start = urls.len();
+    let mut ignore_children = false;
+    // The links of an `<a>` element get its text
+    let mut anchor_links = None;
     match node.data {
         NodeData::Text { ref contents } => {
             // escape_default turns tab characters into "\t", newlines into "\n", etc.
             let esc_contents = contents.borrow().escape_default().to_string();
-            for link in extract_links_from_plaintext(&esc_contents) {
-                urls.push(link);
-            }
+            let found = extract_links_from_plaintext(&esc_contents);
+            push_links(urls, found.into_iter().map(RawLink::from), ignore_next);
+        }
+
+        NodeData::Comment { ref contents } if is_ignore_directive(contents) => {
+            *ignore_next = true;
         }
 
         NodeData::Comment { ref contents } => {
-            for link in extract_links_from_plaintext(&contents.escape_default().to_string()) {
-                urls.push(link);
-            }
+            let found = extract_links_from_plaintext(&contents.escape_default().to_string());
+            push_links(urls, found.into_iter().map(RawLink::from), ignore_next);
         }
 
         NodeData::Element {
@@ -95,17 +338,54 @@ fn walk_html_links(mut urls: &mut Vec<String>, node: &Handle) {
             ref attrs,
             ..
         } => {
+            let mut found = vec![];
+            let social_meta = social_meta_content(name.local.as_ref(), &attrs.borrow());
+            let rel = attrs
+                .borrow()
+                .iter()
+                .find(|attr| attr.name.local.as_ref() == "rel")
+                .map(|attr| attr.value.to_string())
+                .unwrap_or_default();
+            let has_event_handler = attrs
+                .borrow()
+                .iter()
+                .any(|attr| attr.name.local.as_ref().starts_with("on"));
             for attr in attrs.borrow().iter() {
+                let attr_name = attr.name.local.as_ref();
+                let elem_name = name.local.as_ref();
                 let attr_value = attr.value.escape_default().to_string();
 
-                if elem_attr_is_link(attr.name.local.as_ref(), name.local.as_ref()) {
-                    urls.push(attr_value);
-                } else {
-                    for link in extract_links_from_plaintext(&attr_value) {
-                        urls.push(link);
+                if attr_name == "content" && social_meta.is_some() {
+                    continue;
+                } else if attr_name == "href"
+                    && has_event_handler
+                    && is_script_placeholder(&attr_value)
+                {
+                    // The element navigates through its event handler, whose
+                    // links (e.g. `location.href = '...'`) are found below
+                    continue;
+                } else if attr_name == "srcset" || attr_name == "data-srcset" {
+                    // A srcset holds a list of image candidates, e.g.
+                    // `small.png 1x, large.png 2x`
+                    for candidate in parse_srcset(&attr_value) {
+                        found.push((candidate, LinkKind::Image));
                     }
+                } else if elem_attr_is_link(attr_name, elem_name)
+                    || link_attributes
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(attr_name))
+                {
+                    found.push((attr_value, elem_attr_kind(attr_name, elem_name, &rel)));
+                } else {
+                    found.extend(extract_links_from_plaintext(&attr_value));
                 }
             }
+            found.extend(social_meta.map(|content| (content, LinkKind::SocialMeta)));
+            push_links(urls, found.into_iter().map(RawLink::from), ignore_next);
+            ignore_children = urls[start..].iter().any(|link| link.ignored);
+            if name.local.as_ref() == "a" {
+                anchor_links = Some(start..urls.len());
+            }
         }
 
         _ => {}
@@ -114,10 +394,85 @@ fn walk_html_links(mut urls: &mut Vec<String>, node: &Handle) {
     // recursively traverse the document's nodes -- this doesn't need any extra
     // exit conditions because the document is a tree
     for child in node.children.borrow().iter() {
-        walk_html_links(&mut urls, child);
+        walk_html_links(&mut urls, child, link_attributes, ignore_next);
+    }
+
+    // The content of an ignored element gets ignored along with it,
+    // e.g. a URL which is also the text of an ignored link
+    if ignore_children {
+        for link in &mut urls[start..] {
+            link.ignored = true;
+        }
+    }
+    if let Some(anchor_links) = anchor_links {
+        let text = normalize_text(&text_content(node));
+        for link in &mut urls[anchor_links] {
+            link.text = Some(text.clone());
+        }
     }
 }
 
+/// The text of an HTML element and its descendants
+fn text_content(node: &Handle) -> String {
+    match node.data {
+        NodeData::Text { ref contents } => contents.borrow().to_string(),
+        _ => node.children.borrow().iter().map(text_content).collect(),
+    }
+}
+
+/// Collapse whitespace, as e.g. line breaks in a link text don't matter
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `<meta>` properties (Open Graph) and names (Twitter cards) holding the
+/// URL of a social preview in their `content`
+const SOCIAL_META_PROPERTIES: &[&str] = &[
+    "og:url",
+    "og:image",
+    "og:image:url",
+    "og:image:secure_url",
+    "og:video",
+    "og:video:url",
+    "og:video:secure_url",
+    "og:audio",
+    "og:audio:url",
+    "og:audio:secure_url",
+    "twitter:image",
+    "twitter:image:src",
+    "twitter:player",
+];
+
+/// The `content` of a `<meta>` element describing a social preview
+fn social_meta_content(elem_name: &str, attrs: &[html5ever::Attribute]) -> Option<String> {
+    if elem_name != "meta" {
+        return None;
+    }
+    let attr = |name: &str| {
+        attrs
+            .iter()
+            .find(|attr| attr.name.local.as_ref() == name)
+            .map(|attr| attr.value.trim().to_lowercase())
+    };
+    let property = attr("property").or_else(|| attr("name"))?;
+    if !SOCIAL_META_PROPERTIES.contains(&property.as_str()) {
+        return None;
+    }
+    attrs
+        .iter()
+        .find(|attr| attr.name.local.as_ref() == "content")
+        .map(|attr| attr.value.trim().to_string())
+        .filter(|content| !content.is_empty())
+}
+
+/// Whether the `href` of an element with an event handler (e.g. `onclick`)
+/// only stands in for JavaScript navigation, like `#` or a hashbang route
+/// (`#!/settings`). Links to actual anchors (e.g. `#usage`) still count.
+fn is_script_placeholder(href: &str) -> bool {
+    let href = href.trim();
+    href.is_empty() || href == "#" || href.starts_with("#!")
+}
+
 /// Determine if element's attribute contains a link / URL.
 fn elem_attr_is_link(attr_name: &str, elem_name: &str) -> bool {
     // See a comprehensive list of attributes that might contain URLs/URIs
@@ -130,52 +485,468 @@ fn elem_attr_is_link(attr_name: &str, elem_name: &str) -> bool {
             | ("cite", _)
             | ("data", "object")
             | ("onhashchange", "body")
-    )
+    ) || DATA_LINK_ATTRIBUTES.contains(&attr_name)
+}
+
+/// Determine what a link found in an element's attribute is expected to point to.
+/// `rel` is the `rel` attribute of the element, which tells resources
+/// loaded by a `<link>` apart from references like `rel=canonical`.
+fn elem_attr_kind(attr_name: &str, elem_name: &str, rel: &str) -> LinkKind {
+    match (attr_name, elem_name) {
+        ("src", "img") | ("data-src", "img") | ("href", "image") => LinkKind::Image,
+        ("src", "script")
+        | ("src", "audio")
+        | ("src", "video")
+        | ("src", "source")
+        | ("src", "track")
+        | ("src", "embed")
+        | ("data", "object") => LinkKind::Asset,
+        ("href", "link") if is_asset_rel(rel) => LinkKind::Asset,
+        _ => LinkKind::Link,
+    }
+}
+
+fn is_asset_rel(rel: &str) -> bool {
+    rel.split_whitespace().any(|rel| {
+        matches!(
+            rel.to_lowercase().as_str(),
+            "stylesheet" | "icon" | "apple-touch-icon" | "manifest" | "preload" | "modulepreload"
+        )
+    })
+}
+
+/// Split a `srcset` attribute into its image URLs, dropping the
+/// width and density descriptors.
+fn parse_srcset(srcset: &str) -> Vec<String> {
+    srcset
+        .split(',')
+        .filter_map(|candidate| candidate.split_whitespace().next())
+        .map(String::from)
+        .collect()
+}
+
+/// Extract the `<loc>` entries of a sitemap. This includes the child sitemaps
+/// of a sitemap index, so they get checked as well.
+fn extract_links_from_sitemap(input: &str) -> Vec<(String, LinkKind)> {
+    sitemap_locs(input)
+        .into_iter()
+        .map(|(loc, _)| (loc, LinkKind::Link))
+        .collect()
+}
+
+/// Extract the links of the Markdown cells of a Jupyter notebook.
+/// Code cells and their outputs are skipped, as are links to the cell's
+/// attachments (`attachment:image.png`), which are embedded in the notebook.
+fn extract_links_from_notebook(input: &str, link_attributes: &[String]) -> Vec<RawLink> {
+    notebook_markdown_cells(input)
+        .iter()
+        .flat_map(|cell| extract_links_from_markdown(cell, link_attributes))
+        .filter(|link| !link.url.starts_with("attachment:"))
+        .collect()
+}
+
+/// The source of all Markdown cells of a notebook.
+/// A cell's source is either a single string or a list of lines.
+fn notebook_markdown_cells(input: &str) -> Vec<String> {
+    let notebook: serde_json::Value = match serde_json::from_str(input) {
+        Ok(notebook) => notebook,
+        Err(_) => return vec![],
+    };
+    let cells = match notebook["cells"].as_array() {
+        Some(cells) => cells,
+        None => return vec![],
+    };
+    cells
+        .iter()
+        .filter(|cell| cell["cell_type"] == "markdown")
+        .filter_map(|cell| match &cell["source"] {
+            serde_json::Value::String(source) => Some(source.clone()),
+            serde_json::Value::Array(lines) => {
+                Some(lines.iter().filter_map(|line| line.as_str()).collect())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Child sitemaps listed in a sitemap index
+pub(crate) fn sitemap_children(input: &str) -> Vec<String> {
+    sitemap_locs(input)
+        .into_iter()
+        .filter(|(_, is_sitemap)| *is_sitemap)
+        .map(|(loc, _)| loc)
+        .collect()
+}
+
+/// All `<loc>` entries of a sitemap, along with whether they point to
+/// another sitemap (`<sitemap><loc>`) or to a page (`<url><loc>`)
+fn sitemap_locs(input: &str) -> Vec<(String, bool)> {
+    // The HTML parser is lenient enough to read the simple XML of sitemaps
+    let tendril = StrTendril::from(input);
+    let rc_dom = parse_document(RcDom::default(), Default::default()).one(tendril);
+
+    let mut locs = Vec::new();
+    walk_sitemap_locs(&mut locs, &rc_dom.document, "");
+    locs
+}
+
+fn walk_sitemap_locs(locs: &mut Vec<(String, bool)>, node: &Handle, parent: &str) {
+    let name = match node.data {
+        NodeData::Element { ref name, .. } => name.local.to_string(),
+        _ => String::new(),
+    };
+    if name == "loc" {
+        let mut loc = String::new();
+        for child in node.children.borrow().iter() {
+            if let NodeData::Text { ref contents } = child.data {
+                loc.push_str(&contents.borrow());
+            }
+        }
+        locs.push((loc.trim().to_string(), parent == "sitemap"));
+        return;
+    }
+    for child in node.children.borrow().iter() {
+        walk_sitemap_locs(locs, child, &name);
+    }
+}
+
+/// Find the byte ranges of `links` in the document they were extracted from.
+/// Links in HTML don't get a position from the parser, so they get searched
+/// for in document order; links located by the Markdown parser keep their
+/// span. Links whose text differs from the source, e.g. due to HTML entities
+/// or escapes, can't be found and get no span.
+fn locate_links(input: &str, links: &mut [RawLink]) {
+    let mut cursor = 0;
+    for link in links {
+        if let Some(span) = &link.span {
+            cursor = cursor.max(span.end);
+            continue;
+        }
+        if link.url.is_empty() {
+            continue;
+        }
+        let mut from = cursor;
+        while let Some(offset) = input[from..].find(&link.url) {
+            let start = from + offset;
+            let end = start + link.url.len();
+            // Don't take the start of a longer URL for the link.
+            // Punctuation at the end of a sentence is no part of a URL.
+            let rest =
+                input[end..].trim_start_matches(|c| matches!(c, '.' | ',' | ';' | ':' | '!' | '?'));
+            if rest.chars().next().map_or(true, ends_url) {
+                link.span = Some(start..end);
+                cursor = end;
+                break;
+            }
+            from = end;
+        }
+    }
+}
+
+/// Characters which can't be part of a URL in a document
+fn ends_url(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>' | '(' | ')' | '[' | ']' | '`')
+}
+
+/// The links of a Markdown, HTML or plaintext document along with their
+/// byte range in it, in document order, e.g. to replace them. Ignored links
+/// and links which can't be located are left out.
+pub(crate) fn link_spans(input: &str, file_type: FileType) -> Vec<(String, Range<usize>)> {
+    let mut links = match file_type {
+        FileType::Markdown => extract_links_from_markdown(input, &[]),
+        FileType::Html => extract_links_from_html(input, &[]),
+        FileType::Plaintext => text_links(input, &(0..input.len()), input),
+        _ => return vec![],
+    };
+    locate_links(input, &mut links);
+    let mut spans: Vec<_> = links
+        .into_iter()
+        .filter(|link| !link.ignored)
+        .filter_map(|link| link.span.map(|span| (link.url, span)))
+        .collect();
+    // The destination of a Markdown link comes before the links in its text
+    spans.sort_by_key(|(_, span)| span.start);
+    spans
+}
+
+/// The links of a document along with their byte range in it, in document
+/// order, e.g. to highlight or replace them in an editor.
+///
+/// Links in Markdown are located by the parser. Links in HTML get searched
+/// for in the document, which fails for links written differently than they
+/// are read, e.g. with HTML entities; these links are left out, like
+/// relative links (which need a base URL) and links preceded by an inline
+/// ignore directive. Only Markdown, HTML and plaintext are supported.
+pub fn extract_links_with_spans(input: &str, file_type: FileType) -> Vec<(Uri, Range<usize>)> {
+    link_spans(input, file_type)
+        .into_iter()
+        .filter_map(|(link, span)| {
+            let uri = Uri::try_from(resolve_protocol_relative(link, None).as_str()).ok()?;
+            Some((uri, span))
+        })
+        .collect()
 }
 
 /// Extract unparsed URL strings from a plaintext.
-fn extract_links_from_plaintext(input: &str) -> Vec<String> {
+fn extract_links_from_plaintext(input: &str) -> Vec<(String, LinkKind)> {
     find_links(input)
         .iter()
-        .map(|l| String::from(l.as_str()))
+        .map(|l| (String::from(l.as_str()), LinkKind::Link))
         .collect()
 }
 
+/// Finds links in plaintext inputs.
+/// By default, linkify's `LinkFinder` is used. Custom regular expressions can
+/// augment or replace it, e.g. for logs or other formats with their own
+/// URL conventions. The first capture group of a pattern is treated as the URL,
+/// or the whole match if the pattern has no groups.
+#[derive(Debug, Clone, Default)]
+pub struct PlaintextFinder {
+    patterns: Vec<Regex>,
+    replace_default: bool,
+}
+
+impl PlaintextFinder {
+    /// Use `patterns` in addition to the default finder,
+    /// or instead of it if `replace_default` is set.
+    pub fn new(patterns: Vec<Regex>, replace_default: bool) -> Self {
+        Self {
+            patterns,
+            replace_default,
+        }
+    }
+
+    fn find(&self, input: &str) -> Vec<(String, LinkKind)> {
+        let mut links = if self.replace_default {
+            vec![]
+        } else {
+            extract_links_from_plaintext(input)
+        };
+        for pattern in &self.patterns {
+            for caps in pattern.captures_iter(input) {
+                if let Some(m) = caps.get(1).or_else(|| caps.get(0)) {
+                    links.push((m.as_str().to_string(), LinkKind::Link));
+                }
+            }
+        }
+        links
+    }
+}
+
+/// Give protocol-relative URLs (e.g. `//cdn.example.com/lib.js`) the scheme
+/// of the base URL, falling back to https for local or missing base URLs
+fn resolve_protocol_relative(link: String, base_url: Option<&Url>) -> String {
+    if !link.starts_with("//") {
+        return link;
+    }
+    let scheme = match base_url.map(Url::scheme) {
+        Some("http") => "http",
+        _ => "https",
+    };
+    format!("{}:{}", scheme, link)
+}
+
+/// How links get extracted from an input, see `extract_links_with_options`.
+/// The default extracts all links with the built-in finders.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ExtractOptions<'a> {
+    /// How to find links in plaintext, the default finder if `None`
+    pub(crate) plaintext_finder: Option<&'a PlaintextFinder>,
+    /// Additional HTML attributes holding a URL
+    pub(crate) link_attributes: &'a [String],
+    /// Drop links of these kinds
+    pub(crate) skip_kinds: &'a [LinkKind],
+    /// Ignore links whose text matches one of these patterns, like links
+    /// preceded by an inline ignore directive
+    pub(crate) exclude_text: Option<&'a RegexSet>,
+    /// Leave links to anchors in the same document (e.g. `#usage`) to
+    /// `same_document_links`. Otherwise they get resolved like any other
+    /// relative link.
+    pub(crate) check_anchors: bool,
+}
+
+/// Extract the links of an input with the default options
 pub(crate) fn extract_links(
     input_content: &InputContent,
     base_url: Option<Url>,
 ) -> HashSet<Request> {
-    let links = match input_content.file_type {
-        FileType::Markdown => extract_links_from_markdown(&input_content.content),
-        FileType::Html => extract_links_from_html(&input_content.content),
-        FileType::Plaintext => extract_links_from_plaintext(&input_content.content),
+    extract_links_with_options(input_content, base_url, &ExtractOptions::default())
+}
+
+/// Extract the links of an input
+pub(crate) fn extract_links_with_options(
+    input_content: &InputContent,
+    base_url: Option<Url>,
+    opts: &ExtractOptions<'_>,
+) -> HashSet<Request> {
+    let default_finder = PlaintextFinder::default();
+    let plaintext_finder = opts.plaintext_finder.unwrap_or(&default_finder);
+    let link_attributes = opts.link_attributes;
+    let mut links: Vec<RawLink> = match input_content.file_type {
+        FileType::Markdown => extract_links_from_markdown(&input_content.content, link_attributes),
+        FileType::Html => extract_links_from_html(&input_content.content, link_attributes),
+        FileType::Plaintext => plaintext_finder
+            .find(&input_content.content)
+            .into_iter()
+            .map(RawLink::from)
+            .collect(),
+        FileType::Sitemap => extract_links_from_sitemap(&input_content.content)
+            .into_iter()
+            .map(RawLink::from)
+            .collect(),
+        FileType::Notebook => extract_links_from_notebook(&input_content.content, link_attributes),
+    };
+    // The positions of links in sitemaps and notebooks tell nothing useful
+    let newlines: Vec<usize> = match input_content.file_type {
+        FileType::Markdown | FileType::Html | FileType::Plaintext => {
+            locate_links(&input_content.content, &mut links);
+            input_content
+                .content
+                .match_indices('\n')
+                .map(|(index, _)| index)
+                .collect()
+        }
+        _ => vec![],
+    };
+    let line_of = |offset: usize| match newlines.binary_search(&offset) {
+        Ok(line) | Err(line) => line + 1,
+    };
+    // Without a base URL, relative links of a local HTML file point to
+    // other files of the site
+    let file_url = match base_url {
+        Some(_) => None,
+        None => local_html_url(input_content),
     };
 
     // Only keep legit URLs. This sorts out things like anchors.
     // Silently ignore the parse failures for now.
     let mut requests: HashSet<Request> = HashSet::new();
-    for link in links {
-        match Uri::try_from(link.as_str()) {
-            Ok(uri) => {
-                requests.insert(Request::new(uri, input_content.input.clone()));
+    for RawLink {
+        url: link,
+        kind,
+        ignored,
+        text,
+        span,
+    } in links
+    {
+        let ignored = ignored
+            || matches!((opts.exclude_text, text), (Some(patterns), Some(text)) if patterns.is_match(&text));
+        // Skipping happens before merging duplicates, so a skipped image
+        // still gets checked if it is used as a regular link as well
+        if opts.skip_kinds.contains(&kind) {
+            continue;
+        }
+        // Anchors in the same document don't depend on the base URL,
+        // see `same_document_links`
+        if opts.check_anchors && link.starts_with('#') {
+            continue;
+        }
+        let link = resolve_protocol_relative(link, base_url.as_ref());
+        let (uri, relative) = match Uri::try_from(link.as_str()) {
+            Ok(uri) => (uri, false),
+            // The root of the site is unknown for links like `/about.html`
+            Err(_) if file_url.is_some() && !link.starts_with('/') => {
+                match file_url.as_ref().and_then(|file| file.join(&link).ok()) {
+                    Some(new_url) => (Uri::Website(new_url), true),
+                    None => continue,
+                }
             }
             Err(_) => {
-                if !Path::new(&link).exists() {
-                    if let Some(base_url) = &base_url {
-                        if let Ok(new_url) = base_url.join(&link) {
-                            requests.insert(Request::new(
-                                Uri::Website(new_url),
-                                input_content.input.clone(),
-                            ));
-                        }
-                    }
+                if Path::new(&link).exists() {
+                    continue;
+                }
+                match base_url.as_ref().and_then(|base| base.join(&link).ok()) {
+                    Some(new_url) => (Uri::Website(new_url), true),
+                    None => continue,
                 }
             }
         };
+        let request = Request::new(uri, input_content.input.clone())
+            .with_kind(kind)
+            .with_inline_ignore(ignored)
+            .with_line(span.map(|span| line_of(span.start)))
+            .with_relative(relative);
+        let request = match requests.get(&request) {
+            // If a link is used both as an image and a regular link,
+            // hold it to the stricter expectations of an image.
+            // It is only ignored if all of its uses are.
+            Some(existing) => {
+                let kind = match existing.kind {
+                    LinkKind::Image => LinkKind::Image,
+                    _ if kind == LinkKind::Image => LinkKind::Image,
+                    existing_kind => existing_kind,
+                };
+                let ignored = existing.inline_ignore && ignored;
+                let line = existing.line.or(request.line);
+                // Relative only if all of its uses are
+                let relative = existing.relative && request.relative;
+                request
+                    .with_kind(kind)
+                    .with_inline_ignore(ignored)
+                    .with_line(line)
+                    .with_relative(relative)
+            }
+            None => request,
+        };
+        requests.replace(request);
     }
     requests
 }
 
+/// The `file://` URL of a local HTML file, to resolve its relative links
+fn local_html_url(input_content: &InputContent) -> Option<Url> {
+    match (&input_content.input, input_content.file_type) {
+        (Input::FsPath(path), FileType::Html) => {
+            Url::from_file_path(path.canonicalize().ok()?).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Links to anchors in the document itself (e.g. `#usage`), along with
+/// whether the document has these anchors.
+/// Only local files and remote documents are supported, as the links
+/// need a URL to be reported with.
+pub(crate) fn same_document_links(
+    input_content: &InputContent,
+    style: AnchorStyle,
+    ignore_case: bool,
+) -> HashSet<Request> {
+    let content = &input_content.content;
+    let (links, anchors) = match input_content.file_type {
+        FileType::Markdown => (
+            extract_links_from_markdown(content, &[]),
+            markdown_anchors(content, style),
+        ),
+        FileType::Html => (
+            extract_links_from_html(content, &[]),
+            html_anchors(content, style),
+        ),
+        _ => return HashSet::new(),
+    };
+    let document = match &input_content.input {
+        Input::FsPath(path) => match path.canonicalize().map(Url::from_file_path) {
+            Ok(Ok(url)) => url,
+            _ => return HashSet::new(),
+        },
+        Input::RemoteUrl(url) => url.clone(),
+        _ => return HashSet::new(),
+    };
+
+    links
+        .into_iter()
+        .filter(|link| !link.ignored && link.url.len() > 1)
+        .filter_map(|link| link.url.strip_prefix('#').map(String::from))
+        .map(|fragment| {
+            let mut url = document.clone();
+            url.set_fragment(Some(&fragment));
+            let mut request = Request::new(Uri::Website(url), input_content.input.clone());
+            request.anchor_found = Some(contains_anchor(&anchors, &fragment, ignore_case));
+            request
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use crate::test_utils::website;
@@ -219,6 +990,84 @@ mod test {
             FileType::from(Path::new("/absolute/path/to/test.something")),
             FileType::Plaintext
         );
+        assert_eq!(FileType::from(Path::new("test.md.gz")), FileType::Markdown);
+        assert_eq!(FileType::from(Path::new("test.html.gz")), FileType::Html);
+        assert_eq!(FileType::from(Path::new("test.gz")), FileType::Plaintext);
+        assert_eq!(
+            FileType::from(Path::new("sitemap-posts.xml")),
+            FileType::Sitemap
+        );
+        assert_eq!(
+            FileType::from(Path::new("sitemap.xml.gz")),
+            FileType::Sitemap
+        );
+        assert_eq!(FileType::from(Path::new("feed.xml")), FileType::Plaintext);
+        assert_eq!(
+            FileType::from(Path::new("tutorial.ipynb")),
+            FileType::Notebook
+        );
+    }
+
+    #[test]
+    fn test_file_type_from_content() {
+        let from_content = FileType::from_content;
+        assert_eq!(
+            from_content("<!DOCTYPE html><p>Hi</p>", None),
+            FileType::Html
+        );
+        assert_eq!(
+            from_content("  <html lang=\"en\"><body></body></html>", None),
+            FileType::Html
+        );
+        assert_eq!(
+            from_content(
+                r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#,
+                None
+            ),
+            FileType::Sitemap
+        );
+        assert_eq!(
+            from_content("# Title\n\nSome text", None),
+            FileType::Markdown
+        );
+        assert_eq!(
+            from_content("See [the docs](https://example.org).", None),
+            FileType::Markdown
+        );
+        assert_eq!(
+            from_content("Visit https://example.org #1", None),
+            FileType::Plaintext
+        );
+
+        // The content type takes precedence, unless it's unspecific
+        assert_eq!(
+            from_content("Just text", Some("text/html; charset=utf-8")),
+            FileType::Html
+        );
+        assert_eq!(
+            from_content("# Title", Some("text/plain; charset=utf-8")),
+            FileType::Markdown
+        );
+    }
+
+    #[test]
+    fn test_extract_markdown_inline_html() {
+        let input = load_fixture("TEST_INLINE_HTML.md");
+        let links: HashSet<(Uri, LinkKind)> =
+            extract_links(&InputContent::from_string(&input, FileType::Markdown), None)
+                .into_iter()
+                .map(|r| (r.uri, r.kind))
+                .collect();
+
+        let expected: HashSet<(Uri, LinkKind)> = [
+            (website("https://example.org/multiline"), LinkKind::Link),
+            (website("https://example.org/block"), LinkKind::Link),
+            (website("https://example.org/logo.png"), LinkKind::Image),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(links, expected);
     }
 
     #[test]
@@ -272,6 +1121,112 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_same_document_links() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("README.md");
+        let content = "# Usage\n\n\
+            See [usage](#usage), [options](#options) and [elsewhere](other.md#usage).\n\n\
+            <a href=\"#Usage\">Top</a>";
+        let input_content = InputContent {
+            input: Input::FsPath(path.clone()),
+            file_type: FileType::Markdown,
+            content: content.to_string(),
+            encoding: None,
+        };
+        std::fs::write(&path, content)?;
+
+        let found = |ignore_case| -> HashSet<(String, bool)> {
+            same_document_links(&input_content, AnchorStyle::Github, ignore_case)
+                .into_iter()
+                .map(|r| {
+                    let url = match r.uri {
+                        Uri::Website(url) => url,
+                        Uri::Mail(_) | Uri::Git(_) => unreachable!(),
+                    };
+                    (url.fragment().unwrap().to_string(), r.anchor_found.unwrap())
+                })
+                .collect()
+        };
+
+        let expected: HashSet<(String, bool)> = [
+            ("usage".to_string(), true),
+            ("options".to_string(), false),
+            ("Usage".to_string(), false),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(found(false), expected);
+        assert!(found(true).contains(&("Usage".to_string(), true)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_script_links() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("menu.html");
+        let content = r##"<a href="#" onclick="openMenu()">Menu</a>
+            <a href="#!/settings" onclick="openSettings()">Settings</a>
+            <a href="javascript:void(0)">Nothing</a>
+            <a href="#intro" onclick="track('intro')">Intro</a>"##;
+        std::fs::write(&path, content)?;
+        let input_content = InputContent {
+            input: Input::FsPath(path),
+            file_type: FileType::Html,
+            content: content.to_string(),
+            encoding: None,
+        };
+
+        let links: HashSet<Uri> = extract_links(&input_content, None)
+            .into_iter()
+            .map(|request| request.uri)
+            .collect();
+        let expected: HashSet<Uri> = [website("javascript:void(0)")].iter().cloned().collect();
+        assert_eq!(links, expected);
+
+        // Placeholders of script navigation aren't anchors
+        let fragments: Vec<String> =
+            same_document_links(&input_content, AnchorStyle::Github, false)
+                .into_iter()
+                .filter_map(|request| match request.uri {
+                    Uri::Website(url) => url.fragment().map(String::from),
+                    _ => None,
+                })
+                .collect();
+        assert_eq!(fragments, vec!["intro".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_links() {
+        let input = "[Setup](setup.html), [intro](https://docs.example.com/intro) \
+            and [setup again](https://docs.example.com/setup.html)";
+        let links: HashSet<(String, bool)> = extract_links(
+            &InputContent::from_string(input, FileType::Markdown),
+            Some(Url::parse("https://docs.example.com/").unwrap()),
+        )
+        .into_iter()
+        .map(|request| (request.uri.to_string(), request.relative))
+        .collect();
+        let expected: HashSet<(String, bool)> = [
+            ("https://docs.example.com/intro".to_string(), false),
+            // Also used as an absolute link
+            ("https://docs.example.com/setup.html".to_string(), false),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(links, expected);
+
+        let links = extract_links(
+            &InputContent::from_string("[Setup](setup.html)", FileType::Markdown),
+            Some(Url::parse("https://docs.example.com/").unwrap()),
+        );
+        assert!(links.iter().all(|request| request.relative));
+        assert_eq!(links.len(), 1);
+    }
+
     #[test]
     fn test_skip_markdown_anchors() {
         let input = "This is [a test](#lol).";
@@ -371,6 +1326,51 @@ mod test {
         assert_eq!(links, expected_links);
     }
 
+    #[test]
+    fn test_extract_html_relative_links_to_files() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let docs = dir.path().join("docs");
+        std::fs::create_dir(&docs)?;
+        let page = docs.join("index.html");
+        let content = r##"<a href="../other.html#intro">Other</a>
+            <img src="img/logo%20small.png">
+            <a href="/about.html">About</a>
+            <a href="#top">Top</a>
+            <a href="https://example.org/">Example</a>"##;
+        std::fs::write(&page, content)?;
+        let input_content = InputContent {
+            input: Input::FsPath(page),
+            file_type: FileType::Html,
+            content: content.to_string(),
+            encoding: None,
+        };
+        let extract = |base_url: Option<Url>| -> HashSet<Uri> {
+            extract_links(&input_content, base_url)
+                .into_iter()
+                .map(|r| r.uri)
+                .collect()
+        };
+
+        let root = dir.path().canonicalize()?;
+        let file = |path: &str| Url::from_file_path(root.join(path)).unwrap();
+        let mut other = file("other.html");
+        other.set_fragment(Some("intro"));
+        let expected_links = [
+            Uri::Website(other),
+            Uri::Website(file("docs/img/logo small.png")),
+            website("https://example.org/"),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(extract(None), expected_links);
+
+        // A base URL takes precedence
+        let base_url = Url::parse("https://example.org/docs/")?;
+        assert!(extract(Some(base_url)).contains(&website("https://example.org/about.html")));
+        Ok(())
+    }
+
     #[test]
     fn test_extract_html5_lowercase_doctype() {
         // this has been problematic with previous XML based parser
@@ -389,6 +1389,57 @@ mod test {
         assert_eq!(links, expected_links);
     }
 
+    #[test]
+    fn test_extract_notebook() {
+        let input = load_fixture("TEST_NOTEBOOK.ipynb");
+        let links: HashSet<Uri> = extract_links(
+            &InputContent::from_string(&input, FileType::Notebook),
+            Some(Url::parse("https://docs.example.com/tutorials/").unwrap()),
+        )
+        .into_iter()
+        .map(|r| r.uri)
+        .collect();
+
+        let expected_links: HashSet<Uri> = [
+            website("https://pandas.pydata.org/docs/"),
+            website("https://docs.example.com/tutorials/guide/data.md"),
+            website("https://numpy.org"),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(links, expected_links);
+    }
+
+    #[test]
+    fn test_extract_protocol_relative() {
+        let input = load_fixture("TEST_PROTOCOL_RELATIVE.html");
+        let find = |base_url: Option<&str>| -> HashSet<Uri> {
+            extract_links(
+                &InputContent::from_string(&input, FileType::Html),
+                base_url.map(|url| Url::parse(url).unwrap()),
+            )
+            .into_iter()
+            .map(|r| r.uri)
+            .collect()
+        };
+
+        let expected_links: HashSet<Uri> = [
+            website("https://cdn.example.com/style.css"),
+            website("https://cdn.example.com/lib.js"),
+            website("https://example.org/docs/"),
+            website("https://images.example.com/logo.png"),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(find(None), expected_links);
+
+        let links = find(Some("http://example.net"));
+        assert!(links.contains(&website("http://cdn.example.com/lib.js")));
+        assert!(links.contains(&website("http://example.net/about")));
+    }
+
     #[test]
     fn test_extract_html5_minified() {
         // minified HTML with some quirky elements such as href attribute values specified without quotes
@@ -455,4 +1506,449 @@ mod test {
 
         assert_eq!(links, expected_links);
     }
+
+    #[test]
+    fn test_extract_image_kinds() {
+        let input = r#"<html>
+                <a href="https://example.org/page">Page</a>
+                <img src="https://example.org/logo.png">
+                <img srcset="https://example.org/small.png 1x, https://example.org/large.png 2x">
+                <a href="https://example.org/logo.png">Logo</a>
+            </html>"#;
+        let kinds: HashSet<(Uri, LinkKind)> =
+            extract_links(&InputContent::from_string(input, FileType::Html), None)
+                .into_iter()
+                .map(|r| (r.uri, r.kind))
+                .collect();
+
+        let expected = [
+            (website("https://example.org/page"), LinkKind::Link),
+            (website("https://example.org/logo.png"), LinkKind::Image),
+            (website("https://example.org/small.png"), LinkKind::Image),
+            (website("https://example.org/large.png"), LinkKind::Image),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(kinds, expected);
+
+        let input = "[Link](https://example.org/a) ![Image](https://example.org/b.png)";
+        let kinds: HashSet<(Uri, LinkKind)> =
+            extract_links(&InputContent::from_string(input, FileType::Markdown), None)
+                .into_iter()
+                .map(|r| (r.uri, r.kind))
+                .collect();
+        assert!(kinds.contains(&(website("https://example.org/b.png"), LinkKind::Image)));
+        assert!(kinds.contains(&(website("https://example.org/a"), LinkKind::Link)));
+    }
+
+    #[test]
+    fn test_extract_social_meta() {
+        let input = load_fixture("TEST_SOCIAL_META.html");
+        let links: HashSet<(Uri, LinkKind)> = extract_links(
+            &InputContent::from_string(&input, FileType::Html),
+            Some(Url::parse("https://example.org").unwrap()),
+        )
+        .into_iter()
+        .map(|r| (r.uri, r.kind))
+        .collect();
+
+        let expected_links = [
+            (website("https://example.org/changelog"), LinkKind::Link),
+            (
+                website("https://example.org/releases/"),
+                LinkKind::SocialMeta,
+            ),
+            (
+                website("https://example.org/images/preview.png"),
+                LinkKind::SocialMeta,
+            ),
+            (
+                website("https://cdn.example.org/preview.png"),
+                LinkKind::SocialMeta,
+            ),
+            (
+                website("https://example.org/images/twitter.png"),
+                LinkKind::SocialMeta,
+            ),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(links, expected_links);
+    }
+
+    #[test]
+    fn test_extract_link_lines() {
+        let input = "# Links\n\n[first](https://example.org/a)\n\
+            Again: https://example.org/a and <https://example.org/b>.\n";
+        let lines: HashSet<(Uri, Option<usize>)> =
+            extract_links(&InputContent::from_string(input, FileType::Markdown), None)
+                .into_iter()
+                .map(|r| (r.uri, r.line))
+                .collect();
+
+        let expected_lines = [
+            (website("https://example.org/a"), Some(3)),
+            (website("https://example.org/b"), Some(4)),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(lines, expected_lines);
+    }
+
+    #[test]
+    fn test_extract_links_with_spans() {
+        let input = "[https://example.org/a](https://example.org/a \"Title\") \
+            <!-- kimchi-ignore -->\n[skipped](https://example.org/skipped) \
+            ![logo](//cdn.example.org/logo.png) [relative](docs/)\n";
+        let found = extract_links_with_spans(input, FileType::Markdown);
+        assert_eq!(found[0].1, 1..22);
+        let found: Vec<(Uri, &str)> = found
+            .into_iter()
+            .map(|(uri, span)| (uri, &input[span]))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (website("https://example.org/a"), "https://example.org/a"),
+                (website("https://example.org/a"), "https://example.org/a"),
+                (
+                    website("https://cdn.example.org/logo.png"),
+                    "//cdn.example.org/logo.png"
+                ),
+            ]
+        );
+
+        let input = r#"<a href="https://example.org/a">https://example.org/a</a>
+            <img src="https://example.org/&amp;">"#;
+        // The image can't be found, as its URL has an entity
+        assert_eq!(
+            extract_links_with_spans(input, FileType::Html),
+            vec![
+                (website("https://example.org/a"), 9..30),
+                (website("https://example.org/a"), 32..53),
+            ]
+        );
+
+        let input = "Go to https://example.org/a, then https://example.org/b.";
+        assert_eq!(
+            extract_links_with_spans(input, FileType::Plaintext),
+            vec![
+                (website("https://example.org/a"), 6..27),
+                (website("https://example.org/b"), 34..55),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_assets_and_skip_kinds() {
+        let input = r#"<html>
+                <link rel="canonical" href="https://example.org/">
+                <link rel="stylesheet" href="https://example.org/style.css">
+                <script src="https://example.org/app.js"></script>
+                <video><source src="https://example.org/intro.mp4"></video>
+                <img src="https://example.org/logo.png">
+                <a href="https://example.org/logo.png">Logo</a>
+            </html>"#;
+        let find = |skip_kinds: &[LinkKind]| -> HashSet<(Uri, LinkKind)> {
+            extract_links_with_options(
+                &InputContent::from_string(input, FileType::Html),
+                None,
+                &ExtractOptions {
+                    skip_kinds,
+                    ..ExtractOptions::default()
+                },
+            )
+            .into_iter()
+            .map(|r| (r.uri, r.kind))
+            .collect()
+        };
+
+        let links = find(&[]);
+        assert!(links.contains(&(website("https://example.org/"), LinkKind::Link)));
+        assert!(links.contains(&(website("https://example.org/style.css"), LinkKind::Asset)));
+        assert!(links.contains(&(website("https://example.org/app.js"), LinkKind::Asset)));
+        assert!(links.contains(&(website("https://example.org/intro.mp4"), LinkKind::Asset)));
+        assert!(links.contains(&(website("https://example.org/logo.png"), LinkKind::Image)));
+
+        let expected: HashSet<(Uri, LinkKind)> = [
+            (website("https://example.org/"), LinkKind::Link),
+            // Still linked to, so it gets checked as a regular link
+            (website("https://example.org/logo.png"), LinkKind::Link),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(find(&[LinkKind::Image, LinkKind::Asset]), expected);
+    }
+
+    #[test]
+    fn test_inline_ignore() {
+        let ignored = |input: &str, file_type| -> Vec<(String, bool)> {
+            let mut links: Vec<(String, bool)> =
+                extract_links(&InputContent::from_string(input, file_type), None)
+                    .into_iter()
+                    .map(|r| (r.uri.to_string(), r.inline_ignore))
+                    .collect();
+            links.sort();
+            links
+        };
+
+        let markdown = "<!-- kimchi-ignore -->\n\
+            [Broken](https://example.org/broken) [Fine](https://example.org/fine)\n\n\
+            See <!-- kimchi-ignore --> <https://example.org/auto>";
+        assert_eq!(
+            ignored(markdown, FileType::Markdown),
+            vec![
+                ("https://example.org/auto".to_string(), true),
+                ("https://example.org/broken".to_string(), true),
+                ("https://example.org/fine".to_string(), false),
+            ]
+        );
+
+        let html = r#"<p>
+                <!-- kimchi-ignore -->
+                <a href="https://example.org/broken">https://example.org/broken</a>
+                <a href="https://example.org/fine">Fine</a>
+                <a href="https://example.org/twice">Twice</a>
+                <!-- kimchi-ignore -->
+                <a href="https://example.org/twice">Twice</a>
+            </p>"#;
+        assert_eq!(
+            ignored(html, FileType::Html),
+            vec![
+                ("https://example.org/broken".to_string(), true),
+                ("https://example.org/fine".to_string(), false),
+                // Only ignored if all uses are
+                ("https://example.org/twice".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exclude_link_text() {
+        let patterns = RegexSet::new(&["(?i)^example$"]).unwrap();
+        let ignored = |input: &str, file_type| -> Vec<(String, bool)> {
+            let mut links: Vec<(String, bool)> = extract_links_with_options(
+                &InputContent::from_string(input, file_type),
+                None,
+                &ExtractOptions {
+                    exclude_text: Some(&patterns),
+                    ..ExtractOptions::default()
+                },
+            )
+            .into_iter()
+            .map(|r| (r.uri.to_string(), r.inline_ignore))
+            .collect();
+            links.sort();
+            links
+        };
+
+        let markdown = "[Example](https://broken.example.org) \
+            [`example`](https://code.example.org) \
+            [![example](https://img.example.org/logo.png)](https://logo.example.org) \
+            [An example](https://fine.example.org)";
+        assert_eq!(
+            ignored(markdown, FileType::Markdown),
+            vec![
+                ("https://broken.example.org/".to_string(), true),
+                ("https://code.example.org/".to_string(), true),
+                ("https://fine.example.org/".to_string(), false),
+                ("https://img.example.org/logo.png".to_string(), true),
+                // The text of the link includes the alt text of the image
+                ("https://logo.example.org/".to_string(), true),
+            ]
+        );
+
+        let html = r#"<p>
+                <a href="https://broken.example.org"><b>Example</b></a>
+                <a href="https://fine.example.org">Fine</a>
+                <a href="https://twice.example.org">example</a>
+                <a href="https://twice.example.org">Twice</a>
+            </p>"#;
+        assert_eq!(
+            ignored(html, FileType::Html),
+            vec![
+                ("https://broken.example.org/".to_string(), true),
+                ("https://fine.example.org/".to_string(), false),
+                // Only excluded if all uses are
+                ("https://twice.example.org/".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_data_attributes() {
+        let input = r#"<html>
+                <img data-src="/lazy.png" src="data:image/gif;base64,R0lGOD">
+                <img data-srcset="/small.png 1x, /large.png 2x">
+                <a data-href="/page" data-permalink="/permalink">Page</a>
+                <div data-tooltip="Click here" data-config="/not-a-link"></div>
+            </html>"#;
+        let base_url = Url::parse("https://example.org").ok();
+        let find = |link_attributes: &[String]| -> HashSet<(Uri, LinkKind)> {
+            extract_links_with_options(
+                &InputContent::from_string(input, FileType::Html),
+                base_url.clone(),
+                &ExtractOptions {
+                    link_attributes,
+                    ..ExtractOptions::default()
+                },
+            )
+            .into_iter()
+            .map(|r| (r.uri, r.kind))
+            .collect()
+        };
+
+        let links = find(&[]);
+        let expected = [
+            (website("https://example.org/lazy.png"), LinkKind::Image),
+            (website("https://example.org/small.png"), LinkKind::Image),
+            (website("https://example.org/large.png"), LinkKind::Image),
+            (website("https://example.org/page"), LinkKind::Link),
+        ];
+        for link in &expected {
+            assert!(links.contains(link), "missing {:?}", link);
+        }
+        assert!(!links.contains(&(website("https://example.org/permalink"), LinkKind::Link)));
+        assert!(!links.contains(&(website("https://example.org/not-a-link"), LinkKind::Link)));
+
+        let links = find(&["data-permalink".to_string()]);
+        assert!(links.contains(&(website("https://example.org/permalink"), LinkKind::Link)));
+        assert!(!links.contains(&(website("https://example.org/not-a-link"), LinkKind::Link)));
+    }
+
+    #[test]
+    fn test_plaintext_patterns() {
+        let input = "https://example.com doc(https://example.org/a)";
+        let pattern = Regex::new(r"doc\((\S+?)\)").unwrap();
+        let find = |replace_default| -> HashSet<Uri> {
+            let finder = PlaintextFinder::new(vec![pattern.clone()], replace_default);
+            extract_links_with_options(
+                &InputContent::from_string(input, FileType::Plaintext),
+                None,
+                &ExtractOptions {
+                    plaintext_finder: Some(&finder),
+                    ..ExtractOptions::default()
+                },
+            )
+            .into_iter()
+            .map(|r| r.uri)
+            .collect()
+        };
+
+        let augmented = find(false);
+        assert!(augmented.contains(&website("https://example.com")));
+        assert!(augmented.contains(&website("https://example.org/a")));
+
+        let replaced = find(true);
+        assert_eq!(
+            replaced,
+            [website("https://example.org/a")].iter().cloned().collect()
+        );
+    }
+
+    #[test]
+    fn test_extract_sitemap() {
+        let input = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+                <url><loc>https://example.org/</loc><lastmod>2021-01-01</lastmod></url>
+                <url>
+                    <loc>
+                        https://example.org/about
+                    </loc>
+                </url>
+            </urlset>"#;
+        let links = extract_links(&InputContent::from_string(input, FileType::Sitemap), None);
+        let expected: HashSet<Uri> = [
+            website("https://example.org/"),
+            website("https://example.org/about"),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(
+            links.into_iter().map(|r| r.uri).collect::<HashSet<_>>(),
+            expected
+        );
+        assert!(sitemap_children(input).is_empty());
+        assert!(is_sitemap(input));
+    }
+
+    #[test]
+    fn test_sitemap_children() {
+        let input = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+                <sitemap><loc>https://example.org/sitemap-posts.xml</loc></sitemap>
+            </sitemapindex>"#;
+        assert_eq!(
+            sitemap_children(input),
+            vec!["https://example.org/sitemap-posts.xml".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_same_document_anchors() {
+        let input = "[Usage](#usage) and [docs](https://example.org/docs)";
+        let base_url = Some(Url::parse("https://example.org/").unwrap());
+        let extract = |check_anchors: bool| -> HashSet<Uri> {
+            extract_links_with_options(
+                &InputContent::from_string(input, FileType::Markdown),
+                base_url.clone(),
+                &ExtractOptions {
+                    check_anchors,
+                    ..ExtractOptions::default()
+                },
+            )
+            .into_iter()
+            .map(|r| r.uri)
+            .collect()
+        };
+
+        // Resolved against the base URL like any other relative link
+        assert_eq!(
+            extract(false),
+            [
+                website("https://example.org/#usage"),
+                website("https://example.org/docs"),
+            ]
+            .iter()
+            .cloned()
+            .collect()
+        );
+        // Left to `same_document_links`
+        assert_eq!(
+            extract(true),
+            [website("https://example.org/docs")]
+                .iter()
+                .cloned()
+                .collect()
+        );
+    }
+
+    #[test]
+    fn test_link_spans() {
+        let input = "[https://a.org](https://a.org) and [b](https://a.org/b)\n\
+            <!-- kimchi-ignore -->\n[c](https://c.org)";
+        let spans = link_spans(input, FileType::Markdown);
+        let found: Vec<&str> = spans.iter().map(|(_, span)| &input[span.clone()]).collect();
+        assert_eq!(
+            found,
+            vec!["https://a.org", "https://a.org", "https://a.org/b"]
+        );
+        // The link text comes first in the document
+        assert_eq!(spans[1].1, 16..29);
+
+        let input = r#"<a href="https://a.org/x?a=1&amp;b=2">x</a> <img src="https://a.org/x">"#;
+        let spans = link_spans(input, FileType::Html);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(&input[spans[0].1.clone()], "https://a.org/x");
+        assert!(link_spans(input, FileType::Sitemap).is_empty());
+
+        let input = "See https://a.org/x and https://b.org";
+        let spans = link_spans(input, FileType::Plaintext);
+        let found: Vec<&str> = spans.iter().map(|(_, span)| &input[span.clone()]).collect();
+        assert_eq!(found, vec!["https://a.org/x", "https://b.org"]);
+    }
 }
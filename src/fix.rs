@@ -0,0 +1,68 @@
+use crate::extract::{link_spans, FileType};
+use std::collections::HashMap;
+use url::Url;
+
+/// Replace links of a Markdown or HTML document, e.g. permanently
+/// redirected links with their new URL (see `Response::permanent_redirect`).
+/// Only absolute links get replaced. The rest of the document, including
+/// the formatting of the links, is left untouched.
+/// Returns the new document along with the number of replaced links.
+pub fn replace_links(
+    content: &str,
+    file_type: FileType,
+    replacements: &HashMap<Url, Url>,
+) -> (String, usize) {
+    if !matches!(file_type, FileType::Markdown | FileType::Html) {
+        return (content.to_string(), 0);
+    }
+    let mut replaced = String::with_capacity(content.len());
+    let mut last = 0;
+    let mut count = 0;
+    for (link, span) in link_spans(content, file_type) {
+        let replacement = match Url::parse(&link) {
+            Ok(url) => replacements.get(&url),
+            Err(_) => None,
+        };
+        if let Some(replacement) = replacement {
+            replaced.push_str(&content[last..span.start]);
+            replaced.push_str(replacement.as_str());
+            last = span.end;
+            count += 1;
+        }
+    }
+    replaced.push_str(&content[last..]);
+    (replaced, count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_replace_links() {
+        let old = Url::parse("https://old.org/page").unwrap();
+        let new = Url::parse("https://new.org/page").unwrap();
+        let replacements: HashMap<Url, Url> = vec![(old, new)].into_iter().collect();
+
+        let content = "See [https://old.org/page](https://old.org/page), \
+            not [this](https://old.org/page/other).\n";
+        let (replaced, count) = replace_links(content, FileType::Markdown, &replacements);
+        assert_eq!(count, 2);
+        assert_eq!(
+            replaced,
+            "See [https://new.org/page](https://new.org/page), \
+            not [this](https://old.org/page/other).\n"
+        );
+
+        let content = r#"<p><a href="https://old.org/page" class="x">Page</a></p>"#;
+        let (replaced, count) = replace_links(content, FileType::Html, &replacements);
+        assert_eq!(count, 1);
+        assert_eq!(
+            replaced,
+            r#"<p><a href="https://new.org/page" class="x">Page</a></p>"#
+        );
+
+        let (replaced, count) = replace_links(content, FileType::Plaintext, &replacements);
+        assert_eq!((replaced.as_str(), count), (content, 0));
+    }
+}
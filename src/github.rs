@@ -0,0 +1,383 @@
+use percent_encoding::percent_decode_str;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use url::Url;
+
+pub(crate) const GITHUB_API: &str = "https://api.github.com";
+
+/// Issues and pull requests resolved by a single GraphQL query, at most.
+/// Keeps the cost of a query within the limits of the API.
+const MAX_BATCH_SIZE: usize = 100;
+
+/// A GitHub resource whose existence can be resolved through the REST API
+/// instead of scraping the HTML page. Issues and pull requests can also be
+/// resolved in batches through the GraphQL API, see `GithubBatch`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum GithubResource {
+    /// A repository, e.g. `github.com/owner/repo`
+    Repo { owner: String, repo: String },
+    /// An issue, e.g. `github.com/owner/repo/issues/1`
+    Issue {
+        owner: String,
+        repo: String,
+        number: u64,
+    },
+    /// A pull request, e.g. `github.com/owner/repo/pull/1`
+    Pull {
+        owner: String,
+        repo: String,
+        number: u64,
+    },
+    /// A file or directory at a given ref,
+    /// e.g. `github.com/owner/repo/blob/main/README.md`
+    Contents {
+        owner: String,
+        repo: String,
+        reference: String,
+        path: String,
+    },
+}
+
+impl GithubResource {
+    /// Recognize a GitHub URL. Returns `None` for URLs which don't point to
+    /// github.com or whose shape can't be resolved through the API.
+    pub(crate) fn parse(url: &Url) -> Option<Self> {
+        if !matches!(url.host_str(), Some("github.com") | Some("www.github.com")) {
+            return None;
+        }
+        // Decoded, as they get encoded again when building the API URL
+        let segments: Vec<String> = url
+            .path_segments()?
+            .filter(|s| !s.is_empty())
+            .map(|s| Some(percent_decode_str(s).decode_utf8().ok()?.into_owned()))
+            .collect::<Option<_>>()?;
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        let (owner, repo) = match segments.as_slice() {
+            [owner, repo, ..] => (
+                (*owner).to_string(),
+                repo.trim_end_matches(".git").to_string(),
+            ),
+            _ => return None,
+        };
+
+        match segments[2..] {
+            [] => Some(GithubResource::Repo { owner, repo }),
+            ["issues", number] => Some(GithubResource::Issue {
+                owner,
+                repo,
+                number: number.parse().ok()?,
+            }),
+            // Sub-pages such as `/files` or `/commits` belong to the same pull request
+            ["pull", number, ..] => Some(GithubResource::Pull {
+                owner,
+                repo,
+                number: number.parse().ok()?,
+            }),
+            // Note: refs containing a slash (e.g. `feature/foo`) are ambiguous
+            // and get interpreted as the first path segment
+            ["blob", reference, ref path @ ..] | ["tree", reference, ref path @ ..] => {
+                Some(GithubResource::Contents {
+                    owner,
+                    repo,
+                    reference: reference.to_string(),
+                    path: path.join("/"),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// REST endpoint of the API at `api` which resolves this resource.
+    /// Paths and refs get percent-encoded, e.g. if they contain spaces or `#`.
+    pub(crate) fn api_url(&self, api: &Url) -> String {
+        let (owner, repo, resource) = match self {
+            GithubResource::Repo { owner, repo } => (owner, repo, vec![]),
+            GithubResource::Issue {
+                owner,
+                repo,
+                number,
+            } => (owner, repo, vec!["issues".to_string(), number.to_string()]),
+            GithubResource::Pull {
+                owner,
+                repo,
+                number,
+            } => (owner, repo, vec!["pulls".to_string(), number.to_string()]),
+            GithubResource::Contents {
+                owner, repo, path, ..
+            } => {
+                let mut resource = vec!["contents".to_string()];
+                resource.extend(path.split('/').map(String::from));
+                (owner, repo, resource)
+            }
+        };
+        let mut url = api.clone();
+        url.path_segments_mut()
+            .unwrap()
+            .pop_if_empty()
+            .extend(&["repos", owner.as_str(), repo.as_str()])
+            .extend(&resource);
+        if let GithubResource::Contents { reference, .. } = self {
+            url.query_pairs_mut().append_pair("ref", reference);
+        }
+        url.into()
+    }
+
+    /// The repository and number of an issue or pull request
+    fn numbered(&self) -> Option<(&str, &str, u64)> {
+        match self {
+            GithubResource::Issue {
+                owner,
+                repo,
+                number,
+            }
+            | GithubResource::Pull {
+                owner,
+                repo,
+                number,
+            } => Some((owner.as_str(), repo.as_str(), *number)),
+            _ => None,
+        }
+    }
+}
+
+/// Issues and pull requests of a single repository, resolved by one query
+/// to the GraphQL API instead of one REST request each
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GithubBatch {
+    owner: String,
+    repo: String,
+    resources: Vec<GithubResource>,
+}
+
+impl GithubBatch {
+    /// Group the issues and pull requests among `resources` by repository.
+    /// Other resources are left out, as they can only be resolved through
+    /// the REST API.
+    pub(crate) fn group<I: IntoIterator<Item = GithubResource>>(resources: I) -> Vec<Self> {
+        let mut repos: BTreeMap<(String, String), Vec<GithubResource>> = BTreeMap::new();
+        for resource in resources {
+            if let Some((owner, repo, _)) = resource.numbered() {
+                let key = (owner.to_string(), repo.to_string());
+                repos.entry(key).or_default().push(resource);
+            }
+        }
+        repos
+            .into_iter()
+            .flat_map(|((owner, repo), resources)| {
+                resources
+                    .chunks(MAX_BATCH_SIZE)
+                    .map(|chunk| GithubBatch {
+                        owner: owner.clone(),
+                        repo: repo.clone(),
+                        resources: chunk.to_vec(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// GraphQL endpoint of the API at `api`
+    pub(crate) fn endpoint(api: &Url) -> Url {
+        let mut url = api.clone();
+        url.path_segments_mut()
+            .unwrap()
+            .pop_if_empty()
+            .push("graphql");
+        url
+    }
+
+    /// The request body, looking up every number under an alias of its own.
+    /// An issue and a pull request can't share a number, so both are looked
+    /// up with `issueOrPullRequest`.
+    pub(crate) fn query(&self) -> Value {
+        let mut numbers: Vec<u64> = self
+            .resources
+            .iter()
+            .filter_map(|resource| resource.numbered().map(|(_, _, number)| number))
+            .collect();
+        numbers.sort_unstable();
+        numbers.dedup();
+        let fields: String = numbers
+            .iter()
+            .map(|number| {
+                format!(
+                    "n{0}: issueOrPullRequest(number: {0}) {{ __typename }} ",
+                    number
+                )
+            })
+            .collect();
+        json!({
+            "query": format!(
+                "query($owner: String!, $name: String!) {{ \
+                repository(owner: $owner, name: $name) {{ {}}} }}",
+                fields
+            ),
+            "variables": { "owner": self.owner, "name": self.repo },
+        })
+    }
+
+    /// Whether the resources exist, according to the response to `query`.
+    /// Resources the response doesn't tell anything about are left out,
+    /// e.g. all of them if the repository is missing or inaccessible, as the
+    /// REST API gives more specific answers for these.
+    pub(crate) fn resolve(&self, response: &Value) -> Vec<(GithubResource, bool)> {
+        let repository = match response.pointer("/data/repository") {
+            Some(Value::Object(repository)) => repository,
+            _ => return vec![],
+        };
+        self.resources
+            .iter()
+            .filter_map(|resource| {
+                let (_, _, number) = resource.numbered()?;
+                let exists = match repository.get(&format!("n{}", number))? {
+                    Value::Null => false,
+                    // The REST API only has pull requests at `/pulls`,
+                    // but both at `/issues`
+                    found => match resource {
+                        GithubResource::Pull { .. } => {
+                            found.get("__typename") == Some(&json!("PullRequest"))
+                        }
+                        _ => true,
+                    },
+                };
+                Some((resource.clone(), exists))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(url: &str) -> Option<GithubResource> {
+        GithubResource::parse(&Url::parse(url).unwrap())
+    }
+
+    fn api() -> Url {
+        Url::parse(GITHUB_API).unwrap()
+    }
+
+    #[test]
+    fn test_parse_repo() {
+        assert_eq!(
+            parse("https://github.com/wgalyen/kimchi"),
+            Some(GithubResource::Repo {
+                owner: "wgalyen".to_string(),
+                repo: "kimchi".to_string()
+            })
+        );
+        assert_eq!(
+            parse("https://github.com/wgalyen/kimchi.git"),
+            parse("https://github.com/wgalyen/kimchi/")
+        );
+    }
+
+    #[test]
+    fn test_parse_issue_and_pull() {
+        assert_eq!(
+            parse("https://github.com/wgalyen/kimchi/issues/42"),
+            Some(GithubResource::Issue {
+                owner: "wgalyen".to_string(),
+                repo: "kimchi".to_string(),
+                number: 42
+            })
+        );
+        assert_eq!(
+            parse("https://github.com/wgalyen/kimchi/pull/7/files"),
+            Some(GithubResource::Pull {
+                owner: "wgalyen".to_string(),
+                repo: "kimchi".to_string(),
+                number: 7
+            })
+        );
+        assert_eq!(parse("https://github.com/wgalyen/kimchi/issues/new"), None);
+    }
+
+    #[test]
+    fn test_parse_contents() {
+        let resource = parse("https://github.com/wgalyen/kimchi/blob/master/src/lib.rs").unwrap();
+        assert_eq!(
+            resource.api_url(&api()),
+            "https://api.github.com/repos/wgalyen/kimchi/contents/src/lib.rs?ref=master"
+        );
+
+        let resource =
+            parse("https://github.com/wgalyen/kimchi/blob/v%231/docs/a%20b%3F.md").unwrap();
+        assert_eq!(
+            resource,
+            GithubResource::Contents {
+                owner: "wgalyen".to_string(),
+                repo: "kimchi".to_string(),
+                reference: "v#1".to_string(),
+                path: "docs/a b?.md".to_string(),
+            }
+        );
+        assert_eq!(
+            resource.api_url(&api()),
+            "https://api.github.com/repos/wgalyen/kimchi/contents/docs/a%20b%3F.md?ref=v%231"
+        );
+        assert_eq!(
+            parse("https://github.com/wgalyen/kimchi/issues/42")
+                .unwrap()
+                .api_url(&api()),
+            "https://api.github.com/repos/wgalyen/kimchi/issues/42"
+        );
+    }
+
+    #[test]
+    fn test_batch() {
+        let resources = vec![
+            parse("https://github.com/wgalyen/kimchi/issues/1").unwrap(),
+            parse("https://github.com/wgalyen/kimchi/pull/2").unwrap(),
+            parse("https://github.com/wgalyen/kimchi/pull/3").unwrap(),
+            parse("https://github.com/wgalyen/kimchi/issues/4").unwrap(),
+            parse("https://github.com/wgalyen/other/issues/1").unwrap(),
+            parse("https://github.com/wgalyen/kimchi").unwrap(),
+        ];
+        let batches = GithubBatch::group(resources.clone());
+        assert_eq!(batches.len(), 2);
+        let batch = &batches[0];
+        assert_eq!(batch.resources, resources[..4].to_vec());
+        assert_eq!(
+            GithubBatch::endpoint(&api()).as_str(),
+            "https://api.github.com/graphql"
+        );
+
+        let query = batch.query();
+        assert_eq!(
+            query["variables"],
+            json!({"owner": "wgalyen", "name": "kimchi"})
+        );
+        let text = query["query"].as_str().unwrap();
+        assert!(text.contains("n1: issueOrPullRequest(number: 1) { __typename }"));
+        assert!(text.contains("n4: issueOrPullRequest(number: 4) { __typename }"));
+
+        let response = json!({"data": {"repository": {
+            "n1": {"__typename": "PullRequest"},
+            "n2": {"__typename": "PullRequest"},
+            "n3": {"__typename": "Issue"},
+            "n4": null,
+        }}});
+        assert_eq!(
+            batch.resolve(&response),
+            vec![
+                (resources[0].clone(), true),
+                (resources[1].clone(), true),
+                (resources[2].clone(), false),
+                (resources[3].clone(), false),
+            ]
+        );
+        // A missing repository is left to the REST API
+        assert!(batch
+            .resolve(&json!({"data": {"repository": null}}))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_unrecognized() {
+        assert_eq!(parse("https://example.org/wgalyen/kimchi"), None);
+        assert_eq!(parse("https://github.com/wgalyen"), None);
+        assert_eq!(parse("https://github.com/wgalyen/kimchi/wiki/Home"), None);
+    }
+}
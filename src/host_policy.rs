@@ -0,0 +1,318 @@
+use anyhow::{anyhow, bail, Context, Error, Result};
+use regex::RegexSet;
+use std::{collections::HashMap, str::FromStr, sync::Mutex, time::Duration};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::{sleep_until, Instant};
+use url::Url;
+
+use crate::excludes::Excludes;
+
+/// Hosts which are ubiquitous in documentation, but quickly answer with
+/// `429 Too Many Requests` when hit by a link checker.
+/// Entries are `(host pattern, delay in milliseconds, max concurrency)`.
+pub const DEFAULT_HOST_POLICIES: &[(&str, u64, usize)] = &[
+    ("wikipedia.org", 250, 2),
+    ("*.wikipedia.org", 250, 2),
+    ("*.wikimedia.org", 250, 2),
+    ("*.wiktionary.org", 250, 2),
+    ("*.wikidata.org", 250, 2),
+    ("archive.org", 1000, 1),
+    ("*.archive.org", 1000, 1),
+];
+
+/// Politeness rules for requests to a host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostPolicy {
+    /// Minimum time between the start of two requests
+    pub delay: Duration,
+    /// Maximum number of concurrent requests
+    pub max_concurrency: usize,
+}
+
+impl HostPolicy {
+    pub fn new(delay: Duration, max_concurrency: usize) -> Self {
+        Self {
+            delay,
+            max_concurrency,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HostState {
+    policy: HostPolicy,
+    permits: Semaphore,
+    /// Earliest time the next request may start
+    next_slot: Mutex<Instant>,
+}
+
+/// Throttles requests to hosts with a policy.
+/// Hosts matching the same pattern share their limits,
+/// e.g. all language editions of Wikipedia.
+#[derive(Debug)]
+pub(crate) struct HostThrottle {
+    patterns: Option<RegexSet>,
+    hosts: Vec<HostState>,
+}
+
+impl HostThrottle {
+    /// Create a throttle from host glob patterns (see `Excludes::compile_host_globs`)
+    /// and their policies. If several patterns match a host, the first one wins.
+    pub(crate) fn new(policies: Vec<(String, HostPolicy)>) -> Result<Self> {
+        let patterns: Vec<&str> = policies.iter().map(|(p, _)| p.as_str()).collect();
+        let patterns =
+            Excludes::compile_host_globs(&patterns).context("Invalid host policy pattern")?;
+        let hosts = policies
+            .into_iter()
+            .map(|(_, policy)| HostState {
+                policy,
+                permits: Semaphore::new(policy.max_concurrency.max(1)),
+                next_slot: Mutex::new(Instant::now()),
+            })
+            .collect();
+        Ok(Self { patterns, hosts })
+    }
+
+    fn state(&self, url: &Url) -> Option<&HostState> {
+        let host = url.host_str()?.trim_end_matches('.');
+        let index = self.patterns.as_ref()?.matches(host).iter().next()?;
+        self.hosts.get(index)
+    }
+
+    /// Wait until a request to `url` is allowed.
+    /// The returned permit must be held until the request is done.
+    pub(crate) async fn acquire(&self, url: &Url) -> Option<SemaphorePermit<'_>> {
+        let state = self.state(url)?;
+        let permit = state.permits.acquire().await.ok()?;
+        let start = {
+            let mut next_slot = state.next_slot.lock().unwrap();
+            let start = (*next_slot).max(Instant::now());
+            *next_slot = start + state.policy.delay;
+            start
+        };
+        sleep_until(start).await;
+        Some(permit)
+    }
+
+    /// Hold back all requests to the host of `url`,
+    /// e.g. when it asked to come back later via `Retry-After`
+    pub(crate) fn back_off(&self, url: &Url, duration: Duration) {
+        if let Some(state) = self.state(url) {
+            let mut next_slot = state.next_slot.lock().unwrap();
+            *next_slot = (*next_slot).max(Instant::now() + duration);
+        }
+    }
+}
+
+/// Request methods for links to hosts matching a glob pattern,
+/// e.g. `HEAD` for download mirrors or `GET` for hosts rejecting `HEAD`
+#[derive(Debug, Default)]
+pub(crate) struct HostMethods {
+    patterns: Option<RegexSet>,
+    methods: Vec<Vec<reqwest::Method>>,
+}
+
+impl HostMethods {
+    /// Create the method policy from host glob patterns (see
+    /// `Excludes::compile_host_globs`). If several patterns match a host,
+    /// the first one wins. Patterns without any method are ignored.
+    pub(crate) fn new(policies: Vec<(String, Vec<reqwest::Method>)>) -> Result<Self> {
+        let policies: Vec<_> = policies
+            .into_iter()
+            .filter(|(_, methods)| !methods.is_empty())
+            .collect();
+        let patterns: Vec<&str> = policies.iter().map(|(p, _)| p.as_str()).collect();
+        let patterns =
+            Excludes::compile_host_globs(&patterns).context("Invalid host method pattern")?;
+        let methods = policies.into_iter().map(|(_, methods)| methods).collect();
+        Ok(Self { patterns, methods })
+    }
+
+    /// The methods to check `url` with, if its host has a policy
+    pub(crate) fn get(&self, url: &Url) -> Option<&[reqwest::Method]> {
+        let host = url.host_str()?.trim_end_matches('.');
+        let index = self.patterns.as_ref()?.matches(host).iter().next()?;
+        self.methods.get(index).map(Vec::as_slice)
+    }
+}
+
+/// A maximum number of requests per time unit, e.g. `60/min`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestRate {
+    pub requests: u32,
+    pub per: Duration,
+}
+
+impl FromStr for RequestRate {
+    type Err = Error;
+    /// Parse `<n>/<unit>` with the unit being `s`, `min` or `h`.
+    /// A plain number is taken as requests per second.
+    fn from_str(rate: &str) -> Result<Self> {
+        let (requests, unit) = match rate.find('/') {
+            Some(idx) => (&rate[..idx], &rate[idx + 1..]),
+            None => (rate, "s"),
+        };
+        let requests: u32 = requests
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid number of requests in rate {}", rate))?;
+        if requests == 0 {
+            bail!("Request rate {} doesn't allow any requests", rate);
+        }
+        let per = match unit.trim() {
+            "s" | "sec" | "second" => Duration::from_secs(1),
+            "m" | "min" | "minute" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(60 * 60),
+            _ => return Err(anyhow!("Unknown time unit in rate {}", rate)),
+        };
+        Ok(Self { requests, per })
+    }
+}
+
+/// Spreads requests evenly to stay below a `RequestRate`, either for all
+/// requests combined or for every host separately. Requests are never sent
+/// in bursts, as these trip the leaky-bucket rate limiters of many servers.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    interval: Duration,
+    per_host: bool,
+    /// Earliest time the next request may start, per host if `per_host` is set
+    next_slots: Mutex<HashMap<String, Instant>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(rate: RequestRate, per_host: bool) -> Self {
+        Self {
+            interval: rate.per / rate.requests,
+            per_host,
+            next_slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait until another request to `url` is allowed
+    pub(crate) async fn acquire(&self, url: &Url) {
+        let key = match self.per_host {
+            true => url.host_str().unwrap_or_default().to_string(),
+            false => String::new(),
+        };
+        let start = {
+            let mut next_slots = self.next_slots.lock().unwrap();
+            let now = Instant::now();
+            let next_slot = next_slots.entry(key).or_insert(now);
+            let start = (*next_slot).max(now);
+            *next_slot = start + self.interval;
+            start
+        };
+        sleep_until(start).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn throttle() -> HostThrottle {
+        HostThrottle::new(vec![
+            (
+                "*.example.com".to_string(),
+                HostPolicy::new(Duration::from_millis(100), 1),
+            ),
+            (
+                "*.com".to_string(),
+                HostPolicy::new(Duration::from_millis(0), 4),
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_host_policy_matching() {
+        let throttle = throttle();
+        let policy = |url: &str| {
+            throttle
+                .state(&Url::parse(url).unwrap())
+                .map(|state| state.policy.max_concurrency)
+        };
+        assert_eq!(policy("https://docs.example.com/a"), Some(1));
+        assert_eq!(policy("https://example.com"), Some(4));
+        assert_eq!(policy("https://example.org"), None);
+    }
+
+    #[tokio::test]
+    async fn test_host_policy_delay() {
+        let throttle = throttle();
+        let url = Url::parse("https://docs.example.com").unwrap();
+        let start = Instant::now();
+        for _ in 0..3 {
+            throttle.acquire(&url).await.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        let other = Url::parse("https://example.org").unwrap();
+        assert!(throttle.acquire(&other).await.is_none());
+    }
+
+    #[test]
+    fn test_host_methods() {
+        let methods = HostMethods::new(vec![
+            (
+                "downloads.example.com".to_string(),
+                vec![reqwest::Method::HEAD],
+            ),
+            ("*.example.com".to_string(), vec![]),
+            (
+                "*.example.com".to_string(),
+                vec![reqwest::Method::HEAD, reqwest::Method::GET],
+            ),
+        ])
+        .unwrap();
+        let get = |url: &str| methods.get(&Url::parse(url).unwrap());
+        assert_eq!(
+            get("https://downloads.example.com/a.zip"),
+            Some(&[reqwest::Method::HEAD][..])
+        );
+        assert_eq!(
+            get("https://docs.example.com"),
+            Some(&[reqwest::Method::HEAD, reqwest::Method::GET][..])
+        );
+        assert_eq!(get("https://example.org"), None);
+    }
+
+    #[test]
+    fn test_request_rate_from_str() {
+        assert_eq!(
+            "60/min".parse::<RequestRate>().unwrap(),
+            RequestRate {
+                requests: 60,
+                per: Duration::from_secs(60)
+            }
+        );
+        assert_eq!(
+            "5".parse::<RequestRate>().unwrap(),
+            "5/s".parse::<RequestRate>().unwrap()
+        );
+        assert!("0/min".parse::<RequestRate>().is_err());
+        assert!("60/fortnight".parse::<RequestRate>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let rate = "20/s".parse().unwrap();
+        let a = Url::parse("https://a.example.com").unwrap();
+        let b = Url::parse("https://b.example.com").unwrap();
+
+        let limiter = RateLimiter::new(rate, false);
+        let start = Instant::now();
+        for url in &[&a, &b, &a] {
+            limiter.acquire(url).await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        let limiter = RateLimiter::new(rate, true);
+        let start = Instant::now();
+        for url in &[&a, &b] {
+            limiter.acquire(url).await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+}
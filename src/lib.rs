@@ -39,9 +39,18 @@ extern crate doc_comment;
 #[cfg(doctest)]
 doctest!("../README.md");
 
+mod anchors;
+mod cache;
+mod checkpoint;
 mod client;
 mod client_pool;
+mod dns;
 mod excludes;
+mod fix;
+mod github;
+mod host_policy;
+mod oauth;
+mod robots;
 mod types;
 mod uri;
 
@@ -49,10 +58,17 @@ pub mod collector;
 pub mod extract;
 pub mod test_utils;
 
-pub use client::check;
-pub use client::ClientBuilder;
+pub use anchors::AnchorStyle;
+pub use cache::{CacheEntry, LinkCache};
+pub use checkpoint::Checkpoint;
+pub use client::{check, check_content};
+pub use client::{Client, ClientBuilder, DEFAULT_UNCHECKABLE_SCHEMES};
 pub use client_pool::ClientPool;
-pub use collector::Input;
-pub use excludes::Excludes;
+pub use collector::{Input, InputContent};
+pub use excludes::{Excludes, ExcludesBuilder};
+pub use extract::FileType;
+pub use fix::replace_links;
+pub use host_policy::{HostPolicy, RequestRate, DEFAULT_HOST_POLICIES};
+pub use oauth::OAuth2Credentials;
 pub use types::*;
 pub use uri::Uri;
@@ -0,0 +1,155 @@
+use anyhow::{bail, Context, Result};
+use regex::RegexSet;
+use serde::Deserialize;
+use std::{fmt, time::Duration};
+use tokio::{sync::Mutex, time::Instant};
+use url::Url;
+
+use crate::excludes::Excludes;
+
+/// Tokens get renewed this long before they expire, so they don't expire
+/// while a request is on its way
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// Credentials for the OAuth2 client credentials flow, e.g. for
+/// documentation behind an API gateway. The bearer token is only sent
+/// to the `hosts`, never to other hosts.
+#[derive(Clone, PartialEq, Eq)]
+pub struct OAuth2Credentials {
+    /// Token endpoint of the authorization server
+    pub token_url: Url,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Scopes to request, none by default
+    pub scopes: Vec<String>,
+    /// Glob patterns of the hosts to send the token to (e.g. `*.example.com`)
+    pub hosts: Vec<String>,
+}
+
+/// Keeps the secret out of logs
+impl fmt::Debug for OAuth2Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth2Credentials")
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"<redacted>")
+            .field("scopes", &self.scopes)
+            .field("hosts", &self.hosts)
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    /// Lifetime of the token in seconds
+    expires_in: Option<u64>,
+}
+
+struct Token {
+    value: String,
+    expires_at: Option<Instant>,
+}
+
+/// Fetches and renews the bearer token for the OAuth2 hosts.
+/// Shared between all clones of a client.
+#[derive(Debug)]
+pub(crate) struct OAuth2 {
+    credentials: OAuth2Credentials,
+    hosts: RegexSet,
+    token: Mutex<Option<Token>>,
+    /// Timeout for fetching a token
+    timeout: Duration,
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Token")
+    }
+}
+
+impl OAuth2 {
+    pub(crate) fn new(credentials: OAuth2Credentials, timeout: Duration) -> Result<Self> {
+        let hosts = match Excludes::compile_host_globs(&credentials.hosts)
+            .context("Invalid OAuth2 host pattern")?
+        {
+            Some(hosts) => hosts,
+            None => bail!("OAuth2 needs at least one host to send the token to"),
+        };
+        Ok(Self {
+            credentials,
+            hosts,
+            token: Mutex::new(None),
+            timeout,
+        })
+    }
+
+    fn applies_to(&self, url: &Url) -> bool {
+        url.host_str().map_or(false, |host| {
+            self.hosts.is_match(host.trim_end_matches('.'))
+        })
+    }
+
+    /// The bearer token for `url`, if it's one of the OAuth2 hosts.
+    /// A token is fetched on first use and renewed once it expires.
+    pub(crate) async fn token_for(
+        &self,
+        client: &reqwest::Client,
+        url: &Url,
+    ) -> Result<Option<String>> {
+        if !self.applies_to(url) {
+            return Ok(None);
+        }
+        self.token_for_any(client).await.map(Some)
+    }
+
+    /// The bearer token, regardless of the host
+    pub(crate) async fn token_for_any(&self, client: &reqwest::Client) -> Result<String> {
+        let mut token = self.token.lock().await;
+        let expired = match &*token {
+            Some(token) => token.expires_at.map_or(false, |expires_at| {
+                Instant::now() + EXPIRY_MARGIN >= expires_at
+            }),
+            None => true,
+        };
+        if expired {
+            *token = Some(self.fetch(client).await?);
+        }
+        Ok(token.as_ref().unwrap().value.clone())
+    }
+
+    /// Drop a token the server rejected, unless it got renewed meanwhile.
+    /// The next `token_for` fetches a new one.
+    pub(crate) async fn reject(&self, value: &str) {
+        let mut token = self.token.lock().await;
+        if token.as_ref().map_or(false, |token| token.value == value) {
+            *token = None;
+        }
+    }
+
+    async fn fetch(&self, client: &reqwest::Client) -> Result<Token> {
+        let credentials = &self.credentials;
+        let mut form = vec![("grant_type", "client_credentials".to_string())];
+        if !credentials.scopes.is_empty() {
+            form.push(("scope", credentials.scopes.join(" ")));
+        }
+        let response = client
+            .post(credentials.token_url.clone())
+            .basic_auth(&credentials.client_id, Some(&credentials.client_secret))
+            .form(&form)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("Cannot fetch OAuth2 token")?;
+        let body = response.text().await?;
+        let token: TokenResponse =
+            serde_json::from_str(&body).context("Invalid OAuth2 token response")?;
+        Ok(Token {
+            value: token.access_token,
+            expires_at: token
+                .expires_in
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+        })
+    }
+}
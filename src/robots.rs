@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::{sleep_until, Instant};
+use url::Url;
+
+/// Upper bound for a `Crawl-delay`, so a robots.txt can't stall the run
+pub(crate) const MAX_CRAWL_DELAY: Duration = Duration::from_secs(30);
+
+/// Redirects followed when fetching a robots.txt, as recommended by RFC 9309
+pub(crate) const MAX_ROBOTS_REDIRECTS: usize = 5;
+
+/// The `Crawl-delay` in a robots.txt for the given user agent.
+/// Groups naming the product token of the user agent (e.g. `kimchi` for
+/// `kimchi/0.6.0`) take precedence over the `*` group.
+/// The delay is capped at `MAX_CRAWL_DELAY`.
+pub(crate) fn crawl_delay(robots: &str, user_agent: &str) -> Option<Duration> {
+    let token = user_agent
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    let mut specific = None;
+    let mut wildcard = None;
+    // The user agents of the current group. A group ends with the first
+    // `User-agent` line following one of its rules.
+    let mut agents: Vec<String> = vec![];
+    let mut in_rules = false;
+    for line in robots.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let (key, value) = match line.find(':') {
+            Some(index) => (
+                line[..index].trim().to_lowercase(),
+                line[index + 1..].trim(),
+            ),
+            None => continue,
+        };
+        if key == "user-agent" {
+            if in_rules {
+                agents.clear();
+                in_rules = false;
+            }
+            agents.push(value.to_lowercase());
+            continue;
+        }
+        in_rules = true;
+        if key != "crawl-delay" {
+            continue;
+        }
+        let delay = match value.parse::<f64>() {
+            Ok(delay) if delay.is_finite() && delay >= 0.0 => delay,
+            _ => continue,
+        };
+        for agent in &agents {
+            if agent == "*" {
+                wildcard.get_or_insert(delay);
+            } else if *agent == token {
+                specific.get_or_insert(delay);
+            }
+        }
+    }
+    specific
+        .or(wildcard)
+        .map(|delay| Duration::from_secs_f64(delay.min(MAX_CRAWL_DELAY.as_secs_f64())))
+}
+
+/// The crawl delay of a host and the earliest time of the next request
+#[derive(Debug)]
+struct Pacing {
+    delay: Option<Duration>,
+    next_slot: Instant,
+}
+
+/// Spaces requests to every host by the `Crawl-delay` of its robots.txt.
+/// The robots.txt of a host gets fetched along with the first request to it;
+/// hosts without one (or without a crawl delay) aren't held back, just like
+/// hosts whose robots.txt doesn't arrive within the timeout.
+#[derive(Debug)]
+pub(crate) struct CrawlDelays {
+    /// Follows up to `MAX_ROBOTS_REDIRECTS` redirects, unlike the client
+    /// checking the links
+    client: reqwest::Client,
+    user_agent: String,
+    timeout: Duration,
+    /// Per origin, filled once its robots.txt got fetched
+    hosts: Mutex<HashMap<String, Arc<tokio::sync::Mutex<Option<Pacing>>>>>,
+}
+
+impl CrawlDelays {
+    pub(crate) fn new(client: reqwest::Client, user_agent: String, timeout: Duration) -> Self {
+        Self {
+            client,
+            user_agent,
+            timeout,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait until the crawl delay of the host of `url` allows another request
+    pub(crate) async fn acquire(&self, url: &Url) {
+        if !matches!(url.scheme(), "http" | "https") {
+            return;
+        }
+        let origin = url.origin().ascii_serialization();
+        let host = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(origin)
+            .or_default()
+            .clone();
+        let start = {
+            // Concurrent requests to a new host wait for its robots.txt
+            let mut guard = host.lock().await;
+            if guard.is_none() {
+                *guard = Some(Pacing {
+                    delay: self.fetch(url).await,
+                    next_slot: Instant::now(),
+                });
+            }
+            let pacing = guard.as_mut().unwrap();
+            let delay = match pacing.delay {
+                Some(delay) => delay,
+                None => return,
+            };
+            let start = pacing.next_slot.max(Instant::now());
+            pacing.next_slot = start + delay;
+            start
+        };
+        sleep_until(start).await;
+    }
+
+    async fn fetch(&self, url: &Url) -> Option<Duration> {
+        let robots = url.join("/robots.txt").ok()?;
+        let response = self
+            .client
+            .get(robots)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        crawl_delay(&response.text().await.ok()?, &self.user_agent)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_crawl_delay() {
+        let robots = "\
+            User-agent: *\n\
+            Disallow: /private\n\
+            Crawl-delay: 2\n\
+            \n\
+            # Link checkers\n\
+            User-agent: Kimchi\n\
+            User-agent: other-bot\n\
+            Crawl-delay: 0.5 # seconds\n\
+            \n\
+            User-agent: greedy-bot\n\
+            Crawl-delay: 86400\n";
+        assert_eq!(
+            crawl_delay(robots, "kimchi/0.6.0"),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            crawl_delay(robots, "curl/7.0"),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(crawl_delay(robots, "greedy-bot"), Some(MAX_CRAWL_DELAY));
+        assert_eq!(crawl_delay("User-agent: *\nDisallow: /", "kimchi"), None);
+        assert_eq!(
+            crawl_delay("User-agent: *\nCrawl-delay: soon", "kimchi"),
+            None
+        );
+    }
+}
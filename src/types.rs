@@ -1,17 +1,112 @@
-use crate::{collector::Input, uri::Uri};
+use crate::{collector::Input, extract::FileType, uri::Uri};
 use anyhow::anyhow;
-use serde::{Serialize, Serializer};
-use std::{collections::HashSet, convert::TryFrom, fmt::Display};
+use http::HeaderMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{collections::HashSet, convert::TryFrom, fmt::Display, str::FromStr};
+use url::Url;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+/// What a link is expected to point to, based on where it was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkKind {
+    /// A regular hyperlink
+    Link,
+    /// An image source, e.g. from `<img src>`, `srcset` or `![](...)`
+    Image,
+    /// A resource loaded by a page, e.g. a script, stylesheet or video
+    Asset,
+    /// A social preview, e.g. from `<meta property="og:image">`.
+    /// Broken ones only show when a page gets shared.
+    SocialMeta,
+}
+
+impl LinkKind {
+    /// Describes the kind of link in reports, if it's not a regular one
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            LinkKind::SocialMeta => Some("social meta"),
+            _ => None,
+        }
+    }
+}
+
+impl Default for LinkKind {
+    fn default() -> Self {
+        Self::Link
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Request {
     pub uri: Uri,
     pub source: Input,
+    pub kind: LinkKind,
+    /// Excluded from checking by an inline `<!-- kimchi-ignore -->` comment
+    pub inline_ignore: bool,
+    /// For links to an anchor in the same document (e.g. `#usage`), whether
+    /// the document has that anchor. These links are checked while
+    /// collecting, as that is when the document is at hand.
+    pub anchor_found: Option<bool>,
+    /// The link as found in the input, if it got rewritten before checking.
+    /// See `ClientBuilder::url_rewriter`.
+    pub rewritten_from: Option<Uri>,
+    /// Line of the first use of the link in the input, starting at 1.
+    /// Only known for Markdown, HTML and plaintext inputs.
+    pub line: Option<usize>,
+    /// The link was relative in the input (e.g. `../setup.html`) and got
+    /// resolved against a base URL or the location of the input
+    pub relative: bool,
 }
 
 impl Request {
     pub fn new(uri: Uri, source: Input) -> Self {
-        Request { uri, source }
+        Request {
+            uri,
+            source,
+            kind: LinkKind::default(),
+            inline_ignore: false,
+            anchor_found: None,
+            rewritten_from: None,
+            line: None,
+            relative: false,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: LinkKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_inline_ignore(mut self, inline_ignore: bool) -> Self {
+        self.inline_ignore = inline_ignore;
+        self
+    }
+
+    pub fn with_line(mut self, line: Option<usize>) -> Self {
+        self.line = line;
+        self
+    }
+
+    pub fn with_relative(mut self, relative: bool) -> Self {
+        self.relative = relative;
+        self
+    }
+}
+
+// A link is only checked once per input, no matter how it was referenced
+impl PartialEq for Request {
+    fn eq(&self, other: &Self) -> bool {
+        self.uri == other.uri && self.source == other.source
+    }
+}
+
+impl Eq for Request {}
+
+impl Hash for Request {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.uri.hash(state);
+        self.source.hash(state);
     }
 }
 
@@ -26,7 +121,10 @@ impl TryFrom<String> for Request {
 
     fn try_from(s: String) -> Result<Self, Self::Error> {
         let uri = Uri::try_from(s.as_str())?;
-        Ok(Request::new(uri, Input::String(s)))
+        Ok(Request::new(
+            uri,
+            Input::from_string(s, FileType::Plaintext),
+        ))
     }
 }
 
@@ -35,7 +133,44 @@ impl TryFrom<&str> for Request {
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         let uri = Uri::try_from(s)?;
-        Ok(Request::new(uri, Input::String(s.to_owned())))
+        Ok(Request::new(
+            uri,
+            Input::from_string(s, FileType::Plaintext),
+        ))
+    }
+}
+
+/// The HTTP version used to talk to websites
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// Negotiate the version with the server: HTTP/2 if it supports it
+    /// (via ALPN, only over TLS), HTTP/1.1 otherwise
+    Auto,
+    /// Only use HTTP/1.1, e.g. for servers misbehaving over HTTP/2
+    Http1,
+    /// Use HTTP/2 without negotiating it first ("prior knowledge").
+    /// Servers which don't support HTTP/2 can't be reached.
+    Http2,
+}
+
+impl Default for HttpVersion {
+    fn default() -> Self {
+        HttpVersion::Auto
+    }
+}
+
+impl FromStr for HttpVersion {
+    type Err = anyhow::Error;
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        match version.to_lowercase().trim_start_matches("http/") {
+            "auto" => Ok(HttpVersion::Auto),
+            "1" | "1.1" => Ok(HttpVersion::Http1),
+            "2" => Ok(HttpVersion::Http2),
+            _ => Err(anyhow!(
+                "Unknown HTTP version {}, use `auto`, `1.1` or `2`",
+                version
+            )),
+        }
     }
 }
 
@@ -56,13 +191,40 @@ impl TryFrom<String> for RequestMethod {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Serialize)]
+#[derive(Debug, Eq, Serialize)]
 pub struct Response {
     #[serde(flatten)]
     pub uri: Uri,
     pub status: Status,
     #[serde(skip)]
     pub source: Input,
+    /// What the link was expected to point to
+    #[serde(skip)]
+    pub kind: LinkKind,
+    /// Line of the link in the source, see `Request::line`
+    #[serde(skip)]
+    pub line: Option<usize>,
+    #[serde(skip)]
+    pub(crate) headers: Option<HeaderMap>,
+    #[serde(skip)]
+    pub(crate) final_url: Option<Url>,
+    #[serde(skip)]
+    pub(crate) rate_limit: Option<RateLimit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) method: Option<String>,
+    /// How long checking the link took, including retries
+    #[serde(skip)]
+    pub duration: Option<Duration>,
+    /// The redirects a website answered with, in order.
+    /// The last one points to the final URL.
+    #[serde(skip)]
+    pub redirects: Vec<Redirect>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) rewritten_from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) archived: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) excluded_reason: Option<&'static str>,
 }
 
 impl Response {
@@ -71,21 +233,274 @@ impl Response {
             uri,
             status,
             source,
+            kind: LinkKind::default(),
+            line: None,
+            headers: None,
+            final_url: None,
+            rate_limit: None,
+            method: None,
+            duration: None,
+            redirects: vec![],
+            rewritten_from: None,
+            archived: None,
+            excluded_reason: None,
+        }
+    }
+
+    /// Headers of the HTTP response.
+    /// Only available if the client was built with `capture_headers(true)`.
+    pub fn headers(&self) -> Option<&HeaderMap> {
+        self.headers.as_ref()
+    }
+
+    /// The URL of the final response after following all redirects.
+    /// Only available if the client was built with `capture_headers(true)`.
+    pub fn final_url(&self) -> Option<&Url> {
+        self.final_url.as_ref()
+    }
+
+    /// The rate limit quota the server reported, if any
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit
+    }
+
+    /// The link as found in the input, if it got rewritten before checking
+    pub fn rewritten_from(&self) -> Option<&str> {
+        self.rewritten_from.as_deref()
+    }
+
+    /// A snapshot of a broken link in the Wayback Machine.
+    /// Only looked up if the client was built with `wayback_fallback(true)`.
+    pub fn archived(&self) -> Option<&Url> {
+        self.archived.as_ref()
+    }
+
+    /// Why a link got excluded, if it wasn't by the configuration,
+    /// e.g. "unsupported scheme" for `tel:` links
+    pub fn excluded_reason(&self) -> Option<&str> {
+        self.excluded_reason
+    }
+
+    /// The final URL of a working link which got moved permanently,
+    /// along with the fragment of the link. Only links with a single
+    /// permanent redirect are considered, as longer chains or temporary
+    /// redirects often lead to a different resource (e.g. a login page).
+    /// Redirects to the front page of a site are ignored for the same reason.
+    pub fn permanent_redirect(&self) -> Option<Url> {
+        if !self.status.is_success() || self.rewritten_from.is_some() {
+            return None;
+        }
+        let url = match &self.uri {
+            Uri::Website(url) => url,
+            _ => return None,
+        };
+        let redirect = match self.redirects.as_slice() {
+            [redirect] if redirect.is_permanent() => redirect,
+            _ => return None,
+        };
+        if redirect.url.path() == "/" && url.path() != "/" {
+            return None;
+        }
+        let mut target = redirect.url.clone();
+        if target.fragment().is_none() {
+            target.set_fragment(url.fragment());
+        }
+        Some(target)
+    }
+
+    /// The canonical form of a working link which only got redirected to
+    /// another host name or scheme, i.e. from or to `www.` and from `http`
+    /// to `https`, along with the fragment of the link.
+    /// Links like that work, but rely on the redirects of the site staying
+    /// in place.
+    pub fn canonical_host(&self) -> Option<Url> {
+        if !self.status.is_success() || self.rewritten_from.is_some() {
+            return None;
+        }
+        let url = match &self.uri {
+            Uri::Website(url) => url,
+            _ => return None,
+        };
+        let target = &self.redirects.last()?.url;
+        let (host, target_host) = (url.host_str()?, target.host_str()?);
+        let same_site = host.trim_start_matches("www.") == target_host.trim_start_matches("www.");
+        let same_scheme = url.scheme() == target.scheme()
+            || (url.scheme() == "http" && target.scheme() == "https");
+        if !same_site
+            || !same_scheme
+            || url.port() != target.port()
+            || url.path() != target.path()
+            || url.query() != target.query()
+            || (host == target_host && url.scheme() == target.scheme())
+        {
+            return None;
+        }
+        let mut canonical = target.clone();
+        canonical.set_fragment(url.fragment());
+        Some(canonical)
+    }
+
+    /// The request method of the last request made for a website.
+    /// If a link was checked with several methods, this is the first
+    /// method it failed with, or the last one if all of them worked.
+    pub fn method(&self) -> Option<&str> {
+        self.method.as_deref()
+    }
+}
+
+/// The HTTP response of a website, as seen by a custom status classifier
+/// (see `ClientBuilder::status_classifier`)
+#[derive(Debug, Clone, Copy)]
+pub struct ResponseInfo<'a> {
+    /// The checked link
+    pub url: &'a Url,
+    pub status: http::StatusCode,
+    pub headers: &'a HeaderMap,
+    /// The URL of the response, after following redirects
+    pub final_url: &'a Url,
+}
+
+/// A redirect followed while checking a website
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Redirect {
+    /// Status code of the redirect, e.g. `301 Moved Permanently`
+    pub code: http::StatusCode,
+    /// The URL redirected to, without fragment
+    pub url: Url,
+}
+
+impl Redirect {
+    /// `301 Moved Permanently` and `308 Permanent Redirect`
+    pub fn is_permanent(&self) -> bool {
+        is_permanent_redirect(self.code)
+    }
+}
+
+/// Limits for following redirects by their kind, on top of the overall
+/// maximum number of redirects. A redirect beyond its limit isn't followed;
+/// the link gets reported as `Status::Redirected` instead, e.g. to update
+/// links which got moved permanently while tolerating temporary redirects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RedirectPolicy {
+    /// Maximum number of permanent redirects (`301`, `308`) to follow
+    pub max_permanent: Option<usize>,
+    /// Maximum number of temporary redirects (`302`, `303`, `307`) to follow
+    pub max_temporary: Option<usize>,
+}
+
+impl RedirectPolicy {
+    /// Whether to follow a redirect with the given status code after
+    /// following the `followed` redirects
+    pub fn allows(&self, code: http::StatusCode, followed: &[Redirect]) -> bool {
+        let permanent = is_permanent_redirect(code);
+        let limit = match permanent {
+            true => self.max_permanent,
+            false => self.max_temporary,
+        };
+        limit.map_or(true, |limit| {
+            followed
+                .iter()
+                .filter(|redirect| redirect.is_permanent() == permanent)
+                .count()
+                < limit
+        })
+    }
+}
+
+fn is_permanent_redirect(code: http::StatusCode) -> bool {
+    code == http::StatusCode::MOVED_PERMANENTLY || code == http::StatusCode::PERMANENT_REDIRECT
+}
+
+/// Rate limit quota reported by a server, e.g. an API like GitHub's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RateLimit {
+    /// Number of requests left in the current window
+    pub remaining: u64,
+    /// Maximum number of requests per window
+    pub limit: Option<u64>,
+    /// Time the quota gets reset as seconds since the Unix epoch
+    pub reset: Option<u64>,
+}
+
+impl RateLimit {
+    /// Read the rate limit from the `X-RateLimit-*` headers used by GitHub and
+    /// many other APIs, or the standardized `RateLimit-*` headers
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let number =
+            |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.trim().parse().ok() };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        if let Some(remaining) = number("x-ratelimit-remaining") {
+            return Some(RateLimit {
+                remaining,
+                limit: number("x-ratelimit-limit"),
+                // Usually a timestamp, but some APIs send the seconds until the reset
+                reset: number("x-ratelimit-reset").map(|reset| match reset < now / 2 {
+                    true => now + reset,
+                    false => reset,
+                }),
+            });
         }
+        Some(RateLimit {
+            remaining: number("ratelimit-remaining")?,
+            limit: number("ratelimit-limit"),
+            // Always the seconds until the reset
+            reset: number("ratelimit-reset").map(|reset| now + reset),
+        })
+    }
+}
+
+// Responses are identified by what was checked and the outcome;
+// the captured response details don't contribute to that.
+impl PartialEq for Response {
+    fn eq(&self, other: &Self) -> bool {
+        self.uri == other.uri && self.status == other.status && self.source == other.source
+    }
+}
+
+impl Hash for Response {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.uri.hash(state);
+        self.status.hash(state);
+        self.source.hash(state);
     }
 }
 
 impl Display for Response {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let metadata = match &self.status {
-            Status::Ok(code) | Status::Redirected(code) | Status::Failed(code) => {
-                format!(" [{}]", code)
-            }
+            Status::Ok(code)
+            | Status::Redirected(code)
+            | Status::Failed(code)
+            | Status::AuthRequired(code) => format!(" [{}]", code),
             Status::Timeout(code) if code.is_some() => format!(" [{}]", code.unwrap()),
+            Status::Error(e) if f.alternate() => format!(" ({:#})", e),
             Status::Error(e) => format!(" ({})", e),
+            Status::Tls(e) if f.alternate() => format!(" ({:#})", e),
+            Status::Tls(e) => format!(" ({})", e),
+            Status::Invalid(e)
+            | Status::Unsupported(e)
+            | Status::HeaderMismatch(e)
+            | Status::ContentMismatch(e) => format!(" ({})", e),
             _ => "".to_string(),
         };
-        write!(f, "{} {}{}", self.status.icon(), self.uri, metadata)
+        write!(f, "{} {}{}", self.status.icon(), self.uri, metadata)?;
+        if let Some(label) = self.kind.label() {
+            write!(f, " ({})", label)?;
+        }
+        if let Some(original) = &self.rewritten_from {
+            write!(f, " (rewritten from {})", original)?;
+        }
+        if let Some(snapshot) = &self.archived {
+            write!(f, " (archived at {})", snapshot)?;
+        }
+        if let Some(reason) = self.excluded_reason {
+            write!(f, " ({})", reason)?;
+        }
+        Ok(())
     }
 }
 
@@ -103,7 +518,27 @@ pub enum Status {
     /// Resource was excluded from checking
     Excluded,
     /// Low-level error while loading resource
-    Error(String),
+    Error(ErrorChain),
+    /// The TLS connection failed, e.g. because of an expired certificate
+    Tls(TlsError),
+    /// Resource was loaded, but doesn't meet the expectations,
+    /// e.g. an image link returning an HTML page or too many redirects
+    Invalid(String),
+    /// Resource is protected and refused anonymous access (`401` or `403`).
+    /// Only reported if enabled via `ClientBuilder::detect_auth_required`
+    AuthRequired(http::StatusCode),
+    /// Resource can't be checked, e.g. an `ftp` link or a git remote
+    /// reached over SSH without an API token for its host
+    Unsupported(String),
+    /// Resource was loaded, but lacks a required header or has an unexpected
+    /// value, see `ClientBuilder::require_header`
+    HeaderMismatch(String),
+    /// Resource was loaded, but its body lacks required text or contains
+    /// forbidden text, see `ClientBuilder::require_content`
+    ContentMismatch(String),
+    /// Redirects lead back to an URL visited before.
+    /// Holds the URLs from the link to the repeated URL.
+    RedirectLoop(Vec<Url>),
 }
 
 impl Display for Status {
@@ -113,7 +548,17 @@ impl Display for Status {
             Status::Redirected(c) => format!("Redirect ({})", c),
             Status::Excluded => "Excluded".to_string(),
             Status::Failed(c) => format!("Failed ({})", c),
+            // `{:#}` shows the causes of the error as well
+            Status::Error(e) if f.alternate() => format!("Runtime error ({:#})", e),
             Status::Error(e) => format!("Runtime error ({})", e),
+            Status::Tls(e) if f.alternate() => format!("TLS error ({:#})", e),
+            Status::Tls(e) => format!("TLS error ({})", e),
+            Status::Invalid(e) => format!("Invalid ({})", e),
+            Status::AuthRequired(c) => format!("Auth required ({})", c),
+            Status::Unsupported(e) => format!("Unsupported ({})", e),
+            Status::HeaderMismatch(e) => format!("Header mismatch ({})", e),
+            Status::ContentMismatch(e) => format!("Content mismatch ({})", e),
+            Status::RedirectLoop(chain) => format!("Redirect loop ({})", format_chain(chain)),
             Status::Timeout(Some(c)) => format!("Timeout ({})", c),
             Status::Timeout(None) => "Timeout".to_string(),
         };
@@ -121,6 +566,15 @@ impl Display for Status {
     }
 }
 
+/// Join URLs with arrows, e.g. `https://a.org/ → https://b.org/`
+fn format_chain(chain: &[Url]) -> String {
+    chain
+        .iter()
+        .map(Url::as_str)
+        .collect::<Vec<_>>()
+        .join(" → ")
+}
+
 impl Serialize for Status {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -147,10 +601,34 @@ impl Status {
         matches!(self, Status::Ok(_))
     }
 
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, Status::Invalid(_))
+    }
+
+    pub fn is_auth_required(&self) -> bool {
+        matches!(self, Status::AuthRequired(_))
+    }
+
     pub fn is_excluded(&self) -> bool {
         matches!(self, Status::Excluded)
     }
 
+    pub fn is_unsupported(&self) -> bool {
+        matches!(self, Status::Unsupported(_))
+    }
+
+    pub fn is_header_mismatch(&self) -> bool {
+        matches!(self, Status::HeaderMismatch(_))
+    }
+
+    pub fn is_content_mismatch(&self) -> bool {
+        matches!(self, Status::ContentMismatch(_))
+    }
+
+    pub fn is_redirect_loop(&self) -> bool {
+        matches!(self, Status::RedirectLoop(_))
+    }
+
     pub fn icon(&self) -> &str {
         match self {
             Status::Ok(_) => "✅",
@@ -158,6 +636,13 @@ impl Status {
             Status::Excluded => "👻",
             Status::Failed(_) => "🚫",
             Status::Error(_) => "⚡",
+            Status::Tls(_) => "🔐",
+            Status::Invalid(_) => "❌",
+            Status::AuthRequired(_) => "🔒",
+            Status::Unsupported(_) => "❓",
+            Status::HeaderMismatch(_) => "📋",
+            Status::ContentMismatch(_) => "📄",
+            Status::RedirectLoop(_) => "🔁",
             Status::Timeout(_) => "⌛",
         }
     }
@@ -166,18 +651,340 @@ impl Status {
 impl From<reqwest::Error> for Status {
     fn from(e: reqwest::Error) -> Self {
         if e.is_timeout() {
-            Status::Timeout(e.status())
+            return Status::Timeout(e.status());
+        }
+        let chain = ErrorChain::from_error(&e);
+        match TlsErrorKind::detect(&chain) {
+            Some(kind) => Status::Tls(TlsError { kind, error: chain }),
+            None => Status::Error(chain),
+        }
+    }
+}
+
+/// An error message along with the messages of the errors which caused it,
+/// e.g. the DNS or TLS error behind a failed request.
+/// Display it with `{:#}` to include the causes.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorChain {
+    message: String,
+    causes: Vec<String>,
+}
+
+impl ErrorChain {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        ErrorChain {
+            message: message.into(),
+            causes: vec![],
+        }
+    }
+
+    /// Collect the `source()` chain of an error.
+    /// Causes already contained in an earlier message are skipped,
+    /// as many errors repeat their source in their own message.
+    pub fn from_error(error: &(dyn std::error::Error + 'static)) -> Self {
+        let mut chain = ErrorChain::new(error.to_string());
+        let mut source = error.source();
+        while let Some(cause) = source {
+            let cause_message = cause.to_string();
+            let known = std::iter::once(&chain.message)
+                .chain(&chain.causes)
+                .any(|message| message.contains(&cause_message));
+            if !known {
+                chain.causes.push(cause_message);
+            }
+            source = cause.source();
+        }
+        chain
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The messages of the underlying errors, outermost first
+    pub fn causes(&self) -> &[String] {
+        &self.causes
+    }
+}
+
+impl Display for ErrorChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if f.alternate() {
+            for cause in &self.causes {
+                write!(f, ": {}", cause)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<String> for ErrorChain {
+    fn from(message: String) -> Self {
+        ErrorChain::new(message)
+    }
+}
+
+impl From<&str> for ErrorChain {
+    fn from(message: &str) -> Self {
+        ErrorChain::new(message)
+    }
+}
+
+/// What went wrong when establishing a TLS connection
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TlsErrorKind {
+    /// The certificate expired or isn't valid yet
+    Expired,
+    /// The certificate isn't valid for the host name
+    HostnameMismatch,
+    /// The certificate is self-signed or issued by an unknown CA
+    UntrustedIssuer,
+    /// Any other handshake or certificate error
+    Other,
+}
+
+impl TlsErrorKind {
+    /// Tell TLS errors apart from other runtime errors by their messages.
+    /// The TLS libraries don't expose their error kinds through `reqwest`,
+    /// so the messages of OpenSSL, Secure Transport, SChannel and rustls
+    /// are matched.
+    pub fn detect(chain: &ErrorChain) -> Option<Self> {
+        let messages: Vec<String> = std::iter::once(chain.message())
+            .chain(chain.causes().iter().map(String::as_str))
+            .map(str::to_lowercase)
+            .collect();
+        let mentions = |needles: &[&str]| {
+            messages
+                .iter()
+                .any(|message| needles.iter().any(|needle| message.contains(needle)))
+        };
+        if !mentions(&["certificate", "tls", "ssl", "handshake"]) {
+            None
+        } else if mentions(&["expired", "not yet valid"]) {
+            Some(TlsErrorKind::Expired)
+        } else if mentions(&[
+            "hostname mismatch",
+            "certnotvalidforname",
+            "not valid for name",
+            "doesn't match",
+            "does not match",
+        ]) {
+            Some(TlsErrorKind::HostnameMismatch)
+        } else if mentions(&[
+            "self signed",
+            "self-signed",
+            "unable to get local issuer",
+            "unknownissuer",
+            "unknown ca",
+            "untrusted",
+            "not trusted",
+        ]) {
+            Some(TlsErrorKind::UntrustedIssuer)
         } else {
-            Status::Error(e.to_string())
+            Some(TlsErrorKind::Other)
+        }
+    }
+}
+
+impl Display for TlsErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            TlsErrorKind::Expired => "certificate expired",
+            TlsErrorKind::HostnameMismatch => "certificate hostname mismatch",
+            TlsErrorKind::UntrustedIssuer => "untrusted certificate",
+            TlsErrorKind::Other => "handshake failed",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// A failed TLS connection, with the kind of failure and the underlying error
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsError {
+    pub kind: TlsErrorKind,
+    pub error: ErrorChain,
+}
+
+impl Display for TlsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match f.alternate() {
+            true => write!(f, "{}: {:#}", self.kind, self.error),
+            false => write!(f, "{}", self.kind),
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use super::*;
     use crate::test_utils::website;
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
+    #[test]
+    fn test_rate_limit_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(RateLimit::from_headers(&headers), None);
+
+        headers.insert("x-ratelimit-remaining", "42".parse().unwrap());
+        headers.insert("x-ratelimit-limit", "60".parse().unwrap());
+        headers.insert("x-ratelimit-reset", "1617000000".parse().unwrap());
+        assert_eq!(
+            RateLimit::from_headers(&headers),
+            Some(RateLimit {
+                remaining: 42,
+                limit: Some(60),
+                reset: Some(1617000000)
+            })
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert("ratelimit-remaining", "0".parse().unwrap());
+        headers.insert("ratelimit-reset", "30".parse().unwrap());
+        let rate_limit = RateLimit::from_headers(&headers).unwrap();
+        assert_eq!(rate_limit.remaining, 0);
+        assert!(rate_limit.reset.unwrap() > 1617000000);
+    }
+
+    #[derive(Debug)]
+    struct TestError(&'static str, Option<Box<TestError>>);
+
+    impl Display for TestError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl std::error::Error for TestError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            self.1.as_ref().map(|e| e.as_ref() as _)
+        }
+    }
+
+    #[test]
+    fn test_canonical_host() {
+        let redirected = |link: &str, target: &str| {
+            let mut response = Response::new(
+                website(link),
+                Status::Ok(http::StatusCode::OK),
+                Input::Stdin,
+            );
+            response.redirects = vec![Redirect {
+                code: http::StatusCode::MOVED_PERMANENTLY,
+                url: Url::parse(target).unwrap(),
+            }];
+            response.canonical_host().map(String::from)
+        };
+        assert_eq!(
+            redirected(
+                "http://example.com/docs#usage",
+                "https://www.example.com/docs"
+            ),
+            Some("https://www.example.com/docs#usage".to_string())
+        );
+        assert_eq!(
+            redirected("https://www.example.com/", "https://example.com/"),
+            Some("https://example.com/".to_string())
+        );
+        assert_eq!(
+            redirected("http://example.com/?q=1", "https://example.com/?q=1"),
+            Some("https://example.com/?q=1".to_string())
+        );
+        // Other changes than the host name and scheme
+        assert_eq!(
+            redirected("https://example.com/docs", "https://www.example.com/docs/"),
+            None
+        );
+        assert_eq!(
+            redirected("https://example.com/docs", "https://docs.example.com/docs"),
+            None
+        );
+        assert_eq!(
+            redirected("https://example.com/docs", "http://example.com/docs"),
+            None
+        );
+        assert_eq!(
+            redirected("https://example.com/docs", "https://example.com/docs"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_http_version_from_str() {
+        assert_eq!("auto".parse::<HttpVersion>().unwrap(), HttpVersion::Auto);
+        assert_eq!("1.1".parse::<HttpVersion>().unwrap(), HttpVersion::Http1);
+        assert_eq!("HTTP/2".parse::<HttpVersion>().unwrap(), HttpVersion::Http2);
+        assert!("3".parse::<HttpVersion>().is_err());
+    }
+
+    #[test]
+    fn test_error_chain() {
+        let error = TestError(
+            "error sending request",
+            Some(Box::new(TestError(
+                "error trying to connect: dns error",
+                Some(Box::new(TestError("dns error", None))),
+            ))),
+        );
+        let chain = ErrorChain::from_error(&error);
+        assert_eq!(chain.message(), "error sending request");
+        assert_eq!(chain.causes(), ["error trying to connect: dns error"]);
+        assert_eq!(chain.to_string(), "error sending request");
+        assert_eq!(
+            format!("{:#}", chain),
+            "error sending request: error trying to connect: dns error"
+        );
+
+        let status = Status::Error(chain);
+        assert_eq!(
+            format!("{:#}", status),
+            "Runtime error (error sending request: error trying to connect: dns error)"
+        );
+    }
+
+    #[test]
+    fn test_tls_error_kind() {
+        let detect = |message: &str, cause: &str| {
+            TlsErrorKind::detect(&ErrorChain {
+                message: "error sending request".to_string(),
+                causes: vec![format!("{}: {}", message, cause)],
+            })
+        };
+        assert_eq!(
+            detect(
+                "error:1416F086:SSL routines",
+                "certificate verify failed (certificate has expired)"
+            ),
+            Some(TlsErrorKind::Expired)
+        );
+        assert_eq!(
+            detect("invalid peer certificate", "CertNotValidForName"),
+            Some(TlsErrorKind::HostnameMismatch)
+        );
+        assert_eq!(
+            detect(
+                "error:1416F086:SSL routines",
+                "certificate verify failed (self signed certificate)"
+            ),
+            Some(TlsErrorKind::UntrustedIssuer)
+        );
+        assert_eq!(
+            detect("received fatal alert", "HandshakeFailure"),
+            Some(TlsErrorKind::Other)
+        );
+        assert_eq!(detect("dns error", "failed to lookup address"), None);
+
+        let status = Status::Tls(TlsError {
+            kind: TlsErrorKind::Expired,
+            error: "certificate has expired".into(),
+        });
+        assert_eq!(status.to_string(), "TLS error (certificate expired)");
+        assert_eq!(
+            format!("{:#}", status),
+            "TLS error (certificate expired: certificate has expired)"
+        );
+    }
+
     #[test]
     fn test_uri_host_ip_v4() {
         let uri = website("http://127.0.0.1");
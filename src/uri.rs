@@ -1,4 +1,6 @@
 use anyhow::{bail, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use std::{convert::TryFrom, fmt::Display};
@@ -11,6 +13,50 @@ pub enum Uri {
     Website(Url),
     /// Mail address
     Mail(String),
+    /// Git remote reached over SSH, e.g. `git@github.com:owner/repo.git`
+    /// or `ssh://git@github.com/owner/repo.git`
+    Git(String),
+}
+
+lazy_static! {
+    /// The scp-like syntax of git, e.g. `git@github.com:owner/repo.git`
+    static ref SCP_LIKE_REMOTE: Regex =
+        Regex::new(r"^[\w.-]+@([\w.-]+):/?([^\s:]+?)/?$").unwrap();
+}
+
+/// The repository a git remote points to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitRemote {
+    pub host: String,
+    /// The owner of the repository, including subgroups on GitLab
+    pub owner: String,
+    /// The name of the repository, without a `.git` suffix
+    pub repo: String,
+}
+
+impl GitRemote {
+    /// Parse an SSH remote in URL (`ssh://`, `git+ssh://`) or scp-like syntax
+    fn parse(remote: &str) -> Option<Self> {
+        let (host, path) = match Url::parse(remote) {
+            Ok(url) if matches!(url.scheme(), "ssh" | "git+ssh" | "ssh+git") => {
+                (url.host_str()?.to_string(), url.path().to_string())
+            }
+            Ok(_) => return None,
+            Err(_) => {
+                let captures = SCP_LIKE_REMOTE.captures(remote)?;
+                (captures[1].to_string(), captures[2].to_string())
+            }
+        };
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        match segments.as_slice() {
+            [owner @ .., repo] if !owner.is_empty() => Some(GitRemote {
+                host,
+                owner: owner.join("/"),
+                repo: repo.trim_end_matches(".git").to_string(),
+            }),
+            _ => None,
+        }
+    }
 }
 
 impl Uri {
@@ -18,6 +64,7 @@ impl Uri {
         match self {
             Uri::Website(url) => url.as_str(),
             Uri::Mail(address) => address.as_str(),
+            Uri::Git(remote) => remote.as_str(),
         }
     }
 
@@ -25,6 +72,30 @@ impl Uri {
         match self {
             Uri::Website(url) => Some(url.scheme().to_string()),
             Uri::Mail(_address) => None,
+            Uri::Git(_remote) => Some("ssh".to_string()),
+        }
+    }
+
+    /// The host of a website or git remote, or the domain of a mail address
+    pub fn domain(&self) -> Option<&str> {
+        match self {
+            Uri::Website(url) => url.host_str(),
+            Uri::Mail(address) => address.rsplit('@').next(),
+            Uri::Git(remote) => {
+                let host = match remote.find("://") {
+                    Some(idx) => remote[idx + 3..].split(&['/', ':'][..]).next()?,
+                    None => remote.split(':').next()?,
+                };
+                host.rsplit('@').next()
+            }
+        }
+    }
+
+    /// The repository of a git remote
+    pub fn git_remote(&self) -> Option<GitRemote> {
+        match self {
+            Uri::Git(remote) => GitRemote::parse(remote),
+            _ => None,
         }
     }
 
@@ -35,9 +106,71 @@ impl Uri {
                 url::Host::Ipv6(v6_addr) => Some(v6_addr.into()),
                 _ => None,
             },
-            Self::Mail(_) => None,
+            Self::Mail(_) | Self::Git(_) => None,
+        }
+    }
+}
+
+/// Characters allowed in the unquoted local part of a mail address
+/// besides letters and digits (RFC 5322 `atext`)
+const MAIL_LOCAL_SPECIALS: &str = "!#$%&'*+/=?^_`{|}~-";
+
+/// What's wrong with the syntax of a mail address, if anything.
+/// This is a pragmatic subset of RFC 5322: it catches malformed addresses
+/// (e.g. a missing `@` or invalid characters) without any network access.
+/// Header fields of a `mailto` link (`?subject=...`) are ignored.
+pub(crate) fn mail_syntax_error(address: &str) -> Option<String> {
+    let address = address.split('?').next().unwrap_or_default();
+    let (local, domain) = match address.rfind('@') {
+        Some(at) => (&address[..at], &address[at + 1..]),
+        None => return Some("missing `@`".to_string()),
+    };
+    if local.is_empty() {
+        return Some("missing local part before `@`".to_string());
+    }
+    if local.len() > 64 {
+        return Some("local part is longer than 64 characters".to_string());
+    }
+    let quoted = local.len() > 1 && local.starts_with('"') && local.ends_with('"');
+    if !quoted {
+        if let Some(c) = local
+            .chars()
+            .find(|c| !(c.is_alphanumeric() || *c == '.' || MAIL_LOCAL_SPECIALS.contains(*c)))
+        {
+            return Some(format!("invalid character `{}` in local part", c));
+        }
+        if local.starts_with('.') || local.ends_with('.') || local.contains("..") {
+            return Some("misplaced `.` in local part".to_string());
+        }
+    }
+    if domain.is_empty() {
+        return Some("missing domain after `@`".to_string());
+    }
+    // Domain literals like `[192.0.2.1]`
+    if let Some(literal) = domain.strip_prefix('[').and_then(|d| d.strip_suffix(']')) {
+        let literal = literal.trim_start_matches("IPv6:");
+        return match literal.parse::<IpAddr>() {
+            Ok(_) => None,
+            Err(_) => Some(format!("invalid address literal `{}`", domain)),
+        };
+    }
+    if domain.len() > 253 {
+        return Some("domain is longer than 253 characters".to_string());
+    }
+    if !domain.contains('.') {
+        return Some(format!("domain `{}` has no top-level domain", domain));
+    }
+    for label in domain.split('.') {
+        let valid = !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_alphanumeric() || c == '-');
+        if !valid {
+            return Some(format!("invalid domain `{}`", domain));
         }
     }
+    None
 }
 
 impl TryFrom<&str> for Uri {
@@ -47,6 +180,11 @@ impl TryFrom<&str> for Uri {
         // Remove the `mailto` scheme if it exists
         // to avoid parsing it as a website URL.
         let s = s.trim_start_matches("mailto:");
+        // Git remotes would otherwise be taken for a website with an
+        // unsupported scheme, or for a mail address
+        if GitRemote::parse(s).is_some() {
+            return Ok(Uri::Git(s.to_string()));
+        }
         if let Ok(uri) = Url::parse(s) {
             return Ok(Uri::Website(uri));
         };
@@ -87,6 +225,73 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_mail_syntax() {
+        for valid in &[
+            "mail@example.org",
+            "first.last+tag@sub.example.org",
+            "\"with spaces\"@example.org",
+            "user@[192.0.2.1]",
+            "user@[IPv6:2001:db8::1]",
+            "user@example.org?subject=Hello%20world",
+            "jörg@bücher.de",
+        ] {
+            assert_eq!(mail_syntax_error(valid), None, "{}", valid);
+        }
+        for invalid in &[
+            "example.org",
+            "@example.org",
+            "user@",
+            "user@localhost",
+            "user name@example.org",
+            ".user@example.org",
+            "first..last@example.org",
+            "user@-example.org",
+            "user@example..org",
+            "user@exa_mple.org",
+            "user@[not an ip]",
+        ] {
+            assert!(mail_syntax_error(invalid).is_some(), "{}", invalid);
+        }
+        assert_eq!(
+            mail_syntax_error("user(at)example.org"),
+            Some("missing `@`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_uri_git_remote() {
+        let remote = GitRemote {
+            host: "github.com".to_string(),
+            owner: "wgalyen".to_string(),
+            repo: "kimchi".to_string(),
+        };
+        for s in &[
+            "git@github.com:wgalyen/kimchi.git",
+            "git@github.com:wgalyen/kimchi",
+            "ssh://git@github.com/wgalyen/kimchi.git",
+            "git+ssh://git@github.com:22/wgalyen/kimchi.git",
+        ] {
+            let uri = Uri::try_from(*s).unwrap();
+            assert_eq!(uri, Uri::Git(s.to_string()));
+            assert_eq!(uri.domain(), Some("github.com"));
+            assert_eq!(uri.git_remote().as_ref(), Some(&remote), "{}", s);
+        }
+
+        let uri = Uri::try_from("git@gitlab.com:group/subgroup/project.git").unwrap();
+        assert_eq!(uri.git_remote().unwrap().owner, "group/subgroup");
+
+        // Not enough to tell the repository
+        assert!(matches!(
+            Uri::try_from("git@github.com:wgalyen"),
+            Ok(Uri::Mail(_))
+        ));
+        assert!(matches!(
+            Uri::try_from("ssh://example.com"),
+            Ok(Uri::Website(_))
+        ));
+    }
+
     #[test]
     fn test_uri_host_ip_v4() {
         let uri = website("http://127.0.0.1");
@@ -111,6 +316,18 @@ mod test {
         assert!(ip.is_none());
     }
 
+    #[test]
+    fn test_uri_domain() {
+        assert_eq!(
+            website("https://example.org/foo").domain(),
+            Some("example.org")
+        );
+        assert_eq!(
+            Uri::Mail("mail@example.org".to_string()).domain(),
+            Some("example.org")
+        );
+    }
+
     #[test]
     fn test_mail() {
         let uri = website("http://127.0.0.1");
@@ -3,6 +3,7 @@ mod cli {
     use anyhow::Result;
     use assert_cmd::Command;
     use kimchi::test_utils;
+    use predicates::prelude::*;
     use predicates::str::contains;
     use std::fs::{self, File};
     use std::io::Write;
@@ -69,6 +70,21 @@ mod cli {
             .code(2);
     }
 
+    #[tokio::test]
+    async fn test_summary_only() {
+        let mut cmd = main_command();
+        let mock_server = test_utils::get_mock_server(http::StatusCode::NOT_FOUND).await;
+
+        cmd.arg("--summary-only")
+            .arg("-")
+            .write_stdin(mock_server.uri())
+            .assert()
+            .failure()
+            .code(2)
+            .stdout(contains("Summary"))
+            .stdout(contains("[404 Not Found]").not());
+    }
+
     #[test]
     fn test_failure_github_404_no_token() {
         let mut cmd = main_command();
@@ -231,10 +247,174 @@ mod cli {
             .assert()
             .success();
 
-        let expected = r##"{"total":11,"successful":11,"failures":0,"timeouts":0,"redirects":0,"excludes":0,"errors":0,"fail_map":{}}"##;
-        let output = fs::read_to_string(&outfile)?;
-        assert_eq!(output.split_whitespace().collect::<String>(), expected);
+        let expected = r##"{"total":11,"successful":11,"failures":0,"timeouts":0,"redirects":0,"excludes":0,"errors":0,"auth_required":0,"fail_map":{}}"##;
+        let output: serde_json::Value = serde_json::from_str(&fs::read_to_string(&outfile)?)?;
+        assert_eq!(output["schema_version"], 1);
+        assert_eq!(
+            output["results"],
+            serde_json::from_str::<serde_json::Value>(expected)?
+        );
         fs::remove_file(outfile)?;
         Ok(())
     }
+
+    /// Test that the effective config gets printed without secrets
+    #[test]
+    fn test_print_config() -> Result<()> {
+        let mut cmd = main_command();
+        let dir = tempfile::tempdir()?;
+        let config_path = dir.path().join("kimchi.toml");
+        fs::write(
+            &config_path,
+            "exclude = [\"example.org\"]\nmax_redirects = 3\n",
+        )?;
+
+        cmd.arg("--config")
+            .arg(&config_path)
+            .arg("--github-token")
+            .arg("secret-token")
+            .arg("--print-config")
+            .assert()
+            .success()
+            .stdout(contains("max_redirects = 3"))
+            .stdout(contains(r#"exclude = ["example.org"]"#))
+            .stdout(contains(r#"github_token = "<redacted>""#))
+            .stdout(contains("secret-token").not());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_secret_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config_path = dir.path().join("kimchi.toml");
+        fs::write(dir.path().join("github_token"), "secret-token\n")?;
+        fs::write(&config_path, "github_token_file = \"github_token\"\n")?;
+
+        main_command()
+            .env_remove("GITHUB_TOKEN")
+            .arg("--config")
+            .arg(&config_path)
+            .arg("--print-config")
+            .assert()
+            .success()
+            .stdout(contains(r#"github_token = "<redacted>""#))
+            .stdout(contains("secret-token").not());
+
+        // Setting a secret inline and via file is ambiguous
+        fs::write(
+            &config_path,
+            "github_token = \"inline\"\ngithub_token_file = \"github_token\"\n",
+        )?;
+        main_command()
+            .arg("--config")
+            .arg(&config_path)
+            .arg("--print-config")
+            .assert()
+            .failure()
+            .stderr(contains(
+                "Both `github_token` and `github_token_file` are set",
+            ));
+
+        // A missing secret file is an error
+        fs::write(&config_path, "github_token_file = \"missing\"\n")?;
+        main_command()
+            .arg("--config")
+            .arg(&config_path)
+            .arg("--print-config")
+            .assert()
+            .failure()
+            .stderr(contains("Cannot read `github_token_file`"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_preflight() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file_path = dir.path().join("README.md");
+        fs::write(&file_path, "[Example](https://example.org)\n")?;
+
+        // Nothing listens on port 1
+        main_command()
+            .arg("--preflight")
+            .arg("--preflight-url")
+            .arg("http://127.0.0.1:1")
+            .arg("--max-total-retries")
+            .arg("0")
+            .arg(&file_path)
+            .assert()
+            .failure()
+            .code(3)
+            .stderr(contains("No connectivity"));
+
+        // An excluded URL never gets requested, so it proves nothing
+        main_command()
+            .arg(&file_path)
+            .arg("--preflight")
+            .arg("--preflight-url")
+            .arg("https://example.org/")
+            .arg("--exclude")
+            .arg("example\\.org")
+            .assert()
+            .failure()
+            .code(3)
+            .stderr(contains("No connectivity"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_strict_with_cache() -> Result<()> {
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).insert_header("etag", "\"v1\""))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let dir = tempfile::tempdir()?;
+        let cache_path = dir.path().join("cache.json");
+
+        // The second run gets a `304 Not Modified`, which mustn't count
+        // as a redirect under `--strict`
+        for _ in 0..2 {
+            main_command()
+                .arg("--strict")
+                .arg("--cache")
+                .arg(&cache_path)
+                .arg("-")
+                .write_stdin(mock_server.uri())
+                .assert()
+                .success()
+                .stdout(contains("Successful.......1"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bench_extract() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("a.md"), "[A](http://127.0.0.1:1/a)\n")?;
+        fs::write(dir.path().join("b.md"), "[B](http://127.0.0.1:1/b)\n")?;
+
+        // The links would fail if they got checked
+        let output = main_command()
+            .arg("--bench-extract")
+            .arg(dir.path().join("*.md"))
+            .output()?;
+        assert!(output.status.success());
+        let bench: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        assert_eq!(bench["inputs"], 2);
+        assert_eq!(bench["links"], 2);
+        assert!(bench["links_per_sec"].is_number());
+        Ok(())
+    }
 }